@@ -1,7 +1,34 @@
 fn main() {
   windows::build! {
+    Windows::Win32::Foundation::{CloseHandle, DuplicateHandle, DUPLICATE_HANDLE_OPTIONS},
     Windows::Win32::Graphics::Dxgi::*,
     Windows::Win32::Graphics::Direct3D11::*,
-    Windows::Win32::Media::MediaFoundation::*
+    Windows::Win32::Graphics::Gdi::{
+      BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject,
+      EnumDisplayDevicesW, EnumDisplaySettingsW, GetDC, GetDIBits, GetMonitorInfoW,
+      ReleaseDC, SelectObject, BITMAPINFO, BI_RGB, DIB_USAGE, DISPLAY_DEVICEW,
+      MONITORINFO, ROP_CODE
+    },
+    Windows::Win32::Media::MediaFoundation::*,
+    Windows::Win32::System::Com::{CoInitializeEx, COINIT},
+    Windows::Win32::System::DataExchange::{
+      CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData
+    },
+    Windows::Win32::System::Memory::{
+      GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GLOBAL_ALLOC_FLAGS
+    },
+    Windows::Win32::System::Performance::{
+      QueryPerformanceCounter, QueryPerformanceFrequency
+    },
+    Windows::Win32::System::Pipes::{
+      ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId
+    },
+    Windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT},
+    Windows::Win32::System::SystemServices::CLIPBOARD_FORMATS,
+    Windows::Win32::System::Threading::{
+      GetCurrentProcess, OpenProcess, SetThreadAffinityMask, PROCESS_ACCESS_RIGHTS
+    },
+    Windows::Win32::UI::HiDpi::{GetDpiForMonitor, MONITOR_DPI_TYPE},
+    Windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SYSTEM_METRICS_INDEX}
   };
 }
@@ -11,6 +11,7 @@ fn main() -> anyhow::Result<()> {
 
   // encode
   // ...
+  let mut buf = Vec::new();
 
   // time
   let mut time = Instant::now();
@@ -32,8 +33,10 @@ fn main() -> anyhow::Result<()> {
     match display.frame() {
       Err(FrameError::WouldBlock) => continue,
       Err(err) => panic!("{:?}", err),
+      // Pointer only updates have no new pixels so, they shouldn't count towards fps
+      Ok(frame) if frame.is_repeat() => continue,
       Ok(frame) => {
-        frame.as_bytes().unwrap();
+        frame.copy_into(&mut buf).unwrap();
 
         let elapsed = time.elapsed();
         let secs = elapsed.as_secs_f32();
@@ -0,0 +1,98 @@
+//! Provides a hook for supplying the buffers frames are copied into
+
+use std::sync::{Arc, RwLock};
+
+/// The allocator installed with [`set_allocator`], `None` for [`GlobalAllocator`]
+static ALLOCATOR: RwLock<Option<Arc<dyn FrameAllocator>>> = RwLock::new(None);
+
+/// Supplies the buffers pixel data is copied into
+///
+/// # Notes
+/// Used whenever a frame is copied into an [`crate::OwnedFrame`] or converted into a
+/// packed buffer (grayscale, rotation, and transforms), which lets applications route
+/// capture allocations through a pool or measure their footprint.
+pub trait FrameAllocator: Send + Sync {
+  /// Get an empty buffer able to hold at least `capacity` bytes
+  fn allocate(&self, capacity: usize) -> Vec<u8>;
+
+  /// Take back a buffer of a dropped [`crate::OwnedFrame`], e.g. to reuse it later
+  fn release(&self, buf: Vec<u8>) {
+    drop(buf);
+  }
+}
+
+/// Allocates buffers with the global allocator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlobalAllocator;
+
+impl FrameAllocator for GlobalAllocator {
+  fn allocate(&self, capacity: usize) -> Vec<u8> {
+    Vec::with_capacity(capacity)
+  }
+}
+
+/// Route every frame buffer allocation through `allocator`
+///
+/// # Notes
+/// Pass `None` to go back to [`GlobalAllocator`].  Buffers allocated before the change are
+/// released to the allocator installed when they are dropped.
+pub fn set_allocator(allocator: Option<Arc<dyn FrameAllocator>>) {
+  *ALLOCATOR.write().unwrap_or_else(|err| err.into_inner()) = allocator;
+}
+
+/// Get an empty buffer able to hold at least `capacity` bytes from the installed allocator
+pub(crate) fn allocate(capacity: usize) -> Vec<u8> {
+  match &*ALLOCATOR.read().unwrap_or_else(|err| err.into_inner()) {
+    Some(allocator) => allocator.allocate(capacity),
+    None => GlobalAllocator.allocate(capacity),
+  }
+}
+
+/// Return a buffer to the installed allocator
+pub(crate) fn release(buf: Vec<u8>) {
+  if let Some(allocator) = &*ALLOCATOR.read().unwrap_or_else(|err| err.into_inner()) {
+    allocator.release(buf);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{set_allocator, FrameAllocator};
+  use crate::{FrameFormat, OwnedFrame};
+  use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  };
+
+  #[derive(Default)]
+  struct CountingAllocator {
+    allocated: AtomicUsize,
+    released: AtomicUsize,
+  }
+
+  impl FrameAllocator for CountingAllocator {
+    fn allocate(&self, capacity: usize) -> Vec<u8> {
+      self.allocated.fetch_add(1, Ordering::SeqCst);
+      Vec::with_capacity(capacity)
+    }
+
+    fn release(&self, _: Vec<u8>) {
+      self.released.fetch_add(1, Ordering::SeqCst);
+    }
+  }
+
+  #[test]
+  fn test_captures_route_through_allocator() {
+    let counting = Arc::new(CountingAllocator::default());
+    let frame = OwnedFrame::new(2, 2, FrameFormat::Luma8, vec![1, 2, 3, 4]);
+
+    set_allocator(Some(counting.clone()));
+    let copy = OwnedFrame::from_frame(&&frame).unwrap();
+    drop(copy);
+    set_allocator(None);
+
+    // Other tests may copy frames while the allocator is installed
+    assert!(counting.allocated.load(Ordering::SeqCst) >= 1);
+    assert!(counting.released.load(Ordering::SeqCst) >= 1);
+  }
+}
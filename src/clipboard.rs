@@ -0,0 +1,166 @@
+//! Provides copying frames onto the Windows clipboard as device independent bitmaps
+
+use crate::{
+  convert::{self, Layout},
+  FrameFormat,
+};
+
+#[cfg(target_os = "windows")]
+use crate::bindings::Windows::Win32::{
+  Foundation::{HANDLE, HWND},
+  System::{
+    DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData},
+    Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+    SystemServices::CF_DIB,
+  },
+};
+
+/// The size of a `BITMAPINFOHEADER`
+pub const HEADER_LEN: usize = 40;
+
+/// Encode pixels as a `CF_DIB`, a `BITMAPINFOHEADER` followed by 24-bit pixels
+///
+/// # Arguments
+/// * `buf` - The pixels to encode
+/// * `layout` - The layout of `buf`
+///
+/// # Notes
+/// DIB pixels are stored as `BGR` with rows padded to four bytes and, since the height
+/// in the header is positive, the bottom row first.  Alpha is dropped since most
+/// applications pasting a `CF_DIB` ignore it or render it incorrectly.
+pub fn dib(buf: &[u8], layout: Layout) -> anyhow::Result<Vec<u8>> {
+  layout.check(buf)?;
+
+  let bpp = layout.format.bytes_per_pixel();
+  let stride = (layout.width * 3 + 3) & !3;
+  let image_len = stride * layout.height;
+
+  let mut out = Vec::with_capacity(HEADER_LEN + image_len);
+  out.extend_from_slice(&(HEADER_LEN as u32).to_le_bytes());
+  out.extend_from_slice(&(layout.width as i32).to_le_bytes());
+  out.extend_from_slice(&(layout.height as i32).to_le_bytes());
+  // A single plane of 24 bits per pixel
+  out.extend_from_slice(&1u16.to_le_bytes());
+  out.extend_from_slice(&24u16.to_le_bytes());
+  // `BI_RGB`, uncompressed
+  out.extend_from_slice(&0u32.to_le_bytes());
+  out.extend_from_slice(&(image_len as u32).to_le_bytes());
+  // Resolution and palette are left unspecified
+  out.extend_from_slice(&[0; 16]);
+
+  let rows: Vec<_> = layout.rows(buf).collect();
+  for row in rows.iter().rev() {
+    for px in row.chunks_exact(bpp) {
+      let bgr = match layout.format {
+        FrameFormat::B8G8R8A8 | FrameFormat::B8G8R8 => [px[0], px[1], px[2]],
+        FrameFormat::R8G8B8A8 | FrameFormat::R8G8B8 => [px[2], px[1], px[0]],
+        FrameFormat::Luma8 => [px[0]; 3],
+        // Keep the most significant byte of little endian samples
+        FrameFormat::Luma16 => [px[1]; 3],
+        FrameFormat::R10G10B10A2 => {
+          let [r, g, b, _] = convert::unpack_r10g10b10a2(px);
+          [b, g, r]
+        }
+        format @ FrameFormat::R16G16B16A16Float => {
+          anyhow::bail!("Cannot copy {:?} without tonemapping", format)
+        }
+      };
+
+      out.extend_from_slice(&bgr);
+    }
+
+    out.resize(out.len() + stride - layout.width * 3, 0);
+  }
+
+  Ok(out)
+}
+
+/// Replace the contents of the clipboard with pixels encoded as a `CF_DIB`
+///
+/// # Arguments
+/// * `buf` - The pixels to copy
+/// * `layout` - The layout of `buf`
+#[cfg(target_os = "windows")]
+pub fn copy(buf: &[u8], layout: Layout) -> anyhow::Result<()> {
+  let dib = dib(buf, layout)?;
+
+  unsafe {
+    let handle = GlobalAlloc(GMEM_MOVEABLE, dib.len());
+    anyhow::ensure!(handle != 0, "Failed to allocate {} bytes", dib.len());
+
+    let ptr = GlobalLock(handle) as *mut u8;
+    if ptr.is_null() {
+      GlobalFree(handle);
+      anyhow::bail!("Failed to lock clipboard memory");
+    }
+
+    std::ptr::copy_nonoverlapping(dib.as_ptr(), ptr, dib.len());
+    GlobalUnlock(handle);
+
+    if !OpenClipboard(HWND::default()).as_bool() {
+      GlobalFree(handle);
+      anyhow::bail!("Failed to open the clipboard");
+    }
+
+    EmptyClipboard();
+    let set = SetClipboardData(CF_DIB.0, HANDLE(handle));
+    CloseClipboard();
+
+    // The clipboard owns the memory once set, it is only ours to free on failure
+    if set.0 == 0 {
+      GlobalFree(handle);
+      anyhow::bail!("Failed to set clipboard data");
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{dib, HEADER_LEN};
+  use crate::{convert::Layout, FrameFormat};
+
+  #[test]
+  fn test_dib_bottom_up_bgr() {
+    // Two rows of a single RGBA pixel, each padded by 4 bytes
+    let buf = [1, 2, 3, 255, 0, 0, 0, 0, 4, 5, 6, 255, 0, 0, 0, 0];
+    let layout = Layout::new(1, 2, 8, FrameFormat::R8G8B8A8);
+
+    let out = dib(&buf, layout).unwrap();
+
+    assert_eq!(out.len(), HEADER_LEN + 2 * 4);
+    assert_eq!(&out[4..12], &[1, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(&out[HEADER_LEN..], &[6, 5, 4, 0, 3, 2, 1, 0]);
+  }
+
+  #[cfg(target_os = "windows")]
+  #[test]
+  fn test_copy_round_trip() {
+    use crate::bindings::Windows::Win32::Foundation::HWND;
+    use crate::bindings::Windows::Win32::System::{
+      DataExchange::{CloseClipboard, GetClipboardData, OpenClipboard},
+      Memory::{GlobalLock, GlobalUnlock},
+      SystemServices::CF_DIB,
+    };
+
+    let layout = Layout::packed(3, 2, FrameFormat::B8G8R8A8);
+    super::copy(&[128; 3 * 2 * 4], layout).unwrap();
+
+    unsafe {
+      assert!(OpenClipboard(HWND::default()).as_bool());
+      let handle = GetClipboardData(CF_DIB.0);
+      let ptr = GlobalLock(handle.0) as *const u8;
+      assert!(!ptr.is_null());
+
+      let header = std::slice::from_raw_parts(ptr, HEADER_LEN);
+      let width = i32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+      let height = i32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+
+      GlobalUnlock(handle.0);
+      CloseClipboard();
+
+      assert_eq!((width, height), (3, 2));
+    }
+  }
+}
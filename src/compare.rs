@@ -0,0 +1,458 @@
+//! Provides tiled pixel comparison between frame buffers
+
+use crate::{convert::Layout, DirtyRect, OwnedFrame};
+
+/// Controls how finely buffers are compared
+///
+/// # Notes
+/// Smaller blocks give finer deltas at the cost of more CPU time, larger blocks are faster
+/// but coarser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TileConfig {
+  /// The width and height of a tile in pixels
+  pub block: u32,
+}
+
+impl TileConfig {
+  pub fn new(block: u32) -> Self {
+    Self { block }
+  }
+}
+
+impl Default for TileConfig {
+  fn default() -> Self {
+    Self { block: 16 }
+  }
+}
+
+/// Compare two buffers sharing the same layout tile by tile
+///
+/// # Arguments
+/// * `a` - The first buffer
+/// * `b` - The second buffer
+/// * `layout` - The layout of both buffers
+/// * `config` - The size of the tiles compared
+///
+/// # Notes
+/// Returns a rectangle for every tile containing at least one differing byte, tiles on
+/// the right and bottom edges are clipped to the buffer bounds.
+pub fn changed_tiles(
+  a: &[u8],
+  b: &[u8],
+  layout: Layout,
+  config: TileConfig,
+) -> anyhow::Result<Vec<DirtyRect>> {
+  anyhow::ensure!(
+    config.block > 0,
+    "Tile block size must be greater than zero"
+  );
+
+  layout.check(a)?;
+  layout.check(b)?;
+
+  let block = config.block as usize;
+  let bpp = layout.format.bytes_per_pixel();
+  let mut changed = Vec::new();
+
+  for top in (0..layout.height).step_by(block) {
+    let bottom = (top + block).min(layout.height);
+
+    for left in (0..layout.width).step_by(block) {
+      let right = (left + block).min(layout.width);
+      let differs = (top..bottom).any(|y| {
+        let start = y * layout.stride + left * bpp;
+        let end = y * layout.stride + right * bpp;
+
+        a[start..end] != b[start..end]
+      });
+
+      if differs {
+        changed.push(DirtyRect::new(
+          top as i32,
+          right as i32,
+          bottom as i32,
+          left as i32,
+        ));
+      }
+    }
+  }
+
+  Ok(changed)
+}
+
+/// Find the 16x16 tiles that changed between two frames
+///
+/// # Arguments
+/// * `prev` - The earlier frame
+/// * `next` - The later frame
+///
+/// # Notes
+/// Pixels are compared directly so, this works for drivers that don't report dirty
+/// rectangles and regardless of how far apart the frames are.  Fails when the frames
+/// differ in size or format.
+pub fn diff(prev: &OwnedFrame, next: &OwnedFrame) -> anyhow::Result<Vec<DirtyRect>> {
+  diff_with(prev, next, TileConfig::default())
+}
+
+/// Find the `config` sized tiles that changed between two frames, see [`diff`]
+pub fn diff_with(
+  prev: &OwnedFrame,
+  next: &OwnedFrame,
+  config: TileConfig,
+) -> anyhow::Result<Vec<DirtyRect>> {
+  anyhow::ensure!(
+    (prev.width(), prev.height(), prev.format())
+      == (next.width(), next.height(), next.format()),
+    "Previous frame {}x{} {:?} doesn't match next frame {}x{} {:?}",
+    prev.width(),
+    prev.height(),
+    prev.format(),
+    next.width(),
+    next.height(),
+    next.format(),
+  );
+
+  let layout = Layout::packed(next.width(), next.height(), next.format());
+
+  changed_tiles(prev.data(), next.data(), layout, config)
+}
+
+/// Get the smallest rectangle covering every rectangle, clipped to the buffer bounds
+///
+/// # Arguments
+/// * `rects` - The rectangles to cover
+/// * `width` - The width of the buffer
+/// * `height` - The height of the buffer
+///
+/// # Notes
+/// Returns `None` when no rectangle overlaps the buffer.
+pub fn bounding_box<I>(rects: I, width: usize, height: usize) -> Option<DirtyRect>
+where
+  I: IntoIterator<Item = DirtyRect>,
+{
+  rects
+    .into_iter()
+    .map(|rect| {
+      DirtyRect::new(
+        rect.top.max(0),
+        rect.right.min(width as i32),
+        rect.bottom.min(height as i32),
+        rect.left.max(0),
+      )
+    })
+    .filter(|rect| rect.left < rect.right && rect.top < rect.bottom)
+    .reduce(|a, b| {
+      DirtyRect::new(
+        a.top.min(b.top),
+        a.right.max(b.right),
+        a.bottom.max(b.bottom),
+        a.left.min(b.left),
+      )
+    })
+}
+
+/// Clip rectangles to the buffer bounds, dropping empty ones and ones covered by another
+///
+/// # Arguments
+/// * `rects` - The rectangles to clip
+/// * `width` - The width of the buffer
+/// * `height` - The height of the buffer
+///
+/// # Notes
+/// Rectangles that only partially overlap are kept as is, so their shared pixels appear
+/// in both.  Larger rectangles come first.
+pub fn distinct_rects<I>(rects: I, width: usize, height: usize) -> Vec<DirtyRect>
+where
+  I: IntoIterator<Item = DirtyRect>,
+{
+  let mut clipped: Vec<_> = rects
+    .into_iter()
+    .filter_map(|rect| bounding_box(Some(rect), width, height))
+    .collect();
+
+  clipped.sort_by_key(|rect| std::cmp::Reverse(rect.area()));
+
+  let mut distinct: Vec<DirtyRect> = Vec::with_capacity(clipped.len());
+  for rect in clipped {
+    let covered = distinct.iter().any(|other| {
+      other.left <= rect.left
+        && other.top <= rect.top
+        && other.right >= rect.right
+        && other.bottom >= rect.bottom
+    });
+
+    if !covered {
+      distinct.push(rect);
+    }
+  }
+
+  distinct
+}
+
+/// Hash the pixels of a buffer, ignoring row padding
+///
+/// # Arguments
+/// * `buf` - The pixels to hash
+/// * `layout` - The layout of `buf`
+///
+/// # Notes
+/// FNV-1a over 8 byte words rather than single bytes so, hashing keeps up with capture.
+/// Not cryptographic but, changing any single word always changes the hash.  The size and
+/// bytes per pixel are hashed too so, differently shaped buffers don't collide.
+pub fn hash(buf: &[u8], layout: Layout) -> anyhow::Result<u64> {
+  const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+  const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+  layout.check(buf)?;
+
+  let mix = |hash: u64, word: u64| (hash ^ word).wrapping_mul(PRIME);
+  let shape = [layout.width, layout.height, layout.format.bytes_per_pixel()];
+  let mut hash = shape
+    .iter()
+    .fold(OFFSET, |hash, &value| mix(hash, value as u64));
+
+  for row in layout.rows(buf) {
+    let words = row.chunks_exact(8);
+    let rest = words.remainder();
+
+    for word in words {
+      let mut bytes = [0; 8];
+      bytes.copy_from_slice(word);
+      hash = mix(hash, u64::from_le_bytes(bytes));
+    }
+
+    hash = rest.iter().fold(hash, |hash, &byte| mix(hash, byte as u64));
+  }
+
+  Ok(hash)
+}
+
+/// Clip rectangles to the buffer bounds and merge overlapping or touching ones
+///
+/// # Arguments
+/// * `rects` - The rectangles to merge
+/// * `width` - The width of the buffer
+/// * `height` - The height of the buffer
+///
+/// # Notes
+/// Rectangles are merged into their bounding box until none overlap or share an edge so,
+/// the result may cover pixels that weren't in any rectangle.  Rectangles are ordered top
+/// to bottom, then left to right.
+pub fn merge_rects<I>(rects: I, width: usize, height: usize) -> Vec<DirtyRect>
+where
+  I: IntoIterator<Item = DirtyRect>,
+{
+  let touches = |a: &DirtyRect, b: &DirtyRect| {
+    a.left <= b.right && b.left <= a.right && a.top <= b.bottom && b.top <= a.bottom
+  };
+
+  let mut pending: Vec<_> = rects
+    .into_iter()
+    .filter_map(|rect| bounding_box(Some(rect), width, height))
+    .collect();
+  let mut merged: Vec<DirtyRect> = Vec::with_capacity(pending.len());
+
+  // A merged rectangle grows so, it's checked against the merged set again
+  while let Some(mut rect) = pending.pop() {
+    match merged.iter().position(|other| touches(&rect, other)) {
+      Some(index) => {
+        let other = merged.swap_remove(index);
+        rect = DirtyRect::new(
+          rect.top.min(other.top),
+          rect.right.max(other.right),
+          rect.bottom.max(other.bottom),
+          rect.left.min(other.left),
+        );
+        pending.push(rect);
+      }
+      None => merged.push(rect),
+    }
+  }
+
+  merged.sort();
+  merged
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    bounding_box, changed_tiles, diff, distinct_rects, hash, merge_rects, TileConfig,
+  };
+  use crate::{
+    convert::Layout,
+    transform::{Crop, FrameTransform},
+    DirtyRect, FrameFormat, OwnedFrame,
+  };
+
+  #[test]
+  fn test_changed_tiles_single_tile() {
+    let layout = Layout::packed(40, 20, FrameFormat::Luma8);
+    let baseline = vec![0; 40 * 20];
+    let mut frame = baseline.clone();
+
+    // Pixel (20, 3) falls in the second tile of the first row
+    frame[3 * 40 + 20] = 255;
+
+    let changed =
+      changed_tiles(&baseline, &frame, layout, TileConfig::default()).unwrap();
+    assert_eq!(changed, vec![DirtyRect::new(0, 32, 16, 16)]);
+  }
+
+  #[test]
+  fn test_changed_tiles_identical() {
+    let layout = Layout::packed(40, 20, FrameFormat::B8G8R8A8);
+    let buf = vec![7; 40 * 20 * 4];
+
+    assert!(changed_tiles(&buf, &buf, layout, TileConfig::default())
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn test_changed_tiles_clips_edges() {
+    let layout = Layout::packed(40, 20, FrameFormat::Luma8);
+    let baseline = vec![0; 40 * 20];
+    let mut frame = baseline.clone();
+    frame[19 * 40 + 39] = 1;
+
+    let changed =
+      changed_tiles(&baseline, &frame, layout, TileConfig::default()).unwrap();
+    assert_eq!(changed, vec![DirtyRect::new(16, 40, 20, 32)]);
+  }
+
+  #[test]
+  fn test_changed_tiles_block_sizes() {
+    let layout = Layout::packed(64, 64, FrameFormat::Luma8);
+    let baseline = vec![0; 64 * 64];
+    let mut frame = baseline.clone();
+    frame[40 * 64 + 40] = 1;
+
+    let coarse = changed_tiles(&baseline, &frame, layout, TileConfig::new(32)).unwrap();
+    assert_eq!(coarse, vec![DirtyRect::new(32, 64, 64, 32)]);
+
+    let fine = changed_tiles(&baseline, &frame, layout, TileConfig::new(8)).unwrap();
+    assert_eq!(fine, vec![DirtyRect::new(40, 48, 48, 40)]);
+
+    let identical = changed_tiles(&frame, &frame, layout, TileConfig::new(8)).unwrap();
+    assert!(identical.is_empty());
+    assert!(changed_tiles(&frame, &frame, layout, TileConfig::new(0)).is_err());
+  }
+
+  #[test]
+  fn test_bounding_box_crop() {
+    let frame = OwnedFrame::new(40, 20, FrameFormat::Luma8, vec![0; 40 * 20]);
+    let rects = vec![
+      DirtyRect::new(2, 8, 4, 5),
+      DirtyRect::new(6, 12, 9, 10),
+      // Empty and out of bounds rectangles are ignored
+      DirtyRect::new(3, 3, 3, 3),
+      DirtyRect::new(30, 50, 40, 45),
+    ];
+
+    let bounds = bounding_box(rects, 40, 20).unwrap();
+    assert_eq!(bounds, DirtyRect::new(2, 12, 9, 5));
+
+    let crop = Crop::new(
+      bounds.left as usize,
+      bounds.top as usize,
+      (bounds.right - bounds.left) as usize,
+      (bounds.bottom - bounds.top) as usize,
+    );
+    let out = crop.apply(&&frame).unwrap();
+    assert_eq!((out.width(), out.height()), (7, 7));
+
+    assert_eq!(bounding_box(Vec::new(), 40, 20), None);
+  }
+
+  #[test]
+  fn test_distinct_rects() {
+    let rects = vec![
+      DirtyRect::new(0, 2, 2, 0),
+      // Duplicate and contained rectangles are dropped
+      DirtyRect::new(0, 2, 2, 0),
+      DirtyRect::new(1, 2, 2, 1),
+      // Clipped to the buffer
+      DirtyRect::new(2, 10, 10, 2),
+      // Outside of the buffer
+      DirtyRect::new(5, 6, 6, 5),
+    ];
+
+    assert_eq!(
+      distinct_rects(rects, 4, 4),
+      vec![DirtyRect::new(0, 2, 2, 0), DirtyRect::new(2, 4, 4, 2)]
+    );
+  }
+
+  #[test]
+  fn test_merge_rects() {
+    let rects = vec![
+      // Overlapping
+      DirtyRect::new(0, 4, 4, 0),
+      DirtyRect::new(2, 6, 6, 2),
+      // Touches the right edge of the first pair
+      DirtyRect::new(0, 8, 2, 6),
+      // Apart from everything else
+      DirtyRect::new(10, 12, 12, 10),
+      // Clipped to the buffer
+      DirtyRect::new(14, 20, 20, 14),
+      // Outside of the buffer
+      DirtyRect::new(30, 40, 40, 30),
+    ];
+
+    assert_eq!(
+      merge_rects(rects, 16, 16),
+      vec![
+        DirtyRect::new(0, 8, 6, 0),
+        DirtyRect::new(10, 12, 12, 10),
+        DirtyRect::new(14, 16, 16, 14),
+      ]
+    );
+    assert!(merge_rects(Vec::new(), 16, 16).is_empty());
+  }
+
+  #[test]
+  fn test_hash() {
+    // 3x2 `B8G8R8A8` with 4 bytes of row padding
+    let mut buf = (0..32).collect::<Vec<u8>>();
+    let layout = Layout::new(3, 2, 16, FrameFormat::B8G8R8A8);
+    let base = hash(&buf, layout).unwrap();
+
+    // Padding is ignored
+    buf[12..16].copy_from_slice(&[0xff; 4]);
+    assert_eq!(hash(&buf, layout).unwrap(), base);
+
+    // Any pixel byte isn't
+    buf[27] ^= 1;
+    assert_ne!(hash(&buf, layout).unwrap(), base);
+    buf[27] ^= 1;
+
+    // Neither is the shape of the same bytes
+    let packed = Layout::packed(2, 3, FrameFormat::B8G8R8A8);
+    let packed_buf = (0..24).collect::<Vec<u8>>();
+    assert_ne!(
+      hash(&packed_buf, packed).unwrap(),
+      hash(&packed_buf, Layout::packed(3, 2, FrameFormat::B8G8R8A8)).unwrap()
+    );
+
+    assert!(hash(&buf[..20], layout).is_err());
+  }
+
+  #[test]
+  fn test_diff() {
+    let prev = OwnedFrame::new(20, 20, FrameFormat::B8G8R8A8, vec![0; 20 * 20 * 4]);
+    let mut data = prev.data().to_vec();
+
+    // Change the pixel at (17, 3), inside the clipped tile to the right of the first
+    data[(3 * 20 + 17) * 4] = 1;
+    let next = OwnedFrame::new(20, 20, FrameFormat::B8G8R8A8, data);
+
+    assert!(diff(&prev, &prev).unwrap().is_empty());
+    assert_eq!(
+      diff(&prev, &next).unwrap(),
+      vec![DirtyRect::new(0, 20, 16, 16)]
+    );
+
+    let other = OwnedFrame::new(20, 20, FrameFormat::R8G8B8A8, prev.data().to_vec());
+    assert!(diff(&prev, &other).is_err());
+  }
+}
@@ -0,0 +1,363 @@
+//! Provides options that control how frames are captured
+
+use crate::{
+  convert::Luma,
+  transform::{Crop, Filter},
+  FrameFormat,
+};
+use std::time::Duration;
+
+/// Capture options shared by display drivers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaptureConfig {
+  timing: bool,
+  timeout: Option<Duration>,
+  driver_type: DriverType,
+  grayscale: bool,
+  luma: Luma,
+  luma16: bool,
+  drop_policy: DropPolicy,
+  timestamp_overlay: bool,
+  sequence_overlay: bool,
+  cursor_history: usize,
+  cursor_shape: bool,
+  draw_cursor: bool,
+  skip_repeats: bool,
+  scale_filter: Filter,
+  apply_rotation: bool,
+  region: Option<Crop>,
+  output_size: Option<(usize, usize)>,
+  hdr: bool,
+}
+
+impl CaptureConfig {
+  /// Create [`CaptureConfig`] with default options
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record how long each stage of capturing a frame took
+  ///
+  /// # Notes
+  /// Disabled by default, when disabled the clock is never read.
+  pub fn with_timing(mut self, timing: bool) -> Self {
+    self.timing = timing;
+    self
+  }
+
+  /// Whether per frame timing is recorded
+  pub const fn timing(&self) -> bool {
+    self.timing
+  }
+
+  /// Override how long to wait for a new frame
+  ///
+  /// # Notes
+  /// When `None` (the default) the timeout is one refresh interval of the display so a
+  /// frame request blocks just long enough to catch the next update.
+  pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// The user supplied frame timeout, if any
+  pub const fn timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
+
+  /// Get how long to wait for a new frame on a display
+  ///
+  /// # Arguments
+  /// * `refresh_rate` - The refresh rate of the display in hz, if known
+  pub fn frame_timeout(&self, refresh_rate: Option<f32>) -> Duration {
+    // ~124fps to give windows a little time to prepare a frame for us when the refresh
+    // rate of the display is unknown
+    const FPS_124: Duration = Duration::from_millis(8);
+
+    match (self.timeout, refresh_rate) {
+      (Some(timeout), _) => timeout,
+      (None, Some(hz)) if hz > 0.0 => Duration::from_secs_f32(1.0 / hz),
+      _ => FPS_124,
+    }
+  }
+
+  /// Convert frames into single channel luminance while copying them
+  ///
+  /// # Notes
+  /// Grayscale frames are a quarter of the size of color frames which suits OCR and
+  /// computer-vision consumers.
+  pub fn with_grayscale(mut self, grayscale: bool) -> Self {
+    self.grayscale = grayscale;
+    self
+  }
+
+  /// Select the coefficients used when converting frames into grayscale
+  pub fn with_luma(mut self, luma: Luma) -> Self {
+    self.luma = luma;
+    self
+  }
+
+  /// Produce [`FrameFormat::Luma16`] rather than [`FrameFormat::Luma8`] grayscale frames
+  pub fn with_luma16(mut self, luma16: bool) -> Self {
+    self.luma16 = luma16;
+    self
+  }
+
+  /// The luma coefficients and output format when grayscale conversion is enabled
+  pub fn grayscale(&self) -> Option<(Luma, FrameFormat)> {
+    match (self.grayscale, self.luma16) {
+      (false, _) => None,
+      (true, false) => Some((self.luma, FrameFormat::Luma8)),
+      (true, true) => Some((self.luma, FrameFormat::Luma16)),
+    }
+  }
+
+  /// Select which kind of device frames are captured with
+  pub fn with_driver_type(mut self, driver_type: DriverType) -> Self {
+    self.driver_type = driver_type;
+    self
+  }
+
+  /// The kind of device frames are captured with
+  pub const fn driver_type(&self) -> DriverType {
+    self.driver_type
+  }
+
+  /// Select what happens to frames when a consumer of a streaming API falls behind
+  pub fn with_drop_policy(mut self, drop_policy: DropPolicy) -> Self {
+    self.drop_policy = drop_policy;
+    self
+  }
+
+  /// What happens to frames when a consumer falls behind
+  pub const fn drop_policy(&self) -> DropPolicy {
+    self.drop_policy
+  }
+
+  /// Burn the capture time into the top-left corner of every frame
+  ///
+  /// # Notes
+  /// Drawn with a built-in bitmap font once pixels are on the CPU, which helps diagnose
+  /// A/V sync and dropped frames when reviewing recordings.
+  pub fn with_timestamp_overlay(mut self, timestamp_overlay: bool) -> Self {
+    self.timestamp_overlay = timestamp_overlay;
+    self
+  }
+
+  /// Whether the capture time is burned into frames
+  pub const fn timestamp_overlay(&self) -> bool {
+    self.timestamp_overlay
+  }
+
+  /// Follow the timestamp overlay with the sequence number of the frame
+  pub fn with_sequence_overlay(mut self, sequence_overlay: bool) -> Self {
+    self.sequence_overlay = sequence_overlay;
+    self
+  }
+
+  /// Whether the sequence number follows the timestamp overlay
+  pub const fn sequence_overlay(&self) -> bool {
+    self.sequence_overlay
+  }
+
+  /// Remember up to `cursor_history` cursor positions seen while capturing
+  ///
+  /// # Notes
+  /// Disabled (zero) by default, see [`crate::cursor::CursorTracker`].
+  pub fn with_cursor_history(mut self, cursor_history: usize) -> Self {
+    self.cursor_history = cursor_history;
+    self
+  }
+
+  /// The number of cursor positions remembered, zero when disabled
+  pub const fn cursor_history(&self) -> usize {
+    self.cursor_history
+  }
+
+  /// Fetch the pointer bitmap whenever it changes
+  ///
+  /// # Notes
+  /// Disabled by default, the position and visibility of the cursor are always reported
+  /// by [`crate::Frame::cursor`] but, its shape costs an extra call per change.
+  pub fn with_cursor_shape(mut self, cursor_shape: bool) -> Self {
+    self.cursor_shape = cursor_shape;
+    self
+  }
+
+  /// Whether the pointer bitmap is fetched, either explicitly or to draw it
+  pub const fn cursor_shape(&self) -> bool {
+    self.cursor_shape || self.draw_cursor
+  }
+
+  /// Draw the cursor onto frames when pixels are copied
+  ///
+  /// # Notes
+  /// Disabled by default, desktop duplication captures the desktop without the cursor.
+  /// Enabling this also fetches the pointer bitmap, see
+  /// [`CaptureConfig::with_cursor_shape`].
+  pub fn with_draw_cursor(mut self, draw_cursor: bool) -> Self {
+    self.draw_cursor = draw_cursor;
+    self
+  }
+
+  /// Whether the cursor is drawn onto frames
+  pub const fn draw_cursor(&self) -> bool {
+    self.draw_cursor
+  }
+
+  /// Skip frames that only moved or changed the pointer
+  ///
+  /// # Notes
+  /// Disabled by default.  Skipped frames are released and acquiring continues until the
+  /// timeout runs out, only then is `WouldBlock` returned.  The pointer is still tracked so, [`crate::Frame::cursor`] stays
+  /// current on the next frame with new pixels.  Recorders can enable this to avoid
+  /// encoding identical frames.
+  pub fn with_skip_repeats(mut self, skip_repeats: bool) -> Self {
+    self.skip_repeats = skip_repeats;
+    self
+  }
+
+  /// Whether frames without new pixels are skipped
+  pub const fn skip_repeats(&self) -> bool {
+    self.skip_repeats
+  }
+
+  /// Select the filter used when frames are resized
+  ///
+  /// # Notes
+  /// Defaults to [`Filter::Nearest`], prefer [`Filter::Area`] or [`Filter::Lanczos3`]
+  /// for screenshots and thumbnails where quality matters more than speed.  Applies to
+  /// [`CaptureConfig::with_output_size`], where the GPU stands in for
+  /// [`Filter::Nearest`] and [`Filter::Bilinear`] with its linear filtering.  The other
+  /// filters resize on the CPU and only support 8 bit formats and `Luma16`.
+  pub fn with_scale_filter(mut self, scale_filter: Filter) -> Self {
+    self.scale_filter = scale_filter;
+    self
+  }
+
+  /// The filter used when frames are resized
+  pub const fn scale_filter(&self) -> Filter {
+    self.scale_filter
+  }
+
+  /// Rotate frames of rotated displays to match what is shown on screen
+  ///
+  /// # Notes
+  /// Disabled by default, DXGI always captures displays in their native orientation so
+  /// a portrait display is captured sideways.  When enabled frames report rotated
+  /// dimensions and rectangles, at the cost of copying every frame.
+  pub fn with_apply_rotation(mut self, apply_rotation: bool) -> Self {
+    self.apply_rotation = apply_rotation;
+    self
+  }
+
+  /// Whether frames of rotated displays are rotated to match the screen
+  pub const fn apply_rotation(&self) -> bool {
+    self.apply_rotation
+  }
+
+  /// Only capture a region of the display
+  ///
+  /// # Notes
+  /// The region is in the native orientation of the display and clamped to its bounds
+  /// when each frame is captured, frames report the size of the clamped region.  Only
+  /// the region is copied off the GPU or out of the desktop image.
+  pub fn with_region(mut self, region: Option<Crop>) -> Self {
+    self.region = region;
+    self
+  }
+
+  /// The region of the display frames are cropped to, if any
+  pub const fn region(&self) -> Option<Crop> {
+    self.region
+  }
+
+  /// Scale frames to a `(width, height)` on the GPU before they're copied off it
+  ///
+  /// # Notes
+  /// Scales the region when one is set, otherwise the whole display.  Scaled frames
+  /// report the scaled dimensions, rectangles and cursor position.  The cursor shape
+  /// itself is drawn unscaled.  Frames scaled on the GPU are `B8G8R8A8`, desktop images in
+  /// system memory and filters the GPU can't apply are scaled on the CPU when pixels are
+  /// read and keep their format, see [`CaptureConfig::with_scale_filter`].
+  pub fn with_output_size(mut self, output_size: Option<(usize, usize)>) -> Self {
+    self.output_size = output_size;
+    self
+  }
+
+  /// The size frames are scaled to, if any
+  pub const fn output_size(&self) -> Option<(usize, usize)> {
+    self.output_size
+  }
+
+  /// Capture HDR and wide color displays in their own format
+  ///
+  /// # Notes
+  /// Disabled by default, displays are always captured as `B8G8R8A8`.  When enabled
+  /// DXGI may hand out [`FrameFormat::R10G10B10A2`] or [`FrameFormat::R16G16B16A16Float`]
+  /// frames so, check the format of each frame.  Drawing the cursor and grayscale
+  /// conversion only support `B8G8R8A8` frames.
+  pub fn with_hdr(mut self, hdr: bool) -> Self {
+    self.hdr = hdr;
+    self
+  }
+
+  /// Whether displays are captured in their own format
+  pub const fn hdr(&self) -> bool {
+    self.hdr
+  }
+}
+
+/// The kind of device used to capture frames
+///
+/// # Notes
+/// Desktop duplication needs a device on the adapter driving the display so, the WARP
+/// software rasterizer only captures displays of Microsoft's adapters, e.g. the Basic
+/// Display Adapter of VMs and CI machines without a GPU driver.  Unless only hardware is
+/// allowed, WARP is also tried when the device on the adapter can't be created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DriverType {
+  /// Only use the hardware device of the display, failing for displays of Microsoft's
+  /// adapters
+  Hardware,
+  /// Use the hardware device of the display or WARP for displays of Microsoft's adapters
+  #[default]
+  HardwareOrSoftware,
+  /// Only use WARP, failing for displays driven by a GPU
+  Software,
+}
+
+/// What a streaming API does with new frames when its consumer can't keep up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DropPolicy {
+  /// Evict the oldest queued frame, keeping latency low (e.g. live preview)
+  #[default]
+  DropOldest,
+  /// Discard the new frame, keeping the frames already queued
+  DropNewest,
+  /// Wait for the consumer, trading latency for never losing a frame (e.g. recording)
+  Block,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::CaptureConfig;
+  use std::time::Duration;
+
+  #[test]
+  fn test_frame_timeout_auto() {
+    let config = CaptureConfig::new();
+    let timeout = config.frame_timeout(Some(144.0));
+
+    assert!(timeout > Duration::from_micros(6900));
+    assert!(timeout < Duration::from_micros(7000));
+    assert_eq!(config.frame_timeout(None), Duration::from_millis(8));
+  }
+
+  #[test]
+  fn test_frame_timeout_override() {
+    let config = CaptureConfig::new().with_timeout(Some(Duration::from_millis(50)));
+
+    assert_eq!(config.frame_timeout(Some(144.0)), Duration::from_millis(50));
+  }
+}
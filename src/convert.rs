@@ -0,0 +1,466 @@
+//! Provides pixel format conversions over captured frame buffers
+
+use crate::{alloc, DirtyRect, FrameFormat};
+
+/// Describes how pixels are laid out in a buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+  /// The width of the buffer in pixels
+  pub width: usize,
+  /// The height of the buffer in pixels
+  pub height: usize,
+  /// The number of bytes between the start of two rows, including any padding
+  pub stride: usize,
+  /// The pixel format of the buffer
+  pub format: FrameFormat,
+}
+
+impl Layout {
+  pub fn new(width: usize, height: usize, stride: usize, format: FrameFormat) -> Self {
+    Self {
+      width,
+      height,
+      stride,
+      format,
+    }
+  }
+
+  /// Create [`Layout`] for a buffer without row padding
+  pub fn packed(width: usize, height: usize, format: FrameFormat) -> Self {
+    Self::new(width, height, width * format.bytes_per_pixel(), format)
+  }
+
+  /// Create [`Layout`] for a buffer of `len` bytes holding `height` evenly padded rows
+  pub fn infer(width: usize, height: usize, format: FrameFormat, len: usize) -> Self {
+    match height {
+      0 => Self::packed(width, height, format),
+      height => Self::new(width, height, len / height, format),
+    }
+  }
+
+  /// The number of bytes of pixel data in a single row, excluding padding
+  pub fn row_len(&self) -> usize {
+    self.width * self.format.bytes_per_pixel()
+  }
+
+  /// Iterate over the pixel data of each row in `buf`, excluding padding
+  pub fn rows<'a>(&self, buf: &'a [u8]) -> impl Iterator<Item = &'a [u8]> {
+    let row_len = self.row_len();
+
+    buf
+      .chunks(self.stride.max(1))
+      .take(self.height)
+      .map(move |row| &row[..row_len])
+  }
+
+  /// Ensure `buf` is large enough to hold this layout
+  pub fn check(&self, buf: &[u8]) -> anyhow::Result<()> {
+    let required = match self.height {
+      0 => 0,
+      height => self.stride * (height - 1) + self.row_len(),
+    };
+
+    anyhow::ensure!(
+      self.stride >= self.row_len() && buf.len() >= required,
+      "Buffer of {} bytes is too small for {:?}",
+      buf.len(),
+      self
+    );
+
+    Ok(())
+  }
+}
+
+/// Coefficients used to compute luminance from RGB
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Luma {
+  /// ITU-R BT.601, used by standard definition video and most OCR tooling
+  #[default]
+  Rec601,
+  /// ITU-R BT.709, used by high definition video
+  Rec709,
+}
+
+impl Luma {
+  /// The red, green, and blue coefficients in 16 bit fixed point
+  const fn coefficients(&self) -> (u32, u32, u32) {
+    match self {
+      Self::Rec601 => (19595, 38470, 7471),
+      Self::Rec709 => (13933, 46871, 4732),
+    }
+  }
+
+  /// Weighted sum of the channels in 16 bit fixed point
+  fn weigh(&self, r: u8, g: u8, b: u8) -> u32 {
+    let (kr, kg, kb) = self.coefficients();
+    kr * r as u32 + kg * g as u32 + kb * b as u32
+  }
+
+  /// Compute 8 bit luminance
+  pub fn luma8(&self, r: u8, g: u8, b: u8) -> u8 {
+    ((self.weigh(r, g, b) + 0x8000) >> 16) as u8
+  }
+
+  /// Compute 16 bit luminance
+  pub fn luma16(&self, r: u8, g: u8, b: u8) -> u16 {
+    // Scale by 257 so 255 maps onto 65535
+    ((self.weigh(r, g, b) * 257 + 0x8000) >> 16) as u16
+  }
+}
+
+/// Convert a color buffer into tightly packed luminance
+///
+/// # Arguments
+/// * `buf` - The source pixels
+/// * `layout` - The layout of `buf`
+/// * `luma` - The coefficients used to weigh each channel
+/// * `format` - The output format, either [`FrameFormat::Luma8`] or
+///   [`FrameFormat::Luma16`] (stored little endian)
+pub fn to_luma(
+  buf: &[u8],
+  layout: Layout,
+  luma: Luma,
+  format: FrameFormat,
+) -> anyhow::Result<Vec<u8>> {
+  layout.check(buf)?;
+
+  let bpp = layout.format.bytes_per_pixel();
+  let (r, g, b) = match layout.format {
+    FrameFormat::B8G8R8A8 | FrameFormat::B8G8R8 => (2, 1, 0),
+    FrameFormat::R8G8B8A8 | FrameFormat::R8G8B8 => (0, 1, 2),
+    format => anyhow::bail!("Cannot compute luma from {:?}", format),
+  };
+
+  let mut out = alloc::allocate(layout.width * layout.height * format.bytes_per_pixel());
+
+  for row in layout.rows(buf) {
+    for px in row.chunks_exact(bpp) {
+      match format {
+        FrameFormat::Luma8 => out.push(luma.luma8(px[r], px[g], px[b])),
+        FrameFormat::Luma16 => {
+          out.extend_from_slice(&luma.luma16(px[r], px[g], px[b]).to_le_bytes())
+        }
+        format => anyhow::bail!("{:?} is not a luma format", format),
+      }
+    }
+  }
+
+  Ok(out)
+}
+
+/// Convert a buffer into tightly packed `R8G8B8A8`
+///
+/// # Arguments
+/// * `buf` - The source pixels
+/// * `layout` - The layout of `buf`
+///
+/// # Notes
+/// Formats without alpha are made opaque and luma is spread over every color channel.
+/// `R10G10B10A2` keeps the 8 most significant bits of each channel while
+/// `R16G16B16A16Float` is refused, it needs tonemapping first.
+pub fn to_rgba(buf: &[u8], layout: Layout) -> anyhow::Result<Vec<u8>> {
+  layout.check(buf)?;
+
+  let bpp = layout.format.bytes_per_pixel();
+  let mut out = alloc::allocate(
+    layout.width * layout.height * FrameFormat::R8G8B8A8.bytes_per_pixel(),
+  );
+
+  for row in layout.rows(buf) {
+    if layout.format == FrameFormat::R8G8B8A8 {
+      out.extend_from_slice(row);
+      continue;
+    }
+
+    for px in row.chunks_exact(bpp) {
+      let rgba = match layout.format {
+        FrameFormat::B8G8R8A8 => [px[2], px[1], px[0], px[3]],
+        FrameFormat::R8G8B8A8 => [px[0], px[1], px[2], px[3]],
+        FrameFormat::B8G8R8 => [px[2], px[1], px[0], 255],
+        FrameFormat::R8G8B8 => [px[0], px[1], px[2], 255],
+        FrameFormat::Luma8 => [px[0], px[0], px[0], 255],
+        // Keep the most significant byte of the little endian sample
+        FrameFormat::Luma16 => [px[1], px[1], px[1], 255],
+        FrameFormat::R10G10B10A2 => unpack_r10g10b10a2(px),
+        format @ FrameFormat::R16G16B16A16Float => {
+          anyhow::bail!("Cannot convert {:?} to RGBA without tonemapping", format)
+        }
+      };
+
+      out.extend_from_slice(&rgba);
+    }
+  }
+
+  Ok(out)
+}
+
+/// Convert a color buffer into NV12 using limited range ITU-R BT.709
+///
+/// # Arguments
+/// * `buf` - The source pixels
+/// * `layout` - The layout of `buf`
+///
+/// # Notes
+/// The output is the full resolution Y plane followed by the interleaved UV plane at half
+/// resolution, both tightly packed.  Each UV sample averages a 2x2 block of pixels, odd
+/// sized buffers round the UV plane up and average the pixels available.  Alpha is
+/// ignored.
+pub fn to_nv12(buf: &[u8], layout: Layout) -> anyhow::Result<Vec<u8>> {
+  layout.check(buf)?;
+
+  let rgb: fn(&[u8]) -> [i32; 3] = match layout.format {
+    FrameFormat::B8G8R8A8 | FrameFormat::B8G8R8 => {
+      |px| [px[2] as i32, px[1] as i32, px[0] as i32]
+    }
+    FrameFormat::R8G8B8A8 | FrameFormat::R8G8B8 => {
+      |px| [px[0] as i32, px[1] as i32, px[2] as i32]
+    }
+    FrameFormat::R10G10B10A2 => |px| {
+      let [r, g, b, _] = unpack_r10g10b10a2(px);
+      [r as i32, g as i32, b as i32]
+    },
+    format => anyhow::bail!("Cannot convert {:?} to NV12", format),
+  };
+
+  let bpp = layout.format.bytes_per_pixel();
+  let (chroma_width, chroma_height) =
+    (layout.width.div_ceil(2), layout.height.div_ceil(2));
+  let mut out =
+    alloc::allocate(layout.width * layout.height + chroma_width * chroma_height * 2);
+
+  // Coefficients in 8 bit fixed point, rounded so each chroma row sums to zero
+  for row in layout.rows(buf) {
+    for px in row.chunks_exact(bpp) {
+      let [r, g, b] = rgb(px);
+      out.push((((47 * r + 157 * g + 16 * b + 128) >> 8) + 16) as u8);
+    }
+  }
+
+  let rows: Vec<_> = layout.rows(buf).collect();
+  for pair in rows.chunks(2) {
+    for x in (0..layout.width).step_by(2) {
+      let mut sum = [0; 3];
+      let mut count = 0;
+
+      for row in pair {
+        for px in row[x * bpp..].chunks_exact(bpp).take(2) {
+          let [r, g, b] = rgb(px);
+          sum = [sum[0] + r, sum[1] + g, sum[2] + b];
+          count += 1;
+        }
+      }
+
+      let [r, g, b] = sum.map(|channel| (channel + count / 2) / count);
+      out.push((((-26 * r - 86 * g + 112 * b + 128) >> 8) + 128) as u8);
+      out.push((((112 * r - 102 * g - 10 * b + 128) >> 8) + 128) as u8);
+    }
+  }
+
+  Ok(out)
+}
+
+/// Reduce a `R10G10B10A2` pixel to `R8G8B8A8` by keeping the most significant bits
+pub(crate) fn unpack_r10g10b10a2(px: &[u8]) -> [u8; 4] {
+  let px = u32::from_le_bytes([px[0], px[1], px[2], px[3]]);
+
+  [
+    (px >> 2) as u8,
+    (px >> 12) as u8,
+    (px >> 22) as u8,
+    // Spread the 2 bit alpha over the whole byte
+    (px >> 30) as u8 * 0x55,
+  ]
+}
+
+/// How far a display is rotated clockwise from its native orientation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rotation {
+  #[default]
+  Identity,
+  Rotate90,
+  Rotate180,
+  Rotate270,
+}
+
+impl Rotation {
+  /// Whether rotating swaps the width and height of a buffer
+  pub const fn swaps_dimensions(&self) -> bool {
+    matches!(self, Self::Rotate90 | Self::Rotate270)
+  }
+
+  /// Get the width and height of a `width` by `height` buffer once rotated
+  pub const fn dimensions(&self, width: usize, height: usize) -> (usize, usize) {
+    match self.swaps_dimensions() {
+      true => (height, width),
+      false => (width, height),
+    }
+  }
+
+  /// Map a rectangle of a `width` by `height` buffer onto the rotated buffer
+  pub fn rect(&self, rect: DirtyRect, width: usize, height: usize) -> DirtyRect {
+    let (w, h) = (width as i32, height as i32);
+
+    match self {
+      Self::Identity => rect,
+      Self::Rotate90 => {
+        DirtyRect::new(rect.left, h - rect.top, rect.right, h - rect.bottom)
+      }
+      Self::Rotate180 => {
+        DirtyRect::new(h - rect.bottom, w - rect.left, h - rect.top, w - rect.right)
+      }
+      Self::Rotate270 => {
+        DirtyRect::new(w - rect.right, rect.bottom, w - rect.left, rect.top)
+      }
+    }
+  }
+}
+
+/// Rotate a buffer clockwise into a tightly packed buffer
+///
+/// # Arguments
+/// * `buf` - The source pixels
+/// * `layout` - The layout of `buf`
+/// * `rotation` - How far to rotate, see [`Rotation::dimensions`] for the resulting size
+pub fn rotate(buf: &[u8], layout: Layout, rotation: Rotation) -> anyhow::Result<Vec<u8>> {
+  layout.check(buf)?;
+
+  let bpp = layout.format.bytes_per_pixel();
+  let (width, height) = rotation.dimensions(layout.width, layout.height);
+  let mut out = alloc::allocate(width * height * bpp);
+
+  for y in 0..height {
+    for x in 0..width {
+      // Find the source pixel landing on `(x, y)`
+      let (sx, sy) = match rotation {
+        Rotation::Identity => (x, y),
+        Rotation::Rotate90 => (y, layout.height - 1 - x),
+        Rotation::Rotate180 => (layout.width - 1 - x, layout.height - 1 - y),
+        Rotation::Rotate270 => (layout.width - 1 - y, x),
+      };
+
+      let start = sy * layout.stride + sx * bpp;
+      out.extend_from_slice(&buf[start..start + bpp]);
+    }
+  }
+
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{rotate, to_luma, to_nv12, to_rgba, Layout, Luma, Rotation};
+  use crate::{DirtyRect, FrameFormat};
+
+  #[test]
+  fn test_luma_known_color() {
+    assert_eq!(Luma::Rec601.luma8(255, 0, 0), 76);
+    assert_eq!(Luma::Rec709.luma8(255, 0, 0), 54);
+    assert_eq!(Luma::Rec601.luma8(255, 255, 255), 255);
+    assert_eq!(Luma::Rec709.luma16(255, 255, 255), u16::MAX);
+  }
+
+  #[test]
+  fn test_to_luma_honors_stride() {
+    // 1x2 red `B8G8R8A8` pixels with 4 bytes of row padding
+    let buf = [0, 0, 255, 255, 9, 9, 9, 9, 0, 0, 255, 255, 9, 9, 9, 9];
+    let layout = Layout::new(1, 2, 8, FrameFormat::B8G8R8A8);
+
+    let luma8 = to_luma(&buf, layout, Luma::Rec601, FrameFormat::Luma8).unwrap();
+    assert_eq!(luma8, vec![76, 76]);
+
+    let luma16 = to_luma(&buf, layout, Luma::Rec601, FrameFormat::Luma16).unwrap();
+    assert_eq!(luma16.len(), 4);
+    assert_eq!(u16::from_le_bytes([luma16[0], luma16[1]]), 19595);
+  }
+
+  #[test]
+  fn test_to_rgba_honors_stride() {
+    // 2x2 `B8G8R8A8` pixels with 4 bytes of row padding
+    let buf = [
+      1, 2, 3, 4, 5, 6, 7, 8, 9, 9, 9, 9, //
+      10, 20, 30, 40, 50, 60, 70, 80, 9, 9, 9, 9,
+    ];
+    let layout = Layout::new(2, 2, 12, FrameFormat::B8G8R8A8);
+
+    assert_eq!(
+      to_rgba(&buf, layout).unwrap(),
+      vec![3, 2, 1, 4, 7, 6, 5, 8, 30, 20, 10, 40, 70, 60, 50, 80]
+    );
+
+    let layout = Layout::new(2, 2, 12, FrameFormat::R8G8B8A8);
+    assert_eq!(
+      to_rgba(&buf, layout).unwrap(),
+      vec![1, 2, 3, 4, 5, 6, 7, 8, 10, 20, 30, 40, 50, 60, 70, 80]
+    );
+
+    let layout = Layout::packed(2, 1, FrameFormat::B8G8R8);
+    assert_eq!(
+      to_rgba(&buf, layout).unwrap(),
+      vec![3, 2, 1, 255, 6, 5, 4, 255]
+    );
+  }
+
+  #[test]
+  fn test_to_nv12() {
+    // 3x2 `B8G8R8A8` pixels with 4 bytes of row padding, red, white and black on top
+    // of a white row
+    let buf = [
+      0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 0, 255, 9, 9, 9, 9, //
+      255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 9, 9, 9, 9,
+    ];
+    let layout = Layout::new(3, 2, 16, FrameFormat::B8G8R8A8);
+    let nv12 = to_nv12(&buf, layout).unwrap();
+
+    // Y plane then two UV pairs, the second covering the odd column alone
+    assert_eq!(nv12.len(), 3 * 2 + 2 * 2);
+    assert_eq!(nv12[..6], [63, 235, 16, 235, 235, 235]);
+    assert_eq!(nv12[8..], [128, 128]);
+
+    let red = to_nv12(&[255, 0, 0], Layout::packed(1, 1, FrameFormat::R8G8B8)).unwrap();
+    assert_eq!(red, vec![63, 102, 240]);
+  }
+
+  #[test]
+  fn test_to_rgba_hdr() {
+    // Red at 1023, green at 512, blue at 0 and opaque
+    let px = (1023u32 | 512 << 10 | 3 << 30).to_le_bytes();
+    let layout = Layout::packed(1, 1, FrameFormat::R10G10B10A2);
+
+    assert_eq!(to_rgba(&px, layout).unwrap(), vec![255, 128, 0, 255]);
+
+    let layout = Layout::packed(1, 1, FrameFormat::R16G16B16A16Float);
+    assert!(to_rgba(&[0; 8], layout).is_err());
+  }
+
+  #[test]
+  fn test_rotate_each_angle() {
+    // A 3x2 buffer with one byte of row padding
+    //   1 2 3
+    //   4 5 6
+    let buf = [1, 2, 3, 0, 4, 5, 6, 0];
+    let layout = Layout::new(3, 2, 4, FrameFormat::Luma8);
+    let rotated = |rotation| rotate(&buf, layout, rotation).unwrap();
+
+    assert_eq!(rotated(Rotation::Identity), vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(rotated(Rotation::Rotate90), vec![4, 1, 5, 2, 6, 3]);
+    assert_eq!(rotated(Rotation::Rotate180), vec![6, 5, 4, 3, 2, 1]);
+    assert_eq!(rotated(Rotation::Rotate270), vec![3, 6, 2, 5, 1, 4]);
+  }
+
+  #[test]
+  fn test_rotate_rect_matches_pixels() {
+    let layout = Layout::packed(3, 2, FrameFormat::Luma8);
+    // Only the pixel at (2, 0) is lit
+    let buf = [0, 0, 1, 0, 0, 0];
+    let rect = DirtyRect::new(0, 3, 1, 2);
+
+    for &rotation in &[Rotation::Rotate90, Rotation::Rotate180, Rotation::Rotate270] {
+      let out = rotate(&buf, layout, rotation).unwrap();
+      let (width, _) = rotation.dimensions(3, 2);
+      let lit = rotation.rect(rect, 3, 2);
+
+      assert_eq!((lit.right - lit.left, lit.bottom - lit.top), (1, 1));
+      assert_eq!(out[lit.top as usize * width + lit.left as usize], 1);
+    }
+  }
+}
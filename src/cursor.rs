@@ -0,0 +1,362 @@
+//! Provides the cursor seen while capturing and a history of its positions
+
+use crate::{convert::Layout, FrameFormat, MovedPoint};
+use std::{sync::Arc, time::Instant};
+
+/// Where the cursor was when a frame was captured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorInfo {
+  /// The top-left corner of the cursor shape in desktop coordinates of the display
+  pub position: MovedPoint,
+  /// Whether the cursor is shown
+  pub visible: bool,
+  /// The pointer bitmap, when requested by [`crate::CaptureConfig::with_cursor_shape`]
+  /// and reported by the driver
+  pub shape: Option<Arc<CursorShape>>,
+}
+
+/// How the pixels of a [`CursorShape`] combine with the pixels below them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CursorShapeKind {
+  /// A 1 bit AND mask followed by a 1 bit XOR mask, so the bitmap is twice as tall as
+  /// the cursor
+  Monochrome,
+  /// `B8G8R8A8` pixels blended by their alpha
+  Color,
+  /// `B8G8R8A8` pixels replacing the pixels below when alpha is zero and XOR'd with them
+  /// otherwise
+  MaskedColor,
+}
+
+/// The bitmap of a cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorShape {
+  /// How the bitmap combines with the pixels below it
+  pub kind: CursorShapeKind,
+  /// The width of the bitmap in pixels
+  pub width: usize,
+  /// The height of the bitmap in pixels, including both masks of
+  /// [`CursorShapeKind::Monochrome`] bitmaps
+  pub height: usize,
+  /// The number of bytes between the start of two rows of the bitmap
+  pub pitch: usize,
+  /// The point of the bitmap that clicks land on, relative to its top-left corner
+  pub hotspot: MovedPoint,
+  /// The bitmap pixels
+  pub data: Vec<u8>,
+}
+
+impl CursorShape {
+  /// The height of the cursor in pixels
+  pub const fn cursor_height(&self) -> usize {
+    match self.kind {
+      CursorShapeKind::Monochrome => self.height / 2,
+      _ => self.height,
+    }
+  }
+}
+
+/// Draw the shape of a visible cursor onto a `B8G8R8A8` buffer
+///
+/// # Arguments
+/// * `buf` - The pixels to draw on
+/// * `layout` - The layout of `buf`
+/// * `cursor` - The cursor to draw, the parts outside of `buf` are clipped
+///
+/// # Notes
+/// Nothing is drawn for hidden cursors or cursors without a shape.
+pub fn draw(buf: &mut [u8], layout: Layout, cursor: &CursorInfo) -> anyhow::Result<()> {
+  anyhow::ensure!(
+    layout.format == FrameFormat::B8G8R8A8,
+    "Cannot draw a cursor on {:?}",
+    layout.format
+  );
+  layout.check(buf)?;
+
+  let shape = match &cursor.shape {
+    Some(shape) if cursor.visible => shape,
+    _ => return Ok(()),
+  };
+
+  // Color shapes are `B8G8R8A8` like the buffer
+  let bpp = layout.format.bytes_per_pixel();
+  let required = match shape.kind {
+    CursorShapeKind::Monochrome => shape.width.div_ceil(8),
+    _ => shape.width * bpp,
+  };
+  anyhow::ensure!(
+    shape.pitch >= required && shape.data.len() >= shape.pitch * shape.height,
+    "Cursor bitmap of {} bytes is too small for {}x{}",
+    shape.data.len(),
+    shape.width,
+    shape.height
+  );
+
+  for y in 0..shape.cursor_height() {
+    let dst_y = cursor.position.y as isize + y as isize;
+    if dst_y < 0 || dst_y as usize >= layout.height {
+      continue;
+    }
+
+    for x in 0..shape.width {
+      let dst_x = cursor.position.x as isize + x as isize;
+      if dst_x < 0 || dst_x as usize >= layout.width {
+        continue;
+      }
+
+      let start = dst_y as usize * layout.stride + dst_x as usize * bpp;
+      let dst = &mut buf[start..start + 3];
+
+      match shape.kind {
+        CursorShapeKind::Monochrome => {
+          let bit =
+            |row: usize| shape.data[row * shape.pitch + x / 8] & (0x80 >> (x % 8));
+          let and = bit(y) != 0;
+          let xor = bit(y + shape.cursor_height()) != 0;
+
+          for channel in dst {
+            *channel = (if and { *channel } else { 0 }) ^ if xor { 0xff } else { 0 };
+          }
+        }
+        CursorShapeKind::Color => {
+          let src = &shape.data[y * shape.pitch + x * bpp..][..bpp];
+          let alpha = src[3] as u32;
+
+          for (channel, src) in dst.iter_mut().zip(src) {
+            let blended = *src as u32 * alpha + *channel as u32 * (255 - alpha);
+            *channel = ((blended + 127) / 255) as u8;
+          }
+        }
+        CursorShapeKind::MaskedColor => {
+          let src = &shape.data[y * shape.pitch + x * bpp..][..bpp];
+          let xor = src[3] != 0;
+
+          for (channel, src) in dst.iter_mut().zip(src) {
+            *channel = if xor { *channel ^ src } else { *src };
+          }
+        }
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// A bounded history of where the cursor was and when
+///
+/// # Notes
+/// Only movement is recorded, an update at the same position as the last one or while
+/// the cursor is hidden is ignored.  Once full the oldest positions are forgotten.
+#[derive(Debug, Clone)]
+pub struct CursorTracker {
+  history: Vec<(Instant, MovedPoint)>,
+  capacity: usize,
+}
+
+impl CursorTracker {
+  /// Create [`CursorTracker`] remembering at most `capacity` positions
+  pub fn new(capacity: usize) -> Self {
+    assert!(
+      capacity > 0,
+      "CursorTracker capacity must be greater than zero"
+    );
+
+    Self {
+      history: Vec::with_capacity(capacity),
+      capacity,
+    }
+  }
+
+  /// The maximum number of positions remembered
+  pub const fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Record a pointer update
+  ///
+  /// # Arguments
+  /// * `at` - When the update was observed
+  /// * `position` - Where the cursor is, in desktop coordinates of the display
+  /// * `visible` - Whether the cursor is shown
+  pub fn record(&mut self, at: Instant, position: MovedPoint, visible: bool) {
+    if !visible || self.latest().map(|(_, last)| last) == Some(position) {
+      return;
+    }
+
+    // Evict in batches rather than shifting the history on every update
+    if self.history.len() == self.capacity * 2 {
+      self.history.drain(..self.capacity);
+    }
+
+    self.history.push((at, position));
+  }
+
+  /// The recorded positions, oldest first
+  pub fn positions(&self) -> &[(Instant, MovedPoint)] {
+    let start = self.history.len().saturating_sub(self.capacity);
+    &self.history[start..]
+  }
+
+  /// The most recently recorded position
+  pub fn latest(&self) -> Option<(Instant, MovedPoint)> {
+    self.history.last().copied()
+  }
+
+  /// Forget every recorded position
+  pub fn clear(&mut self) {
+    self.history.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{draw, CursorInfo, CursorShape, CursorShapeKind, CursorTracker};
+  use crate::{convert::Layout, FrameFormat, MovedPoint};
+  use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+  };
+
+  fn cursor(
+    x: i32,
+    y: i32,
+    kind: CursorShapeKind,
+    pitch: usize,
+    data: Vec<u8>,
+  ) -> CursorInfo {
+    let height = data.len() / pitch;
+    let width = match kind {
+      CursorShapeKind::Monochrome => 2,
+      _ => pitch / 4,
+    };
+
+    CursorInfo {
+      position: MovedPoint::new(x, y),
+      visible: true,
+      shape: Some(Arc::new(CursorShape {
+        kind,
+        width,
+        height,
+        pitch,
+        hotspot: MovedPoint::new(0, 0),
+        data,
+      })),
+    }
+  }
+
+  #[test]
+  fn test_draw_color_clips() {
+    let layout = Layout::packed(2, 1, FrameFormat::B8G8R8A8);
+    let mut buf = vec![100; 8];
+
+    // Opaque red then half transparent white, hanging off the left edge by one pixel
+    let data = vec![0, 0, 255, 255, 0, 0, 255, 255, 255, 255, 255, 128];
+    draw(
+      &mut buf,
+      layout,
+      &cursor(-1, 0, CursorShapeKind::Color, 12, data),
+    )
+    .unwrap();
+
+    assert_eq!(buf, vec![0, 0, 255, 100, 178, 178, 178, 100]);
+  }
+
+  #[test]
+  fn test_draw_monochrome() {
+    let layout = Layout::packed(2, 1, FrameFormat::B8G8R8A8);
+    let mut buf = vec![10, 10, 10, 0, 10, 10, 10, 0];
+
+    // AND keeps the first pixel and clears the second, XOR then inverts the first
+    let data = vec![0b1000_0000, 0b1000_0000];
+    draw(
+      &mut buf,
+      layout,
+      &cursor(0, 0, CursorShapeKind::Monochrome, 1, data),
+    )
+    .unwrap();
+
+    assert_eq!(buf, vec![245, 245, 245, 0, 0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn test_draw_monochrome_truth_table() {
+    let layout = Layout::packed(4, 1, FrameFormat::B8G8R8A8);
+    let mut buf = vec![10; 16];
+
+    // AND 1 XOR 0 is transparent, 0 0 black, 0 1 white and 1 1 inverts
+    let mut pointer = cursor(
+      0,
+      0,
+      CursorShapeKind::Monochrome,
+      1,
+      vec![0b1001_0000, 0b0011_0000],
+    );
+    if let Some(shape) = &mut pointer.shape {
+      Arc::make_mut(shape).width = 4;
+    }
+    draw(&mut buf, layout, &pointer).unwrap();
+
+    assert_eq!(
+      buf,
+      vec![10, 10, 10, 10, 0, 0, 0, 10, 255, 255, 255, 10, 245, 245, 245, 10]
+    );
+  }
+
+  #[test]
+  fn test_draw_masked_color() {
+    let layout = Layout::packed(2, 1, FrameFormat::B8G8R8A8);
+    let mut buf = vec![0b1100, 0b1100, 0b1100, 0, 7, 7, 7, 0];
+
+    let data = vec![0b1010, 0b1010, 0b1010, 255, 1, 2, 3, 0];
+    let mut hidden = cursor(0, 0, CursorShapeKind::MaskedColor, 8, data.clone());
+    hidden.visible = false;
+    draw(&mut buf, layout, &hidden).unwrap();
+    assert_eq!(buf[4..7], [7, 7, 7]);
+
+    draw(
+      &mut buf,
+      layout,
+      &cursor(0, 0, CursorShapeKind::MaskedColor, 8, data),
+    )
+    .unwrap();
+    assert_eq!(buf, vec![0b0110, 0b0110, 0b0110, 0, 1, 2, 3, 0]);
+  }
+
+  #[test]
+  fn test_records_movement() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let mut tracker = CursorTracker::new(3);
+
+    tracker.record(at(0), MovedPoint::new(0, 0), true);
+    // A pointer-only update without movement, e.g. a click
+    tracker.record(at(1), MovedPoint::new(0, 0), true);
+    tracker.record(at(2), MovedPoint::new(5, 5), false);
+    tracker.record(at(3), MovedPoint::new(1, 2), true);
+
+    assert_eq!(
+      tracker.positions(),
+      &[
+        (at(0), MovedPoint::new(0, 0)),
+        (at(3), MovedPoint::new(1, 2))
+      ]
+    );
+  }
+
+  #[test]
+  fn test_history_is_bounded() {
+    let start = Instant::now();
+    let mut tracker = CursorTracker::new(3);
+
+    for x in 0..10 {
+      tracker.record(start, MovedPoint::new(x, 0), true);
+    }
+
+    let xs: Vec<_> = tracker
+      .positions()
+      .iter()
+      .map(|(_, point)| point.x)
+      .collect();
+    assert_eq!(xs, vec![7, 8, 9]);
+  }
+}
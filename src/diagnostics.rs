@@ -0,0 +1,206 @@
+//! Provides a summary of what the current environment is able to capture
+
+use crate::driver::{drivers, Driver};
+use std::fmt;
+
+/// Whether a display driver works
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverReport {
+  /// The name of the driver
+  pub name: &'static str,
+  /// Why the driver failed to open its primary display or capture from it
+  pub error: Option<String>,
+}
+
+/// Whether a display can be duplicated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayReport {
+  /// The GDI device name of the display
+  pub name: String,
+  /// The width of the display in pixels
+  pub width: usize,
+  /// The height of the display in pixels
+  pub height: usize,
+  /// Whether the display is the primary display
+  pub primary: bool,
+  /// Why duplication of the display failed
+  pub error: Option<String>,
+  /// Whether duplication was refused by access rights or policy
+  pub blocked: bool,
+}
+
+/// A summary of the capture capabilities of the current environment
+///
+/// # Notes
+/// Meant to be attached to bug reports, the [`fmt::Display`] implementation prints a
+/// readable summary.  Checks that don't apply to the platform are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+  /// Every driver available on the platform, in priority order
+  pub drivers: Vec<DriverReport>,
+  /// Whether the process runs in a Remote Desktop session
+  pub remote_session: Option<bool>,
+  /// Whether the Direct3D debug layer is installed, debug builds create capture devices
+  /// with it when it is
+  pub debug_layer: Option<bool>,
+  /// Whether the WARP software rasterizer, which captures displays of Microsoft's
+  /// adapters, is available
+  pub warp: Option<bool>,
+  /// Every display of the platform
+  pub displays: Vec<DisplayReport>,
+  /// Failures that prevented a check from running at all
+  pub errors: Vec<String>,
+}
+
+/// Probe the current environment, never failing or panicking
+pub fn diagnostics() -> Diagnostics {
+  diagnostics_with(&drivers())
+}
+
+/// Probe the current environment, checking `drivers` rather than every platform driver
+pub fn diagnostics_with(drivers: &[Driver]) -> Diagnostics {
+  let diagnostics = Diagnostics {
+    drivers: drivers
+      .iter()
+      .map(|driver| DriverReport {
+        name: driver.name,
+        error: driver.probe().err().map(|err| format!("{:#}", err)),
+      })
+      .collect(),
+    ..Diagnostics::default()
+  };
+
+  #[cfg(target_os = "windows")]
+  let diagnostics = dxgi(diagnostics);
+
+  diagnostics
+}
+
+/// Fill in the checks of the DXGI driver
+#[cfg(target_os = "windows")]
+fn dxgi(mut diagnostics: Diagnostics) -> Diagnostics {
+  use crate::driver::dxgi::{display::DxgiDisplays, probe};
+
+  diagnostics.remote_session = Some(probe::is_remote_session());
+  diagnostics.debug_layer = probe::debug_layer_installed();
+  diagnostics.warp = Some(probe::warp_available());
+
+  let displays = match DxgiDisplays::new() {
+    Ok(displays) => displays,
+    Err(err) => {
+      diagnostics
+        .errors
+        .push(format!("Failed to enumerate displays: {}", err));
+      return diagnostics;
+    }
+  };
+
+  for display in displays {
+    let display = match display {
+      Ok(display) => display,
+      Err(err) => {
+        diagnostics
+          .errors
+          .push(format!("Failed to open display: {}", err));
+        continue;
+      }
+    };
+
+    let duplication = probe::duplication(&display);
+
+    diagnostics.displays.push(DisplayReport {
+      name: display.name(),
+      width: display.width(),
+      height: display.height(),
+      primary: display.is_primary(),
+      blocked: duplication.as_ref().is_err_and(probe::is_blocked),
+      error: duplication.err().map(|err| err.to_string()),
+    });
+  }
+
+  diagnostics
+}
+
+impl fmt::Display for Diagnostics {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let check = |value: Option<bool>| match value {
+      Some(true) => "yes",
+      Some(false) => "no",
+      None => "n/a",
+    };
+
+    writeln!(f, "scraptor {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(f, "remote session: {}", check(self.remote_session))?;
+    writeln!(f, "debug layer: {}", check(self.debug_layer))?;
+    writeln!(f, "warp: {}", check(self.warp))?;
+
+    writeln!(f, "drivers:")?;
+    if self.drivers.is_empty() {
+      writeln!(f, "  none available")?;
+    }
+
+    for driver in &self.drivers {
+      match &driver.error {
+        None => writeln!(f, "  {}: ok", driver.name)?,
+        Some(err) => writeln!(f, "  {}: failed ({})", driver.name, err)?,
+      }
+    }
+
+    writeln!(f, "displays:")?;
+    for display in &self.displays {
+      write!(
+        f,
+        "  {} {}x{}{}: ",
+        display.name,
+        display.width,
+        display.height,
+        if display.primary { " primary" } else { "" }
+      )?;
+
+      match &display.error {
+        None => writeln!(f, "ok")?,
+        Some(err) if display.blocked => writeln!(f, "blocked ({})", err)?,
+        Some(err) => writeln!(f, "failed ({})", err)?,
+      }
+    }
+
+    for err in &self.errors {
+      writeln!(f, "error: {}", err)?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{diagnostics, diagnostics_with};
+  use crate::driver::Driver;
+
+  #[test]
+  fn test_diagnostics_completes() {
+    let diagnostics = diagnostics();
+    let summary = diagnostics.to_string();
+
+    assert!(summary.contains("drivers:"));
+    assert_eq!(diagnostics.drivers.len(), crate::driver::drivers().len());
+  }
+
+  #[test]
+  fn test_diagnostics_reports_driver_failure() {
+    let broken = Driver {
+      name: "broken",
+      open: || anyhow::bail!("access denied"),
+    };
+
+    let diagnostics = diagnostics_with(&[broken]);
+
+    assert_eq!(
+      diagnostics.drivers[0].error.as_deref(),
+      Some("access denied")
+    );
+    assert!(diagnostics
+      .to_string()
+      .contains("broken: failed (access denied)"));
+  }
+}
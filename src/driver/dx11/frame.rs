@@ -1,75 +1,168 @@
 use crate::bindings::Windows::Win32::Graphics::{
-  Direct3D11::{
-    ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, D3D11_CPU_ACCESS_READ,
-    D3D11_TEXTURE2D_DESC, D3D11_USAGE_STAGING,
-  },
-  Dxgi::{IDXGISurface, DXGI_MAPPED_RECT, DXGI_MAP_READ, DXGI_RESOURCE_PRIORITY_MAXIMUM},
+  Direct3D11::{ID3D11DeviceContext, ID3D11Texture2D, D3D11_BOX, D3D11_TEXTURE2D_DESC},
+  Dxgi::{IDXGISurface, DXGI_MAPPED_RECT, DXGI_MAP_READ},
 };
+use crate::{alloc, convert::Layout, transform::Crop, FrameFormat};
 use std::slice;
 use windows::Interface;
 
 #[derive(Debug, Clone)]
 pub struct Dx11FrameData<'frame> {
-  device: &'frame ID3D11Device,
   context: &'frame ID3D11DeviceContext,
   texture: ID3D11Texture2D,
+  staging: &'frame ID3D11Texture2D,
+  format: FrameFormat,
+  region: Option<Crop>,
 }
 
 impl<'frame> Dx11FrameData<'frame> {
+  /// Create [`Dx11FrameData`]
+  ///
+  /// # Arguments
+  /// * `context` - The immediate context of the device owning `texture`
+  /// * `texture` - The frame on the GPU
+  /// * `staging` - A CPU readable texture matching the description of `texture`, reused
+  ///   for every copy off the GPU
   pub fn new(
-    device: &'frame ID3D11Device,
     context: &'frame ID3D11DeviceContext,
     texture: ID3D11Texture2D,
+    staging: &'frame ID3D11Texture2D,
   ) -> Self {
     Self {
-      device,
       context,
       texture,
+      staging,
+      format: FrameFormat::B8G8R8A8,
+      region: None,
     }
   }
 
-  pub fn get_bytes(&self) -> anyhow::Result<&'frame [u8]> {
-    let mut rect = DXGI_MAPPED_RECT::default();
-    let mut desc = D3D11_TEXTURE2D_DESC::default();
-    let data = unsafe {
-      self.texture.GetDesc(&mut desc);
-      self.get_surface()?.Map(&mut rect, DXGI_MAP_READ).ok()?;
+  /// Describe the pixels of the texture as `format` rather than `B8G8R8A8`
+  pub fn with_format(mut self, format: FrameFormat) -> Self {
+    self.format = format;
+    self
+  }
 
-      let len = desc.Height as usize * rect.Pitch as usize;
-      let data = rect.pBits;
+  /// Only copy `region` of the texture off the GPU
+  ///
+  /// # Notes
+  /// `region` must lie within the texture.
+  pub fn with_region(mut self, region: Option<Crop>) -> Self {
+    self.region = region;
+    self
+  }
+
+  /// The texture holding the frame on the GPU
+  pub const fn texture(&self) -> &ID3D11Texture2D {
+    &self.texture
+  }
 
-      slice::from_raw_parts(data, len)
-    };
+  /// Copy the frame off the GPU
+  ///
+  /// # Notes
+  /// Allocates a new buffer on every call, see [`Dx11FrameData::copy_into`] to reuse one.
+  pub fn get_bytes(&self) -> anyhow::Result<Vec<u8>> {
+    let (width, height) = unsafe { self.dimensions() };
+    let len = width * height * self.format.bytes_per_pixel();
+    let mut data = alloc::allocate(len);
+    self.copy_into(&mut data)?;
 
     Ok(data)
   }
 
-  unsafe fn get_surface(&self) -> anyhow::Result<IDXGISurface> {
-    let mut texture_desc = D3D11_TEXTURE2D_DESC::default();
+  /// Copy the frame off the GPU into `buf`
+  ///
+  /// # Notes
+  /// The padding the staging texture adds to each row is dropped so, rows are tightly
+  /// packed.  `buf` is cleared first and only grows when the frame doesn't fit.
+  pub fn copy_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+    self.map(|bytes, layout| {
+      buf.clear();
+      buf.reserve(layout.row_len() * layout.height);
+      for row in layout.rows(bytes) {
+        buf.extend_from_slice(row);
+      }
+    })
+  }
 
-    self.texture.GetDesc(&mut texture_desc);
-    texture_desc.Usage = D3D11_USAGE_STAGING;
-    texture_desc.BindFlags = 0.into();
-    texture_desc.MiscFlags = 0.into();
-    texture_desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+  /// Copy the frame into the staging texture and pass its mapped pixels to `f`
+  ///
+  /// # Arguments
+  /// * `f` - Reads the pixels, which are only mapped until it returns
+  ///
+  /// # Notes
+  /// The pixels are padded to the pitch of the staging texture, described by
+  /// the [`Layout`] passed along.  When a region is set only it is copied, into the
+  /// top-left corner of the staging texture.
+  pub fn map<F, R>(&self, f: F) -> anyhow::Result<R>
+  where
+    F: FnOnce(&[u8], Layout) -> R,
+  {
+    let mut rect = DXGI_MAPPED_RECT::default();
 
-    let mut readable = None;
+    unsafe {
+      let (width, height) = self.dimensions();
+
+      match self.region {
+        None => self.context.CopyResource(self.staging, &self.texture),
+        Some(region) => {
+          let area = D3D11_BOX {
+            left: region.x as u32,
+            top: region.y as u32,
+            front: 0,
+            right: (region.x + region.width) as u32,
+            bottom: (region.y + region.height) as u32,
+            back: 1,
+          };
+
+          self.context.CopySubresourceRegion(
+            self.staging,
+            0,
+            0,
+            0,
+            0,
+            &self.texture,
+            0,
+            &area,
+          );
+        }
+      }
+
+      let surface: IDXGISurface = self.staging.cast()?;
+      surface.Map(&mut rect, DXGI_MAP_READ).ok()?;
+
+      // Unmaps even when `f` panics so, the staging texture can be mapped again
+      let _mapped = Mapped(&surface);
+
+      let layout = Layout::new(width, height, rect.Pitch as usize, self.format);
+      let len = layout.stride * layout.height.saturating_sub(1) + layout.row_len();
+
+      Ok(f(slice::from_raw_parts(rect.pBits, len), layout))
+    }
+  }
 
-    self
-      .device
-      .CreateTexture2D(&texture_desc, std::ptr::null(), &mut readable)
-      .ok()?;
+  /// The width and height of the pixels copied off the GPU
+  ///
+  /// # Safety
+  /// Calls to DirectX 11
+  unsafe fn dimensions(&self) -> (usize, usize) {
+    if let Some(region) = self.region {
+      return (region.width, region.height);
+    }
 
-    let readable = match readable {
-      Some(readable) => readable,
-      None => anyhow::bail!("Failed to create texture, texture is `None`"),
-    };
+    let mut desc = D3D11_TEXTURE2D_DESC::default();
+    self.staging.GetDesc(&mut desc);
 
-    readable.SetEvictionPriority(DXGI_RESOURCE_PRIORITY_MAXIMUM.0);
+    (desc.Width as usize, desc.Height as usize)
+  }
+}
 
-    self.context.CopyResource(&readable, &self.texture);
+/// Unmaps a surface when dropped
+struct Mapped<'a>(&'a IDXGISurface);
 
-    Ok(readable.cast()?)
+impl Drop for Mapped<'_> {
+  fn drop(&mut self) {
+    let _ = unsafe { self.0.Unmap() };
   }
 }
 
@@ -1,34 +1,88 @@
 //! Provides interface to capture desktop frames using Desktop Duplication API
 
-use super::{display::DxgiDisplay, errors::FrameError, frame::DxgiFrame};
+use super::{
+  display::{self, DxgiDisplay},
+  errors::FrameError,
+  frame::{DxgiFrame, DxgiFrameData},
+  processor::GpuConverter,
+  shared::create_staging,
+};
 use crate::{
   bindings::Windows::Win32::{
     Foundation::HINSTANCE,
     Graphics::{
       Direct3D11::{
-        D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, D3D11_CREATE_DEVICE_DEBUG,
-        D3D11_SDK_VERSION, D3D_DRIVER_TYPE_UNKNOWN, D3D_FEATURE_LEVEL_9_1,
+        D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D,
+        D3D11_CREATE_DEVICE_FLAG, D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC,
+        D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_UNKNOWN, D3D_DRIVER_TYPE_WARP,
+        D3D_FEATURE_LEVEL, D3D_FEATURE_LEVEL_9_1,
       },
       Dxgi::{
-        IDXGIOutputDuplication, DXGI_ERROR_WAIT_TIMEOUT, DXGI_MAPPED_RECT,
-        DXGI_OUTDUPL_DESC, DXGI_OUTDUPL_FRAME_INFO,
+        IDXGIAdapter, IDXGIAdapter1, IDXGIOutput1, IDXGIOutput5, IDXGIOutputDuplication,
+        DXGI_ADAPTER_DESC1, DXGI_ERROR_ACCESS_LOST, DXGI_ERROR_UNSUPPORTED,
+        DXGI_ERROR_WAIT_TIMEOUT, DXGI_FORMAT, DXGI_FORMAT_B8G8R8A8_UNORM,
+        DXGI_FORMAT_NV12, DXGI_FORMAT_R10G10B10A2_UNORM, DXGI_FORMAT_R16G16B16A16_FLOAT,
+        DXGI_MAPPED_RECT, DXGI_OUTDUPL_DESC, DXGI_OUTDUPL_FRAME_INFO,
+        DXGI_OUTDUPL_POINTER_SHAPE_INFO, DXGI_OUTDUPL_POINTER_SHAPE_TYPE,
+        DXGI_OUTDUPL_POINTER_SHAPE_TYPE_COLOR,
+        DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR,
+        DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME, DXGI_RESOURCE_PRIORITY_MAXIMUM,
       },
     },
+    System::SystemServices::LUID,
   },
+  convert::{Layout, Rotation},
+  cursor::{CursorInfo, CursorShape, CursorShapeKind, CursorTracker},
   driver::dx11::frame::Dx11FrameData,
+  overlay,
+  power::{PowerMonitor, PowerState},
+  transform::{Crop, Filter},
+  CaptureConfig, DriverType, FrameFormat, FrameTiming, MovedPoint,
+};
+use std::{
+  slice,
+  sync::Arc,
+  time::{Duration, Instant, SystemTime},
 };
-use std::{slice, time::Duration};
 use windows::Interface;
 
+#[cfg(debug_assertions)]
+use crate::bindings::Windows::Win32::Graphics::Direct3D11::D3D11_CREATE_DEVICE_DEBUG;
+
 /// Captures frames using windows Desktop Duplication API
 #[derive(Debug, Clone)]
 pub struct DxgiDisplayCapturer {
   rect: DXGI_MAPPED_RECT,
   desc: DXGI_OUTDUPL_DESC,
+  level: D3D_FEATURE_LEVEL,
+  luid: LUID,
   device: ID3D11Device,
   context: ID3D11DeviceContext,
-  duplication: IDXGIOutputDuplication,
-  has_frame: bool,
+  output: IDXGIOutput1,
+  duplication: Option<IDXGIOutputDuplication>,
+  staging: Option<ID3D11Texture2D>,
+  region_buf: Vec<u8>,
+  config: CaptureConfig,
+  is_warp: bool,
+  state: FrameState,
+  sequence: u64,
+  generation: u32,
+  cursor: Option<CursorTracker>,
+  pointer: Option<CursorInfo>,
+  power: PowerMonitor,
+  nv12: Option<GpuConverter>,
+  scaler: Option<GpuConverter>,
+}
+
+/// Tracks which ties to the previous frame must be released before acquiring another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameState {
+  /// No frame is held, either none was acquired yet or the last acquire failed
+  Released,
+  /// A frame was acquired but its surface isn't mapped
+  Acquired,
+  /// A frame was acquired and its surface is mapped into system memory
+  Mapped,
 }
 
 impl DxgiDisplayCapturer {
@@ -40,54 +94,158 @@ impl DxgiDisplayCapturer {
   /// # Safety
   /// Heavy use of unsafe calls to DirectX 11 and DXGI
   pub unsafe fn new(display: &DxgiDisplay) -> Result<Self, FrameError> {
-    let mut level = D3D_FEATURE_LEVEL_9_1;
-    let mut device = None;
-    let mut context = None;
-    let mut duplication = None;
-
-    // Create D3D11 device with debug support, an unknown driver type, and all feature
-    // levels
-    D3D11CreateDevice(
-      display.adapter.clone(),
-      D3D_DRIVER_TYPE_UNKNOWN,
-      HINSTANCE::NULL,
-      D3D11_CREATE_DEVICE_DEBUG,
-      std::ptr::null_mut(),
-      0,
-      D3D11_SDK_VERSION,
-      &mut device,
-      &mut level,
-      &mut context,
-    )
-    .ok()?;
-
-    // Ensure device and device context were in fact initialized although this shouldn't
-    // really happen
-    let device = device.ok_or(FrameError::None)?;
-    let context = context.ok_or(FrameError::None)?;
+    // `DuplicateOutput` only accepts a device on the adapter driving the output so, WARP
+    // is only picked up front for outputs of Microsoft's adapters, a device created on
+    // them already is WARP.  Otherwise WARP is the last resort when the device fails
+    let driver_type = display.config.driver_type();
+    let is_warp = is_warp_adapter(&display.adapter)?;
+    match (driver_type, is_warp) {
+      (DriverType::Hardware, true) | (DriverType::Software, false) => {
+        return Err(FrameError::DriverType(driver_type))
+      }
+      _ => {}
+    }
 
-    // Initialize output duplication API and ensure initialization didn't give us `None`
-    display
-      .output
-      .DuplicateOutput(device.clone(), &mut duplication)
-      .ok()?;
-    let duplication = duplication.ok_or(FrameError::None)?;
+    let hardware = create_device(Some(&display.adapter), D3D_DRIVER_TYPE_UNKNOWN);
+    let ((device, context, level), is_warp) =
+      device_or_warp(hardware, is_warp, driver_type)?;
 
-    // Get output duplication metadata for checking desktop bounds and if frames will be
-    // in memory or not
-    let mut desc = DXGI_OUTDUPL_DESC::default();
-    duplication.GetDesc(&mut desc);
+    let luid = adapter_luid(&display.adapter)?;
+    let (duplication, desc) =
+      duplicate_output(&display.output, &device, display.config.hdr())?;
 
     Ok(Self {
       rect: DXGI_MAPPED_RECT::default(),
       desc,
+      level,
+      luid,
       device,
       context,
-      duplication,
-      has_frame: false,
+      output: display.output.clone(),
+      duplication: Some(duplication),
+      staging: None,
+      region_buf: Vec::new(),
+      config: display.config.clone(),
+      is_warp,
+      state: FrameState::Released,
+      sequence: 0,
+      generation: display.generation,
+      cursor: match display.config.cursor_history() {
+        0 => None,
+        capacity => Some(CursorTracker::new(capacity)),
+      },
+      pointer: None,
+      power: PowerMonitor::default(),
+      nv12: None,
+      scaler: None,
     })
   }
 
+  /// The feature level of the device frames are captured with
+  pub const fn feature_level(&self) -> D3D_FEATURE_LEVEL {
+    self.level
+  }
+
+  /// The device frames are captured with
+  pub const fn device(&self) -> &ID3D11Device {
+    &self.device
+  }
+
+  /// The immediate context of the device frames are captured with
+  pub const fn context(&self) -> &ID3D11DeviceContext {
+    &self.context
+  }
+
+  /// The generation frames are captured in, bumped whenever the output is duplicated
+  /// again after access was lost
+  pub const fn generation(&self) -> u32 {
+    self.generation
+  }
+
+  /// The LUID of the adapter the capture device was created on
+  pub const fn adapter_luid(&self) -> LUID {
+    self.luid
+  }
+
+  /// The cursor positions seen while capturing, when enabled by
+  /// [`CaptureConfig::with_cursor_history`]
+  pub const fn cursor(&self) -> Option<&CursorTracker> {
+    self.cursor.as_ref()
+  }
+
+  /// Guess whether the display is powered from how recently it showed new content
+  ///
+  /// # Arguments
+  /// * `attached` - Whether the display is still part of the desktop
+  pub fn power_state(&self, attached: bool) -> PowerState {
+    self.power.state(Instant::now(), attached)
+  }
+
+  /// Draw the cursor onto frames when pixels are copied
+  ///
+  /// # Notes
+  /// Unlike re-creating the capturer this keeps duplication running.  DXGI only reports
+  /// the pointer bitmap when it changes so, a cursor is drawn starting from its next
+  /// change unless its bitmap was already fetched.
+  pub fn set_draw_cursor(&mut self, draw_cursor: bool) {
+    self.config = self.config.clone().with_draw_cursor(draw_cursor);
+  }
+
+  /// Only capture `region` of the display, or the whole display when `None`
+  ///
+  /// # Notes
+  /// Same as [`CaptureConfig::with_region`] but, keeps duplication running.
+  pub fn set_region(&mut self, region: Option<Crop>) {
+    self.config = self.config.clone().with_region(region);
+  }
+
+  /// Scale frames to `output_size` on the GPU, or keep them at their captured size when
+  /// `None`
+  ///
+  /// # Notes
+  /// Same as [`CaptureConfig::with_output_size`] but, keeps duplication running.
+  pub fn set_output_size(&mut self, output_size: Option<(usize, usize)>) {
+    self.config = self.config.clone().with_output_size(output_size);
+  }
+
+  /// Report frames that only changed the pointer as [`FrameError::WouldBlock`]
+  ///
+  /// # Notes
+  /// Same as [`CaptureConfig::with_skip_repeats`] but, keeps duplication running.
+  pub fn set_skip_repeats(&mut self, skip_repeats: bool) {
+    self.config = self.config.clone().with_skip_repeats(skip_repeats);
+  }
+
+  /// Whether frames are captured with the WARP software rasterizer rather than the
+  /// hardware device of the display, see [`DriverType`]
+  pub const fn is_warp(&self) -> bool {
+    self.is_warp
+  }
+
+  /// The refresh rate of the duplicated output in hz, if reported
+  pub fn refresh_rate(&self) -> Option<f32> {
+    let rate = self.desc.ModeDesc.RefreshRate;
+
+    match rate.Denominator {
+      0 => None,
+      denominator => Some(rate.Numerator as f32 / denominator as f32),
+    }
+  }
+
+  /// The pixel format of the desktop image
+  ///
+  /// # Notes
+  /// Always `B8G8R8A8` unless [`CaptureConfig::with_hdr`] is enabled and the display is
+  /// in HDR or wide color mode.
+  pub const fn format(&self) -> FrameFormat {
+    frame_format(self.desc.ModeDesc.Format)
+  }
+
+  /// How long [`DxgiDisplayCapturer::get_frame`] should wait for a new frame by default
+  pub fn frame_timeout(&self) -> Duration {
+    self.config.frame_timeout(self.refresh_rate())
+  }
+
   /// Read next from from DXGI
   ///
   /// # Arguments
@@ -103,69 +261,749 @@ impl DxgiDisplayCapturer {
     let mut frame = DXGI_OUTDUPL_FRAME_INFO::default();
     let mut resource = None;
 
-    // In order for `AcquireNextFrame` to work properly we need to manually release all
-    // ties to the previous frame.  In order to not do that twice if we receive a timeout
-    // error we assign and check `has_frame`
-    if self.has_frame {
-      // Release frame memory and ignore error
-      if self.desc.DesktopImageInSystemMemory.as_bool() {
-        let _ = self.duplication.UnMapDesktopSurface();
+    // Only read the clock when timing was requested so it costs nothing otherwise
+    let timed = self.config.timing();
+    let now = || if timed { Some(Instant::now()) } else { None };
+    let release_start = now();
+
+    // Pointer only updates are skipped by acquiring again so, the caller's timeout bounds
+    // the whole wait rather than a single acquire
+    let deadline = Instant::now() + timeout;
+    let acquire_start = loop {
+      // In order for `AcquireNextFrame` to work properly we need to manually release all
+      // ties to the previous frame.  Only undo what actually happened so a timed out or
+      // failed acquire never leads to releasing a frame that was never acquired
+      if let Some(duplication) = &self.duplication {
+        if self.state == FrameState::Mapped {
+          // Release frame memory and ignore error
+          let _ = duplication.UnMapDesktopSurface();
+        }
+
+        if self.state != FrameState::Released {
+          // Release frame and ignore error
+          let _ = duplication.ReleaseFrame();
+        }
       }
 
-      // Release frame and ignore error
-      let _ = self.duplication.ReleaseFrame();
+      self.state = FrameState::Released;
 
-      self.has_frame = false;
-    }
+      let acquire_start = now();
+      let timeout = deadline
+        .saturating_duration_since(Instant::now())
+        .as_millis() as u32;
+
+      // Get next frame.  Mode changes, full-screen transitions, UAC prompts and driver
+      // resets invalidate the duplication so, it's re-created and acquiring is retried once
+      let mut result =
+        self
+          .duplication()?
+          .AcquireNextFrame(timeout, &mut frame, &mut resource);
+
+      if result == DXGI_ERROR_ACCESS_LOST {
+        self.duplication = None;
+        result = self
+          .duplication()?
+          .AcquireNextFrame(timeout, &mut frame, &mut resource);
+      }
+
+      match result {
+        // If timeout expires before the next frame is ready return `WouldBlock` error
+        result if result.0 == DXGI_ERROR_WAIT_TIMEOUT.0 => {
+          return Err(FrameError::WouldBlock)
+        }
+        result if result == DXGI_ERROR_ACCESS_LOST => {
+          self.duplication = None;
+          result.ok().map_err(FrameError::AccessLost)?
+        }
+        result => result.ok()?,
+      };
 
-    // Get next frame
-    match self.duplication.AcquireNextFrame(
-      timeout.as_millis() as u32,
-      &mut frame,
-      &mut resource,
-    ) {
-      // If timeout expires before the next frame is ready return `WouldBlock` error
-      result if result.0 == DXGI_ERROR_WAIT_TIMEOUT.0 => {
-        return Err(FrameError::WouldBlock)
+      // Indicate a frame needs to be released before calling `AcquireNextFrame`
+      self.state = FrameState::Acquired;
+
+      // Either update time being non-zero means the display showed something new
+      if frame.LastPresentTime != 0 || frame.LastMouseUpdateTime != 0 {
+        self.power.active(Instant::now());
+      }
+
+      // A non-zero mouse update time means the pointer changed, possibly without the desktop
+      // changing at all
+      if frame.LastMouseUpdateTime != 0 {
+        let pointer = frame.PointerPosition;
+        let position = MovedPoint::new(pointer.Position.x, pointer.Position.y);
+        let visible = pointer.Visible.as_bool();
+
+        if let Some(cursor) = &mut self.cursor {
+          cursor.record(Instant::now(), position, visible);
+        }
+
+        // The shape is only reported when it changed, keep the last one otherwise.  A shape
+        // that can't be read shouldn't fail the whole frame
+        let last = self.pointer.take().and_then(|pointer| pointer.shape);
+        let shape = match frame.PointerShapeBufferSize {
+          len if len > 0 && self.config.cursor_shape() => {
+            self.pointer_shape(len).ok().map(Arc::new).or(last)
+          }
+          _ => last,
+        };
+
+        self.pointer = Some(CursorInfo {
+          position,
+          visible,
+          shape,
+        });
       }
-      result => result.ok()?,
+
+      // Pointer only updates carry no new pixels so, release them and keep waiting
+      if frame.LastPresentTime == 0 && self.config.skip_repeats() {
+        resource = None;
+        if Instant::now() >= deadline {
+          return Err(FrameError::WouldBlock);
+        }
+        continue;
+      }
+
+      break acquire_start;
     };
 
-    // Indicate a frame needs to be released before calling `AcquireNextFrame`
-    self.has_frame = true;
+    let sequence = self.sequence;
+    self.sequence += 1;
+
+    let overlay = if self.config.timestamp_overlay() {
+      let sequence = self.config.sequence_overlay().then_some(sequence);
+      Some(overlay::timestamp(SystemTime::now(), sequence))
+    } else {
+      None
+    };
+
+    let width = self.desc.ModeDesc.Width as usize;
+    let height = self.desc.ModeDesc.Height as usize;
+    let region = self
+      .config
+      .region()
+      .and_then(|region| region.clamp(width, height));
+    let output_size = self.config.output_size();
+    let filter = self.config.scale_filter();
+
+    // The video processor only filters linearly so, desktop images in system memory and
+    // the other filters are scaled on the CPU once pixels are read
+    let gpu_scale = match self.desc.DesktopImageInSystemMemory.as_bool() {
+      true => None,
+      false => output_size.filter(|_| is_gpu_filter(filter)),
+    };
+    let cpu_scale = match (gpu_scale, output_size) {
+      (None, Some(_)) => Some(filter),
+      _ => None,
+    };
+    let format = match gpu_scale {
+      Some(_) => FrameFormat::B8G8R8A8,
+      None => self.format(),
+    };
 
-    // Frame is already in system memory, map to `DXGI_MAPPED_RECT` and cast to slice
-    if self.desc.DesktopImageInSystemMemory.as_bool() {
-      // Map surface to [`DXGI_MAPPED_RECT`]
-      self.duplication.MapDesktopSurface(&mut self.rect).ok()?;
+    let map_start = now();
+    let data: DxgiFrameData = if self.desc.DesktopImageInSystemMemory.as_bool() {
+      // Frame is already in system memory, map to `DXGI_MAPPED_RECT` and cast to slice
+      let mut rect = DXGI_MAPPED_RECT::default();
+      self.duplication()?.MapDesktopSurface(&mut rect).ok()?;
+      self.rect = rect;
+      self.state = FrameState::Mapped;
 
       // Convert [`DXGI_MAPPED_RECT.pBits`] into [u8]
       let buf = self.rect.pBits;
-      let len = (self.desc.ModeDesc.Height * self.rect.Pitch as u32) as usize;
-      let buf = slice::from_raw_parts(buf, len);
+      let pitch = self.rect.Pitch as usize;
+      let desktop = slice::from_raw_parts(buf, height * pitch);
 
-      return Ok(DxgiFrame::new(buf, &self.duplication));
-    }
+      match region {
+        None => desktop.into(),
+        Some(region) => {
+          // Copy just the region into a buffer reused across frames
+          let layout = Layout::new(width, height, pitch, self.format());
+          let bpp = layout.format.bytes_per_pixel();
+          let columns = region.x * bpp..(region.x + region.width) * bpp;
+
+          self.region_buf.clear();
+          self.region_buf.reserve(region.width * region.height * bpp);
+          for row in layout.rows(desktop).skip(region.y).take(region.height) {
+            self.region_buf.extend_from_slice(&row[columns.clone()]);
+          }
+
+          self.region_buf.as_slice().into()
+        }
+      }
+    } else if let Some(resource) = resource {
+      // Convert frame [`IDXGIResource`] into [`ID3D11Texture2D`]
+      let texture: ID3D11Texture2D = resource.cast()?;
+
+      // Scaling happens before reading back so, only the scaled pixels are copied
+      let texture = match gpu_scale {
+        Some(output_size) => self.scale(&texture, region, output_size)?,
+        None => texture,
+      };
 
-    // Convert frame [`IDXGIResource`] into [`ID3D11Texture2D`]
-    if let Some(resource) = resource {
-      let device = &self.device;
-      let context = &self.context;
-      let texture = resource.cast()?;
-      let texture = Dx11FrameData::new(device, context, texture);
+      // The texture frames are read back through is reused until the desktop image
+      // changes size or format
+      let stale = match &self.staging {
+        Some(staging) => !same_shape(staging, &texture),
+        None => true,
+      };
 
-      Ok(DxgiFrame::new(texture, &self.duplication))
+      if stale {
+        let staging = create_staging(&self.device, &texture)?;
+        staging.SetEvictionPriority(DXGI_RESOURCE_PRIORITY_MAXIMUM.0);
+        self.staging = Some(staging);
+      }
+
+      let staging = self.staging.as_ref().ok_or(FrameError::None)?;
+
+      Dx11FrameData::new(&self.context, texture, staging)
+        .with_format(format)
+        .with_region(region.filter(|_| gpu_scale.is_none()))
+        .into()
     } else {
-      Err(FrameError::None)
+      return Err(FrameError::None);
+    };
+
+    let timing = match (release_start, acquire_start, map_start) {
+      (Some(release_start), Some(acquire_start), Some(map_start)) => Some(FrameTiming {
+        release: acquire_start - release_start,
+        acquire: map_start - acquire_start,
+        map: map_start.elapsed(),
+      }),
+      _ => None,
+    };
+
+    // Frames only cover the region, so the cursor is positioned relative to it
+    let source = match region {
+      Some(region) => (region.width, region.height),
+      None => (width, height),
+    };
+    let (width, height) = output_size.unwrap_or(source);
+    let cursor = self.pointer.clone().map(|mut cursor| {
+      if let Some(region) = region {
+        cursor.position.x -= region.x as i32;
+        cursor.position.y -= region.y as i32;
+      }
+
+      if output_size.is_some() {
+        cursor.position.x = scale(cursor.position.x, width, source.0);
+        cursor.position.y = scale(cursor.position.y, height, source.1);
+      }
+
+      cursor
+    });
+
+    let rotation = match self.config.apply_rotation() {
+      true => display::rotation(self.desc.Rotation),
+      false => Rotation::Identity,
+    };
+
+    // Acquiring succeeded so, the duplication is in place
+    let duplication = self.duplication.as_ref().ok_or(FrameError::None)?;
+
+    Ok(
+      DxgiFrame::new(data, width, height, duplication)
+        .with_format(format)
+        .with_timing(timing)
+        .with_grayscale(self.config.grayscale())
+        .with_overlay(overlay)
+        .with_rotation(rotation)
+        .with_repeat(frame.LastPresentTime == 0)
+        .with_present(frame.LastPresentTime, frame.AccumulatedFrames)
+        .with_sequence(sequence, self.generation)
+        .with_cursor(cursor, self.config.draw_cursor())
+        .with_region(region)
+        .with_scale(output_size.map(|_| source))
+        .with_scale_filter(cpu_scale),
+    )
+  }
+
+  /// Read the next frame from DXGI and convert it into NV12 without leaving the GPU
+  ///
+  /// # Arguments
+  /// * `timeout` - The amount of time that this method waits for a new frame before it
+  ///   returns to the caller
+  ///
+  /// # Notes
+  /// Returns a `DXGI_FORMAT_NV12` texture on [`DxgiDisplayCapturer::device`] holding
+  /// limited range BT.709, ready for a hardware encoder.  The texture is reused so, it's
+  /// overwritten by the next call.  The whole desktop image is converted as captured,
+  /// regions, rotation, overlays and the cursor aren't applied.  Fails with
+  /// [`FrameError::NotOnGpu`] when the desktop image is in system memory.
+  ///
+  /// # Safety
+  /// Heavy use of unsafe calls to DirectX 11 and DXGI
+  pub unsafe fn get_frame_nv12_texture(
+    &mut self,
+    timeout: Duration,
+  ) -> Result<ID3D11Texture2D, FrameError> {
+    let texture = match self.get_frame(timeout)?.data() {
+      DxgiFrameData::DirectX(data) => data.texture().clone(),
+      DxgiFrameData::Memory(_) => return Err(FrameError::NotOnGpu),
+    };
+
+    let size = texture_size(&texture);
+    converter(
+      &mut self.nv12,
+      &self.device,
+      &self.context,
+      size,
+      size,
+      DXGI_FORMAT_NV12,
+    )?
+    .convert(&texture, None)
+  }
+
+  /// Scale `texture`, or just `region` of it, to `output_size` on the GPU
+  ///
+  /// # Notes
+  /// Returns a `DXGI_FORMAT_B8G8R8A8_UNORM` texture that's overwritten by the next call.
+  /// The video processor filters linearly, see [`is_gpu_filter`].
+  ///
+  /// # Safety
+  /// Calls to DirectX 11
+  unsafe fn scale(
+    &mut self,
+    texture: &ID3D11Texture2D,
+    region: Option<Crop>,
+    output_size: (usize, usize),
+  ) -> Result<ID3D11Texture2D, FrameError> {
+    converter(
+      &mut self.scaler,
+      &self.device,
+      &self.context,
+      texture_size(texture),
+      (output_size.0 as u32, output_size.1 as u32),
+      DXGI_FORMAT_B8G8R8A8_UNORM,
+    )?
+    .convert(texture, region)
+  }
+
+  /// Get the duplication of the output, duplicating it again when access was lost
+  ///
+  /// # Notes
+  /// Returns [`FrameError::AccessLost`] while the output can't be duplicated, e.g. while
+  /// the secure desktop is shown, so a later call tries again.
+  ///
+  /// # Safety
+  /// Calls to DXGI
+  unsafe fn duplication(&mut self) -> Result<&IDXGIOutputDuplication, FrameError> {
+    if self.duplication.is_none() {
+      let (duplication, desc) =
+        duplicate_output(&self.output, &self.device, self.config.hdr()).map_err(
+          |err| match err {
+            FrameError::Unexpected(err) => FrameError::AccessLost(err),
+            err => err,
+          },
+        )?;
+
+      // The mode may have changed along with losing access so, frames start a new
+      // generation for consumers to notice the discontinuity
+      self.desc = desc;
+      self.pointer = None;
+      self.duplication = Some(duplication);
+      self.generation = self.generation.wrapping_add(1);
+      self.sequence = 0;
     }
+
+    self.duplication.as_ref().ok_or(FrameError::None)
+  }
+
+  /// Read the bitmap of the pointer reported with the current frame
+  ///
+  /// # Arguments
+  /// * `len` - The size of the bitmap reported by `AcquireNextFrame`
+  ///
+  /// # Safety
+  /// Calls to DXGI
+  unsafe fn pointer_shape(&mut self, len: u32) -> Result<CursorShape, FrameError> {
+    let mut data = vec![0u8; len as usize];
+    let mut required = 0;
+    let mut info = DXGI_OUTDUPL_POINTER_SHAPE_INFO::default();
+
+    self
+      .duplication()?
+      .GetFramePointerShape(len, data.as_mut_ptr() as _, &mut required, &mut info)
+      .ok()?;
+
+    let kind = match DXGI_OUTDUPL_POINTER_SHAPE_TYPE(info.Type as i32) {
+      DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MONOCHROME => CursorShapeKind::Monochrome,
+      DXGI_OUTDUPL_POINTER_SHAPE_TYPE_COLOR => CursorShapeKind::Color,
+      DXGI_OUTDUPL_POINTER_SHAPE_TYPE_MASKED_COLOR => CursorShapeKind::MaskedColor,
+      _ => return Err(FrameError::None),
+    };
+
+    data.truncate(required as usize);
+
+    Ok(CursorShape {
+      kind,
+      width: info.Width as usize,
+      height: info.Height as usize,
+      pitch: info.Pitch as usize,
+      hotspot: MovedPoint::new(info.HotSpot.x, info.HotSpot.y),
+      data,
+    })
+  }
+}
+
+/// Whether two textures have the same size and format
+///
+/// # Safety
+/// Calls to DirectX 11
+unsafe fn same_shape(a: &ID3D11Texture2D, b: &ID3D11Texture2D) -> bool {
+  let mut a_desc = D3D11_TEXTURE2D_DESC::default();
+  let mut b_desc = D3D11_TEXTURE2D_DESC::default();
+  a.GetDesc(&mut a_desc);
+  b.GetDesc(&mut b_desc);
+
+  (a_desc.Width, a_desc.Height, a_desc.Format)
+    == (b_desc.Width, b_desc.Height, b_desc.Format)
+}
+
+/// Get the width and height of `texture` in pixels
+///
+/// # Safety
+/// Calls to DirectX 11
+unsafe fn texture_size(texture: &ID3D11Texture2D) -> (u32, u32) {
+  let mut desc = D3D11_TEXTURE2D_DESC::default();
+  texture.GetDesc(&mut desc);
+
+  (desc.Width, desc.Height)
+}
+
+/// Get the converter in `slot`, recreating it when the sizes or format changed
+///
+/// # Safety
+/// Calls to DirectX 11
+unsafe fn converter<'a>(
+  slot: &'a mut Option<GpuConverter>,
+  device: &ID3D11Device,
+  context: &ID3D11DeviceContext,
+  input_size: (u32, u32),
+  output_size: (u32, u32),
+  format: DXGI_FORMAT,
+) -> Result<&'a GpuConverter, FrameError> {
+  let stale = match slot {
+    Some(converter) => !converter.fits(input_size, output_size, format),
+    None => true,
+  };
+
+  if stale {
+    *slot = Some(GpuConverter::new(
+      device,
+      context,
+      input_size,
+      output_size,
+      format,
+    )?);
+  }
+
+  slot.as_ref().ok_or(FrameError::None)
+}
+
+/// Whether the video processor can stand in for `filter` when scaling on the GPU
+///
+/// # Notes
+/// Its linear filtering is the closest hardware equivalent of [`Filter::Bilinear`] and
+/// at least as fast as [`Filter::Nearest`] so, both are scaled on the GPU.
+const fn is_gpu_filter(filter: Filter) -> bool {
+  matches!(filter, Filter::Nearest | Filter::Bilinear)
+}
+
+/// Scale `value` by `output / source`
+const fn scale(value: i32, output: usize, source: usize) -> i32 {
+  (value as i64 * output as i64 / source as i64) as i32
+}
+
+/// Fall back on a WARP device when the device on the adapter of a display can't be created
+///
+/// # Arguments
+/// * `hardware` - The result of creating the device on the adapter of the display
+/// * `is_warp` - Whether the adapter of the display is one of Microsoft's
+/// * `driver_type` - The kind of device allowed, [`DriverType::Hardware`] never falls back
+///
+/// # Notes
+/// Returns the device along with whether it's WARP.  The error creating the hardware
+/// device is kept when WARP fails too, `DuplicateOutput` still rejects a WARP device for
+/// outputs driven by a GPU.
+///
+/// # Safety
+/// Calls to DirectX 11
+unsafe fn device_or_warp(
+  hardware: Result<(ID3D11Device, ID3D11DeviceContext, D3D_FEATURE_LEVEL), FrameError>,
+  is_warp: bool,
+  driver_type: DriverType,
+) -> Result<((ID3D11Device, ID3D11DeviceContext, D3D_FEATURE_LEVEL), bool), FrameError> {
+  match hardware {
+    Ok(device) => Ok((device, is_warp)),
+    Err(err) if driver_type == DriverType::Hardware => Err(err),
+    Err(err) => create_device(None, D3D_DRIVER_TYPE_WARP)
+      .map(|device| (device, true))
+      .map_err(|_| err),
+  }
+}
+
+/// Create a D3D11 device and immediate context
+///
+/// # Arguments
+/// * `adapter` - The adapter to create the device on or `None` for the default adapter
+/// * `driver_type` - The driver backing the device, must be `D3D_DRIVER_TYPE_UNKNOWN` when
+///   an adapter is supplied
+///
+/// # Safety
+/// Calls to DirectX 11
+pub(super) unsafe fn create_device(
+  adapter: Option<&IDXGIAdapter1>,
+  driver_type: D3D_DRIVER_TYPE,
+) -> Result<(ID3D11Device, ID3D11DeviceContext, D3D_FEATURE_LEVEL), FrameError> {
+  // The debug layer is only installed with the Graphics Tools optional feature so, debug
+  // builds ask for it and retry without it when it's missing
+  #[cfg(debug_assertions)]
+  if let Ok(created) = create_device_with(adapter, driver_type, D3D11_CREATE_DEVICE_DEBUG)
+  {
+    return Ok(created);
+  }
+
+  create_device_with(adapter, driver_type, D3D11_CREATE_DEVICE_FLAG(0))
+}
+
+/// Create a D3D11 device and immediate context with `flags`
+///
+/// # Safety
+/// Calls to DirectX 11
+pub(super) unsafe fn create_device_with(
+  adapter: Option<&IDXGIAdapter1>,
+  driver_type: D3D_DRIVER_TYPE,
+  flags: D3D11_CREATE_DEVICE_FLAG,
+) -> Result<(ID3D11Device, ID3D11DeviceContext, D3D_FEATURE_LEVEL), FrameError> {
+  let mut level = D3D_FEATURE_LEVEL_9_1;
+  let mut device = None;
+  let mut context = None;
+
+  // Create D3D11 device with all feature levels
+  D3D11CreateDevice(
+    adapter.map(IDXGIAdapter::from),
+    driver_type,
+    HINSTANCE::NULL,
+    flags,
+    std::ptr::null_mut(),
+    0,
+    D3D11_SDK_VERSION,
+    &mut device,
+    &mut level,
+    &mut context,
+  )
+  .ok()?;
+
+  // Ensure device and device context were in fact initialized although this shouldn't
+  // really happen
+  let device = device.ok_or(FrameError::None)?;
+  let context = context.ok_or(FrameError::None)?;
+
+  Ok((device, context, level))
+}
+
+/// Duplicate an output with a device along with the duplication metadata, used for
+/// checking desktop bounds and if frames will be in memory or not
+///
+/// # Arguments
+/// * `output` - The output to duplicate
+/// * `device` - The device living on the adapter driving `output`
+/// * `hdr` - Let HDR and wide color displays be duplicated in their own format
+///
+/// # Notes
+/// HDR duplication needs `IDXGIOutput5` (Windows 10 1703) and a per monitor DPI aware
+/// process, otherwise the output is duplicated as `B8G8R8A8`.
+///
+/// # Safety
+/// Calls to DXGI
+unsafe fn duplicate_output(
+  output: &IDXGIOutput1,
+  device: &ID3D11Device,
+  hdr: bool,
+) -> Result<(IDXGIOutputDuplication, DXGI_OUTDUPL_DESC), FrameError> {
+  // DXGI picks the format closest to the desktop, so the 8 bit format is only used for
+  // SDR displays
+  const FORMATS: [DXGI_FORMAT; 3] = [
+    DXGI_FORMAT_R16G16B16A16_FLOAT,
+    DXGI_FORMAT_R10G10B10A2_UNORM,
+    DXGI_FORMAT_B8G8R8A8_UNORM,
+  ];
+
+  let mut duplication = None;
+  let output5 = match hdr {
+    true => output.cast::<IDXGIOutput5>().ok(),
+    false => None,
+  };
+
+  let result = match output5 {
+    Some(output5) => output5.DuplicateOutput1(
+      device.clone(),
+      0,
+      FORMATS.len() as u32,
+      FORMATS.as_ptr(),
+      &mut duplication,
+    ),
+    None => DXGI_ERROR_UNSUPPORTED,
+  };
+
+  if result == DXGI_ERROR_UNSUPPORTED {
+    output
+      .DuplicateOutput(device.clone(), &mut duplication)
+      .ok()?;
+  } else {
+    result.ok()?;
+  }
+
+  // Ensure initialization didn't give us `None`
+  let duplication = duplication.ok_or(FrameError::None)?;
+
+  let mut desc = DXGI_OUTDUPL_DESC::default();
+  duplication.GetDesc(&mut desc);
+
+  Ok((duplication, desc))
+}
+
+/// Translate the format of a desktop image into a [`FrameFormat`]
+///
+/// # Notes
+/// `DuplicateOutput` always produces `DXGI_FORMAT_B8G8R8A8_UNORM` and `DuplicateOutput1`
+/// only produces the formats it was given so, anything else is treated as `B8G8R8A8`.
+const fn frame_format(format: DXGI_FORMAT) -> FrameFormat {
+  match format {
+    DXGI_FORMAT_R16G16B16A16_FLOAT => FrameFormat::R16G16B16A16Float,
+    DXGI_FORMAT_R10G10B10A2_UNORM => FrameFormat::R10G10B10A2,
+    _ => FrameFormat::B8G8R8A8,
   }
 }
 
+/// Whether an adapter renders with WARP, i.e. it's one of Microsoft's such as the Basic
+/// Display Adapter of VMs and machines without a GPU driver
+///
+/// # Safety
+/// Calls to DXGI
+unsafe fn is_warp_adapter(adapter: &IDXGIAdapter1) -> Result<bool, FrameError> {
+  // PCI vendor ID of Microsoft
+  const MICROSOFT: u32 = 0x1414;
+
+  let mut desc = DXGI_ADAPTER_DESC1::default();
+  adapter.GetDesc1(&mut desc).ok()?;
+
+  Ok(desc.VendorId == MICROSOFT)
+}
+
+/// Get the LUID of an adapter
+///
+/// # Safety
+/// Calls to DXGI
+pub(super) unsafe fn adapter_luid(adapter: &IDXGIAdapter1) -> Result<LUID, FrameError> {
+  let mut desc = DXGI_ADAPTER_DESC1::default();
+  adapter.GetDesc1(&mut desc).ok()?;
+
+  Ok(desc.AdapterLuid)
+}
+
 #[cfg(test)]
 mod tests {
-  use super::DxgiDisplayCapturer;
-  use crate::driver::dxgi::{display::DxgiDisplays, errors::FrameError};
-  use std::time::Duration;
+  use super::{
+    adapter_luid, device_or_warp, frame_format, is_warp_adapter, DxgiDisplayCapturer,
+  };
+  use crate::{
+    bindings::Windows::Win32::Graphics::{
+      Direct3D11::D3D11_TEXTURE2D_DESC,
+      Dxgi::{
+        IDXGIDevice, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_NV12,
+        DXGI_FORMAT_R16G16B16A16_FLOAT,
+      },
+    },
+    driver::dxgi::{display::DxgiDisplays, errors::FrameError, frame::DxgiFrameData},
+    transform::{Crop, Filter},
+    CaptureConfig, DriverType, Frame, FrameFormat,
+  };
+  use std::{borrow::Cow, time::Duration};
+  use windows::Interface;
+
+  fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0u64, |sum, &byte| {
+      sum.wrapping_mul(31).wrapping_add(byte as u64)
+    })
+  }
+
+  #[test]
+  fn test_frame_format() {
+    assert_eq!(
+      frame_format(DXGI_FORMAT_R16G16B16A16_FLOAT),
+      FrameFormat::R16G16B16A16Float
+    );
+    assert_eq!(
+      frame_format(DXGI_FORMAT_B8G8R8A8_UNORM),
+      FrameFormat::B8G8R8A8
+    );
+  }
+
+  #[test]
+  fn test_get_frame_nv12_texture() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut frames = 0;
+
+      for _ in 0..10 {
+        let texture = match capturer.get_frame_nv12_texture(Duration::from_millis(16)) {
+          Ok(texture) => texture,
+          Err(FrameError::WouldBlock) => continue,
+          // Desktop images in system memory still count as acquired
+          Err(FrameError::NotOnGpu) => {
+            frames += 1;
+            continue;
+          }
+          Err(err) => panic!("{:?}", err),
+        };
+
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        texture.GetDesc(&mut desc);
+
+        assert_eq!(desc.Format, DXGI_FORMAT_NV12);
+        assert_eq!(desc.Width % 2, 0);
+        frames += 1;
+      }
+
+      assert!(frames > 0);
+    }
+  }
+
+  #[test]
+  fn test_get_frame_hdr() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let mut display = displays.next().unwrap().unwrap();
+      display.set_config(CaptureConfig::new().with_hdr(true));
+
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let format = capturer.format();
+      let mut buf = Vec::new();
+      let mut frames = 0;
+
+      for _ in 0..10 {
+        let frame = match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => frame,
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        };
+
+        frame.copy_into(&mut buf).unwrap();
+
+        assert_eq!(frame.format(), format);
+        assert_eq!(
+          buf.len(),
+          frame.width() * frame.height() * format.bytes_per_pixel()
+        );
+        frames += 1;
+      }
+
+      assert!(frames > 0);
+    }
+  }
 
   #[test]
   fn test_get_frame() {
@@ -173,6 +1011,7 @@ mod tests {
       let mut displays = DxgiDisplays::new().unwrap();
       let display = displays.next().unwrap().unwrap();
       let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut frames = 0;
 
       for _ in 0..10 {
         let frame = capturer.get_frame(Duration::from_millis(16));
@@ -183,11 +1022,527 @@ mod tests {
         };
 
         let frame_buf = frame.as_bytes().unwrap();
-        let _ = frame.dirty();
-        let _ = frame.moved();
+        frame.dirty().unwrap();
+        frame.moved().unwrap();
 
         assert!(frame_buf.len() > 0);
+        frames += 1;
+      }
+
+      assert!(frames > 0);
+    }
+  }
+
+  #[test]
+  fn test_bytes_outlive_frame() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut kept = Vec::new();
+
+      for _ in 0..20 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => {
+            // Only a mapped desktop image is borrowed, textures are always copied
+            let borrowed = matches!(frame.as_bytes().unwrap(), Cow::Borrowed(_));
+            assert_eq!(borrowed, matches!(frame.data(), DxgiFrameData::Memory(_)));
+
+            let sum = checksum(&frame.as_bytes().unwrap());
+            kept.push((sum, frame.to_vec().unwrap()));
+          }
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      // Every buffer still holds the pixels of its frame after later frames were acquired
+      // and released
+      assert!(!kept.is_empty());
+      for (sum, bytes) in &kept {
+        assert!(!bytes.is_empty());
+        assert_eq!(checksum(bytes), *sum);
+      }
+    }
+  }
+
+  #[test]
+  fn test_with_bytes_matches_copy() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => {
+            let mut packed = Vec::new();
+            frame.copy_into(&mut packed).unwrap();
+
+            let rows: Vec<u8> = frame
+              .with_bytes(|bytes, layout| layout.rows(bytes).flatten().copied().collect())
+              .unwrap();
+
+            return assert_eq!(packed, rows);
+          }
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      panic!("no frame was acquired");
+    }
+  }
+
+  #[test]
+  fn test_dirty_crop_of_first_frame() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => {
+            let mut packed = Vec::new();
+            frame.copy_into(&mut packed).unwrap();
+
+            // The first frame has nothing to compare against so, it's cropped whole
+            let crop = frame.dirty_crop().unwrap().unwrap();
+            assert_eq!(
+              (crop.width(), crop.height()),
+              (frame.width(), frame.height())
+            );
+            return assert_eq!(crop.data(), &packed[..]);
+          }
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      panic!("no frame was acquired");
+    }
+  }
+
+  #[test]
+  fn test_owned_frame_outlives_capture() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut kept = Vec::new();
+
+      for _ in 0..20 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => {
+            let (width, height, dirty) =
+              (frame.width(), frame.height(), frame.dirty().unwrap());
+            let owned = frame.into_owned().unwrap();
+
+            assert_eq!((owned.width(), owned.height()), (width, height));
+            assert_eq!((&owned).dirty().unwrap(), dirty);
+            kept.push(owned);
+          }
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      assert!(!kept.is_empty());
+      for frame in &kept {
+        assert_eq!(frame.data().len(), frame.stride() * frame.height());
+      }
+    }
+  }
+
+  #[test]
+  fn test_get_frame_repeat() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut frames = 0;
+
+      for _ in 0..20 {
+        let frame = match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => frame,
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        };
+        frames += 1;
+
+        // A repeat only changed the pointer yet its pixels can still be copied on demand
+        if frame.is_repeat() {
+          assert_eq!(frame.present_time(), 0);
+          assert_eq!(frame.accumulated_frames(), 0);
+          assert!(frame.present_instant().is_none());
+          assert!(frame.dirty().unwrap().is_empty());
+          assert!(frame.moved().unwrap().is_empty());
+          assert!(!frame.as_bytes().unwrap().is_empty());
+        }
+      }
+
+      assert!(frames > 0);
+
+      // With repeats skipped every frame handed out has new pixels
+      capturer.set_skip_repeats(true);
+      for _ in 0..20 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => assert!(!frame.is_repeat()),
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_device_on_display_adapter() {
+    unsafe {
+      for display in DxgiDisplays::new().unwrap() {
+        let mut display = display.unwrap();
+        display.set_config(CaptureConfig::new().with_driver_type(DriverType::Hardware));
+
+        // Microsoft's adapters have no hardware device
+        let capturer = match DxgiDisplayCapturer::new(&display) {
+          Ok(capturer) => capturer,
+          Err(FrameError::DriverType(DriverType::Hardware)) => continue,
+          Err(err) => panic!("{:?}", err),
+        };
+
+        // The device must live on the adapter driving the display
+        let mut adapter = None;
+        let device = capturer.device().cast::<IDXGIDevice>().unwrap();
+        device.GetAdapter(&mut adapter).ok().unwrap();
+        let luid = adapter_luid(&adapter.unwrap().cast().unwrap()).unwrap();
+
+        assert!(luid == adapter_luid(&display.adapter).unwrap());
+        assert!(capturer.adapter_luid() == luid);
+      }
+    }
+  }
+
+  #[test]
+  fn test_get_frame_after_timeout() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+
+      // A zero timeout on an idle screen times out without acquiring a frame, which must
+      // not leave the capturer thinking it holds one
+      match capturer.get_frame(Duration::ZERO) {
+        Ok(_) | Err(FrameError::WouldBlock) => {}
+        Err(err) => panic!("{:?}", err),
+      }
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => return assert!(!frame.as_bytes().unwrap().is_empty()),
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      panic!("no frame was acquired");
+    }
+  }
+
+  #[test]
+  fn test_get_frame_after_access_lost() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+
+      match capturer.get_frame(Duration::from_millis(16)) {
+        Ok(_) | Err(FrameError::WouldBlock) => {}
+        Err(err) => panic!("{:?}", err),
+      }
+
+      // Dropping the duplication is what handling `DXGI_ERROR_ACCESS_LOST` does
+      capturer.duplication = None;
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => return assert!(!frame.as_bytes().unwrap().is_empty()),
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      panic!("no frame was acquired");
+    }
+  }
+
+  #[test]
+  fn test_get_frame_sequence() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let display = displays.next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut sequences = Vec::new();
+
+      for _ in 0..20 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => sequences.push(frame.sequence()),
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      assert!(!sequences.is_empty());
+
+      // Timeouts must not leave gaps in the sequence
+      let expected = (0..sequences.len() as u64).collect::<Vec<_>>();
+      assert_eq!(sequences, expected);
+    }
+  }
+
+  #[test]
+  fn test_get_frame_timing() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let mut display = displays.next().unwrap().unwrap();
+      display.set_config(CaptureConfig::new().with_timing(true));
+
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut frames = 0;
+
+      for _ in 0..10 {
+        let frame = match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => frame,
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        };
+
+        let timing = frame.timing().unwrap();
+        assert!(timing.acquire >= Duration::ZERO);
+        assert!(timing.map >= Duration::ZERO);
+        assert!(timing.release >= Duration::ZERO);
+        frames += 1;
+      }
+
+      assert!(frames > 0);
+    }
+  }
+
+  #[test]
+  fn test_get_frame_overlay() {
+    unsafe {
+      for driver_type in [DriverType::HardwareOrSoftware, DriverType::Software] {
+        let mut displays = DxgiDisplays::new().unwrap();
+        let mut display = displays.next().unwrap().unwrap();
+        display.set_config(
+          CaptureConfig::new()
+            .with_driver_type(driver_type)
+            .with_timestamp_overlay(true)
+            .with_sequence_overlay(true),
+        );
+
+        // Displays driven by a GPU can't be captured with WARP
+        let mut capturer = match DxgiDisplayCapturer::new(&display) {
+          Ok(capturer) => capturer,
+          Err(FrameError::DriverType(DriverType::Software)) => continue,
+          Err(err) => panic!("{:?}", err),
+        };
+
+        let mut frames = 0;
+        for _ in 0..10 {
+          let frame = match capturer.get_frame(Duration::from_millis(16)) {
+            Ok(frame) => frame,
+            Err(FrameError::WouldBlock) => continue,
+            Err(err) => panic!("{:?}", err),
+          };
+
+          // The top-left corner is the margin of the overlay box
+          let bytes = frame.as_bytes().unwrap();
+          assert_eq!(&bytes[..4], &[0, 0, 0, 255]);
+          frames += 1;
+        }
+
+        assert!(frames > 0);
+      }
+    }
+  }
+
+  #[test]
+  fn test_get_frame_cursor_history() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let mut display = displays.next().unwrap().unwrap();
+      display.set_config(CaptureConfig::new().with_cursor_history(4));
+
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(_) | Err(FrameError::WouldBlock) => {}
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      let cursor = capturer.cursor().unwrap();
+      assert!(cursor.positions().len() <= 4);
+      assert!(cursor
+        .positions()
+        .windows(2)
+        .all(|pair| pair[0].0 <= pair[1].0));
+    }
+  }
+
+  #[test]
+  fn test_get_frame_draw_cursor() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let mut display = displays.next().unwrap().unwrap();
+      display.set_config(CaptureConfig::new().with_draw_cursor(true));
+
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut frames = 0;
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => {
+            if let Some(shape) = frame.cursor().and_then(|cursor| cursor.shape) {
+              assert!(shape.width > 0 && shape.cursor_height() > 0);
+            }
+
+            frame.as_bytes().unwrap();
+            frames += 1;
+          }
+          Err(FrameError::WouldBlock) => {}
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      assert!(frames > 0);
+    }
+  }
+
+  #[test]
+  fn test_get_frame_region() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let mut display = displays.next().unwrap().unwrap();
+      let (width, height) = (display.width(), display.height());
+
+      // Hangs off the bottom-right corner so it's clamped to 20x10
+      let region = Crop::new(width - 20, height - 10, 300, 300);
+      display.set_config(CaptureConfig::new().with_region(Some(region)));
+
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+      let mut frames = 0;
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => {
+            assert_eq!((frame.width(), frame.height()), (20, 10));
+            let bpp = frame.format().bytes_per_pixel();
+            assert_eq!(frame.stride(), 20 * bpp);
+            assert_eq!(frame.as_bytes().unwrap().len(), 20 * 10 * bpp);
+            assert!(frame
+              .dirty()
+              .unwrap()
+              .iter()
+              .all(|rect| rect.right <= 20 && rect.bottom <= 10));
+            frames += 1;
+          }
+          Err(FrameError::WouldBlock) => {}
+          Err(err) => panic!("{:?}", err),
+        }
       }
+
+      assert!(frames > 0);
+    }
+  }
+
+  #[test]
+  fn test_get_frame_output_size() {
+    unsafe {
+      // The GPU scales with `Bilinear`, `Area` is scaled on the CPU
+      for filter in [Filter::Bilinear, Filter::Area] {
+        let mut displays = DxgiDisplays::new().unwrap();
+        let mut display = displays.next().unwrap().unwrap();
+        display.set_config(
+          CaptureConfig::new()
+            .with_output_size(Some((320, 180)))
+            .with_scale_filter(filter),
+        );
+
+        let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+        let mut frames = 0;
+
+        for _ in 0..10 {
+          let frame = match capturer.get_frame(Duration::from_millis(16)) {
+            Ok(frame) => frame,
+            Err(FrameError::WouldBlock) => continue,
+            Err(err) => panic!("{:?}", err),
+          };
+
+          let bpp = frame.format().bytes_per_pixel();
+          assert_eq!((frame.width(), frame.height()), (320, 180));
+          assert_eq!(frame.stride(), 320 * bpp);
+          assert_eq!(frame.as_bytes().unwrap().len(), 320 * 180 * bpp);
+          assert!(frame
+            .dirty()
+            .unwrap()
+            .iter()
+            .all(|rect| rect.right <= 320 && rect.bottom <= 180));
+          frames += 1;
+        }
+
+        assert!(frames > 0);
+      }
+    }
+  }
+
+  #[test]
+  fn test_get_frame_warp() {
+    unsafe {
+      let mut displays = DxgiDisplays::new().unwrap();
+      let mut display = displays.next().unwrap().unwrap();
+      let is_warp = is_warp_adapter(&display.adapter).unwrap();
+      display.set_config(CaptureConfig::new().with_driver_type(DriverType::Software));
+
+      // WARP can only duplicate outputs of Microsoft's adapters
+      let mut capturer = match DxgiDisplayCapturer::new(&display) {
+        Ok(capturer) => capturer,
+        Err(FrameError::DriverType(DriverType::Software)) => return assert!(!is_warp),
+        Err(err) => panic!("{:?}", err),
+      };
+      assert!(is_warp && capturer.is_warp());
+
+      for _ in 0..10 {
+        match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => return assert!(!frame.as_bytes().unwrap().is_empty()),
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        };
+      }
+
+      panic!("no frame was acquired");
+    }
+  }
+
+  #[test]
+  fn test_device_falls_back_on_warp() {
+    unsafe {
+      let ((device, _, _), is_warp) =
+        device_or_warp(Err(FrameError::None), false, DriverType::HardwareOrSoftware)
+          .unwrap();
+      assert!(is_warp);
+
+      // The fallback device lives on Microsoft's software adapter
+      let mut adapter = None;
+      let device = device.cast::<IDXGIDevice>().unwrap();
+      device.GetAdapter(&mut adapter).ok().unwrap();
+      assert!(is_warp_adapter(&adapter.unwrap().cast().unwrap()).unwrap());
+
+      assert!(matches!(
+        device_or_warp(Err(FrameError::None), false, DriverType::Hardware),
+        Err(FrameError::None)
+      ));
     }
   }
 }
@@ -1,30 +1,126 @@
 //! Provides interface to get display information for Desktop Duplication API frame capture.
 
-use super::{capture::DxgiDisplayCapturer, frame::DxgiFrame};
+use super::{
+  capture::DxgiDisplayCapturer, errors::FrameError as DxgiFrameError, frame::DxgiFrame,
+};
 use crate::{
-  bindings::Windows::Win32::Graphics::Dxgi::{
-    CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput1, DXGI_ERROR_NOT_FOUND,
-    DXGI_OUTPUT_DESC,
+  bindings::Windows::Win32::{
+    Foundation::{PWSTR, RECT},
+    Graphics::{
+      Dxgi::{
+        CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput1,
+        DXGI_ADAPTER_DESC1, DXGI_ADAPTER_FLAG_SOFTWARE, DXGI_ERROR_NOT_FOUND,
+        DXGI_MODE_ROTATION, DXGI_MODE_ROTATION_ROTATE180, DXGI_MODE_ROTATION_ROTATE270,
+        DXGI_MODE_ROTATION_ROTATE90, DXGI_OUTPUT_DESC,
+      },
+      Gdi::{
+        EnumDisplayDevicesW, EnumDisplaySettingsW, GetMonitorInfoW, DISPLAY_DEVICEW,
+        ENUM_CURRENT_SETTINGS, HMONITOR, MONITORINFO,
+      },
+    },
+    System::{
+      Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY},
+      SystemServices::LUID,
+    },
+    UI::{
+      DisplayDevices::DEVMODEW,
+      HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+    },
   },
+  compare::{self, TileConfig},
+  convert::Rotation,
+  cursor::CursorTracker,
+  edid::Edid,
   errors::{DisplayError, FrameError},
-  Display,
+  power::PowerState,
+  transform::Crop,
+  CaptureConfig, DirtyRect, Display, OwnedFrame,
+};
+use std::{
+  hint::unreachable_unchecked,
+  mem,
+  time::{Duration, Instant},
 };
-use std::{hint::unreachable_unchecked, time::Duration};
 use windows::Interface;
 
 /// A Dxgi display
+///
+/// # Notes
+/// Displays hold COM objects bound to the thread that enumerated them so, they aren't
+/// `Send`.  Open a display on the thread capturing it, as
+/// [`crate::parallel::ParallelCapture`] does.
 #[derive(Debug, Clone)]
 pub struct DxgiDisplay {
   pub(super) desc: DXGI_OUTPUT_DESC,
   pub(super) output: IDXGIOutput1,
   pub(super) adapter: IDXGIAdapter1,
+  pub(super) config: CaptureConfig,
+  pub(super) generation: u32,
   pub(super) capturer: Option<DxgiDisplayCapturer>,
+  /// The last snapshot and the config it was captured with
+  pub(super) last: Option<(CaptureConfig, OwnedFrame)>,
 }
 
 impl DxgiDisplay {
   /// The name of the display
   pub fn name(&self) -> String {
-    String::from_utf16_lossy(&self.desc.DeviceName)
+    wide_to_string(&self.desc.DeviceName)
+  }
+
+  /// Get how the OS identifies the display
+  ///
+  /// # Notes
+  /// DXGI enumerates displays by adapter, which doesn't match the numbering of Windows
+  /// Display Settings.  The OS number is looked up with `EnumDisplayDevices` on every
+  /// call so it reflects displays being attached or detached.
+  pub fn info(&self) -> DisplayInfo {
+    // Flag of `DISPLAY_DEVICEW::StateFlags` marking the primary display
+    const DISPLAY_DEVICE_PRIMARY_DEVICE: u32 = 0x4;
+
+    let name = self.name();
+    let device = unsafe { display_devices() }
+      .into_iter()
+      .enumerate()
+      .find(|(_, device)| wide_to_string(&device.DeviceName) == name);
+
+    DisplayInfo {
+      os_index: device.as_ref().map(|(idx, _)| *idx as u32 + 1),
+      is_primary: device.is_some_and(|(_, device)| {
+        device.StateFlags & DISPLAY_DEVICE_PRIMARY_DEVICE != 0
+      }),
+      name,
+    }
+  }
+
+  /// Identify the physical monitor behind the display
+  ///
+  /// # Notes
+  /// The EDID is read from the registry key Windows caches it in when the monitor is
+  /// connected, `None` when the monitor didn't report one or it couldn't be parsed.
+  pub fn edid(&self) -> Option<Edid> {
+    Edid::parse(&unsafe { read_edid(&self.name()) }?)
+  }
+
+  /// Whether the display is the primary display
+  ///
+  /// # Notes
+  /// The flags of the monitor are queried so, this reflects the current primary display.
+  /// When the monitor is gone Windows' placement of the primary display at the origin of
+  /// the virtual desktop, as it was when enumerated, is used instead.
+  pub fn is_primary(&self) -> bool {
+    let flags = unsafe { monitor_flags(self.desc.Monitor) };
+    is_primary_display(flags, &self.desc.DesktopCoordinates)
+  }
+
+  /// The position of the display's top-left corner on the virtual desktop
+  ///
+  /// # Notes
+  /// Displays left of or above the primary display have negative positions.
+  pub const fn position(&self) -> (i32, i32) {
+    (
+      self.desc.DesktopCoordinates.left,
+      self.desc.DesktopCoordinates.top,
+    )
   }
 
   /// The width of the display
@@ -37,10 +133,287 @@ impl DxgiDisplay {
     (self.desc.DesktopCoordinates.bottom - self.desc.DesktopCoordinates.top) as usize
   }
 
+  /// How far the display is rotated clockwise from its native orientation
+  ///
+  /// # Notes
+  /// Frames are captured in the native orientation unless
+  /// [`CaptureConfig::with_apply_rotation`] is enabled, [`DxgiDisplay::width`] and
+  /// [`DxgiDisplay::height`] are always in the rotated orientation.
+  pub fn rotation(&self) -> Rotation {
+    rotation(self.desc.Rotation)
+  }
+
+  /// The refresh rate of the current display mode in hz
+  ///
+  /// # Notes
+  /// The display settings are read on every call so this follows mode changes, `None`
+  /// when they can't be read or the driver only reports its default rate.  See
+  /// [`DxgiDisplayCapturer::refresh_rate`] for the rate of a running duplication.
+  pub fn refresh_rate_hz(&self) -> Option<f32> {
+    let mut name = self.desc.DeviceName;
+    let mut mode: DEVMODEW = unsafe { mem::zeroed() };
+    mode.dmSize = mem::size_of::<DEVMODEW>() as u16;
+
+    let found = unsafe {
+      EnumDisplaySettingsW(PWSTR(name.as_mut_ptr()), ENUM_CURRENT_SETTINGS, &mut mode)
+    };
+
+    match mode.dmDisplayFrequency {
+      // Zero and one both mean the hardware default rate
+      0 | 1 => None,
+      hz => found.as_bool().then_some(hz as f32),
+    }
+  }
+
+  /// The DPI scale factor of the display, e.g. `1.5` at 150% scaling
+  ///
+  /// # Notes
+  /// This is the effective scale Windows applies to DPI aware applications, `1.0` when it
+  /// can't be read.
+  pub fn scale_factor(&self) -> f32 {
+    // The DPI of 100% scaling
+    const BASE_DPI: f32 = 96.0;
+
+    let (mut x, mut y) = (0, 0);
+    let result =
+      unsafe { GetDpiForMonitor(self.desc.Monitor, MDT_EFFECTIVE_DPI, &mut x, &mut y) };
+
+    match result.ok() {
+      Ok(()) if x > 0 => x as f32 / BASE_DPI,
+      _ => 1.0,
+    }
+  }
+
+  /// The Win32 monitor handle of the display
+  ///
+  /// # Notes
+  /// Useful for cross-referencing the display with other Windows APIs such as
+  /// `GetMonitorInfo` or `MonitorFromWindow`.
+  pub const fn hmonitor(&self) -> HMONITOR {
+    self.desc.Monitor
+  }
+
+  /// The options used when capturing frames
+  pub const fn config(&self) -> &CaptureConfig {
+    &self.config
+  }
+
+  /// Sets the options used when capturing frames
+  ///
+  /// # Notes
+  /// Any existing capturer is dropped and re-created with the new options on the next
+  /// frame.
+  pub fn set_config(&mut self, config: CaptureConfig) {
+    self.config = config;
+
+    // The capturer may have moved on to later generations by itself
+    if let Some(capturer) = self.capturer.take() {
+      self.generation = self.generation.max(capturer.generation().wrapping_add(1));
+    }
+  }
+
+  /// Draw the cursor onto frames when pixels are copied
+  ///
+  /// # Notes
+  /// Same as [`CaptureConfig::with_draw_cursor`] but, an existing capturer is kept, see
+  /// [`DxgiDisplayCapturer::set_draw_cursor`].
+  pub fn set_draw_cursor(&mut self, draw_cursor: bool) {
+    self.config = self.config.clone().with_draw_cursor(draw_cursor);
+
+    if let Some(capturer) = &mut self.capturer {
+      capturer.set_draw_cursor(draw_cursor);
+    }
+  }
+
+  /// Only capture a region of the display
+  ///
+  /// # Arguments
+  /// * `x` - The left edge of the region in the native orientation of the display
+  /// * `y` - The top edge of the region in the native orientation of the display
+  /// * `width` - The width of the region
+  /// * `height` - The height of the region
+  ///
+  /// # Notes
+  /// Same as [`CaptureConfig::with_region`] but, an existing capturer is kept.  The
+  /// region is clamped to the display when each frame is captured.
+  pub fn set_region(&mut self, x: usize, y: usize, width: usize, height: usize) {
+    self.set_region_with(Some(Crop::new(x, y, width, height)));
+  }
+
+  /// Capture the whole display again after [`DxgiDisplay::set_region`]
+  pub fn clear_region(&mut self) {
+    self.set_region_with(None);
+  }
+
+  fn set_region_with(&mut self, region: Option<Crop>) {
+    self.config = self.config.clone().with_region(region);
+
+    if let Some(capturer) = &mut self.capturer {
+      capturer.set_region(region);
+    }
+  }
+
+  /// The cursor positions seen while capturing, when enabled by
+  /// [`CaptureConfig::with_cursor_history`]
+  ///
+  /// # Notes
+  /// The history starts over whenever the capturer is re-created.
+  pub fn cursor(&self) -> Option<&CursorTracker> {
+    self.capturer.as_ref()?.cursor()
+  }
+
+  /// Guess whether the display is powered
+  ///
+  /// # Notes
+  /// A display removed from the desktop is [`PowerState::Off`].  Otherwise the guess is
+  /// based on how recently captured frames showed new content, so it is
+  /// [`PowerState::Unknown`] until a frame is captured and after a long idle period,
+  /// since duplication can't tell a sleeping monitor from an idle desktop.
+  pub fn power_state(&self) -> PowerState {
+    let attached = self.is_attached();
+
+    match &self.capturer {
+      Some(capturer) => capturer.power_state(attached),
+      None if !attached => PowerState::Off,
+      None => PowerState::Unknown,
+    }
+  }
+
+  /// Whether the display is currently part of the desktop
+  fn is_attached(&self) -> bool {
+    let mut desc = DXGI_OUTPUT_DESC::default();
+
+    // Assume the display is still there when the output can't be queried
+    match unsafe { self.output.GetDesc(&mut desc) }.ok() {
+      Ok(()) => desc.AttachedToDesktop.as_bool(),
+      Err(_) => true,
+    }
+  }
+
+  /// Gets a screen capture frame, waiting up to `timeout` for a new one
+  ///
+  /// # Arguments
+  /// * `timeout` - How long to wait for the desktop to change, overriding
+  ///   [`CaptureConfig::with_timeout`] for this call only
+  ///
+  /// # Notes
+  /// A zero `timeout` never waits, [`FrameError::WouldBlock`] is returned right away
+  /// when no new frame is available.
+  pub fn frame_with_timeout(
+    &mut self,
+    timeout: Duration,
+  ) -> Result<DxgiFrame<'_>, FrameError> {
+    self.next_frame(Some(timeout))
+  }
+
+  /// Gets a screen capture frame, waiting until the desktop changes
+  ///
+  /// # Arguments
+  /// * `deadline` - The longest to wait overall or `None` to wait indefinitely
+  ///
+  /// # Notes
+  /// The wait happens inside `AcquireNextFrame` so, no CPU is spent while the desktop
+  /// is idle.  [`FrameError::WouldBlock`] is returned when `deadline` passes first.
+  pub fn frame_blocking(
+    &mut self,
+    deadline: Option<Duration>,
+  ) -> Result<DxgiFrame<'_>, FrameError> {
+    // `AcquireNextFrame` waits forever when given `INFINITE`
+    const INFINITE: Duration = Duration::from_millis(u32::MAX as u64);
+
+    let timeout = match deadline {
+      Some(deadline) => deadline.min(INFINITE - Duration::from_millis(1)),
+      None => INFINITE,
+    };
+
+    self.next_frame(Some(timeout))
+  }
+
+  /// Gets a screen capture frame, waiting up to `timeout` or the configured timeout
+  fn next_frame(
+    &mut self,
+    timeout: Option<Duration>,
+  ) -> Result<DxgiFrame<'_>, FrameError> {
+    // Frames handed out here move the capturer past the last snapshot
+    self.last = None;
+
+    // Report a display leaving the desktop mid-capture rather than timing out forever
+    if self.capturer.is_some() && !self.is_attached() {
+      return Err(DxgiFrameError::DisplayOff.into());
+    }
+
+    Ok(unsafe {
+      let capturer = self.capturer_mut()?;
+      let timeout = timeout.unwrap_or_else(|| capturer.frame_timeout());
+
+      capturer.get_frame(timeout)?
+    })
+  }
+
+  /// Capture a frame and compare it against an earlier frame
+  ///
+  /// # Arguments
+  /// * `baseline` - A previously captured frame, this may be arbitrarily old
+  ///
+  /// # Notes
+  /// DXGI only reports what changed since the previous frame so, differences are found
+  /// by comparing pixels tile by tile instead.
+  pub fn snapshot_delta(
+    &mut self,
+    baseline: &OwnedFrame,
+  ) -> anyhow::Result<(OwnedFrame, Vec<DirtyRect>)> {
+    self.snapshot_delta_with(baseline, TileConfig::default())
+  }
+
+  /// Capture a frame and compare it against an earlier frame using `tiles` sized blocks
+  pub fn snapshot_delta_with(
+    &mut self,
+    baseline: &OwnedFrame,
+    tiles: TileConfig,
+  ) -> anyhow::Result<(OwnedFrame, Vec<DirtyRect>)> {
+    let frame = self.snapshot()?;
+    let changed = compare::diff_with(baseline, &frame, tiles)?;
+
+    Ok((frame, changed))
+  }
+
+  /// Capture the next frame into an [`OwnedFrame`], waiting up to a second for one
+  ///
+  /// # Notes
+  /// DXGI only hands out frames when something new was presented so, the last snapshot
+  /// is kept and returned again while the desktop is static.
+  pub(super) fn snapshot(&mut self) -> anyhow::Result<OwnedFrame> {
+    const DEADLINE: Duration = Duration::from_secs(1);
+
+    let start = Instant::now();
+
+    loop {
+      let capturer = unsafe { self.capturer_mut()? };
+      let timeout = capturer.frame_timeout();
+
+      match unsafe { capturer.get_frame(timeout) }.map_err(FrameError::from) {
+        Ok(frame) => {
+          let frame = OwnedFrame::from_frame(&frame)?;
+          self.last = Some((self.config.clone(), frame.clone()));
+
+          return Ok(frame);
+        }
+        Err(FrameError::WouldBlock) => match &self.last {
+          // Frames captured with other options no longer match the desktop image
+          Some((config, last)) if *config == self.config => return Ok(last.clone()),
+          _ if start.elapsed() < DEADLINE => continue,
+          _ => return Err(FrameError::WouldBlock.into()),
+        },
+        Err(err) => return Err(err.into()),
+      }
+    }
+  }
+
   /// Gets or initializes a [`DxgiDisplayCapturer`]
   unsafe fn capturer_mut(&mut self) -> Result<&mut DxgiDisplayCapturer, FrameError> {
     if self.capturer.is_none() {
-      self.capturer = Some(DxgiDisplayCapturer::new(self).unwrap());
+      self.capturer = Some(DxgiDisplayCapturer::new(self)?);
+      self.generation = self.generation.wrapping_add(1);
     }
 
     match &mut self.capturer {
@@ -64,24 +437,230 @@ impl<'frame> Display<'frame> for DxgiDisplay {
   }
 
   fn frame(&'frame mut self) -> Result<Self::Frame, FrameError> {
-    // ~124fps to give windows a little time to prepare a frame for us.
-    const FPS_124: u64 = 8;
+    self.next_frame(None)
+  }
+}
 
-    Ok(unsafe {
-      self
-        .capturer_mut()?
-        .get_frame(Duration::from_millis(FPS_124))?
+/// How the OS identifies a display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayInfo {
+  /// The GDI device name of the display, e.g. `\\.\DISPLAY1`
+  pub name: String,
+  /// The number of the display in Windows Display Settings starting at one, `None` when
+  /// the display is no longer attached
+  pub os_index: Option<u32>,
+  /// Whether the display is currently the primary display
+  pub is_primary: bool,
+}
+
+/// A display adapter, usually a GPU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterInfo {
+  /// The position of the adapter in DXGI enumeration order, see
+  /// [`DxgiDisplays::on_adapter`]
+  pub index: u32,
+  /// The description of the adapter, usually its product name
+  pub description: String,
+  /// The PCI vendor ID of the adapter
+  pub vendor_id: u32,
+  /// The PCI device ID of the adapter
+  pub device_id: u32,
+  /// The amount of video memory in bytes not shared with the CPU
+  pub dedicated_video_memory: usize,
+  /// The locally unique identifier of the adapter, the same as
+  /// [`super::capture::DxgiDisplayCapturer::adapter_luid`] of capturers on it
+  pub luid: LUID,
+  /// Whether the adapter is a software rasterizer, e.g. the Microsoft Basic Render
+  /// Driver, which can't duplicate outputs
+  pub is_software: bool,
+}
+
+impl AdapterInfo {
+  /// Read the description of an adapter
+  ///
+  /// # Safety
+  /// Calls to DXGI
+  unsafe fn new(index: u32, adapter: &IDXGIAdapter1) -> windows::Result<Self> {
+    let mut desc = DXGI_ADAPTER_DESC1::default();
+    adapter.GetDesc1(&mut desc).ok()?;
+
+    Ok(Self {
+      index,
+      description: wide_to_string(&desc.Description),
+      vendor_id: desc.VendorId,
+      device_id: desc.DeviceId,
+      dedicated_video_memory: desc.DedicatedVideoMemory,
+      luid: desc.AdapterLuid,
+      is_software: desc.Flags & DXGI_ADAPTER_FLAG_SOFTWARE.0 != 0,
     })
   }
 }
 
-/// A Dxgi display iterator where first display is the primary display
+/// Get every GDI display device in the order the OS numbers them
+///
+/// # Safety
+/// Calls to windows API
+pub(crate) unsafe fn display_devices() -> Vec<DISPLAY_DEVICEW> {
+  let mut devices = Vec::new();
+
+  loop {
+    let mut device = DISPLAY_DEVICEW {
+      cb: mem::size_of::<DISPLAY_DEVICEW>() as u32,
+      ..Default::default()
+    };
+
+    if !EnumDisplayDevicesW(PWSTR::default(), devices.len() as u32, &mut device, 0)
+      .as_bool()
+    {
+      return devices;
+    }
+
+    devices.push(device);
+  }
+}
+
+/// Convert a DXGI rotation, treating an unspecified rotation as none
+pub(super) fn rotation(rotation: DXGI_MODE_ROTATION) -> Rotation {
+  match rotation {
+    DXGI_MODE_ROTATION_ROTATE90 => Rotation::Rotate90,
+    DXGI_MODE_ROTATION_ROTATE180 => Rotation::Rotate180,
+    DXGI_MODE_ROTATION_ROTATE270 => Rotation::Rotate270,
+    _ => Rotation::Identity,
+  }
+}
+
+/// Read the raw EDID of the monitor attached to the GDI display device `name`
+///
+/// # Safety
+/// Calls to windows API
+unsafe fn read_edid(name: &str) -> Option<Vec<u8>> {
+  // Report the device interface path rather than the hardware id of the monitor
+  const EDD_GET_DEVICE_INTERFACE_NAME: u32 = 0x1;
+
+  let mut monitor = DISPLAY_DEVICEW {
+    cb: mem::size_of::<DISPLAY_DEVICEW>() as u32,
+    ..Default::default()
+  };
+
+  if !EnumDisplayDevicesW(name, 0, &mut monitor, EDD_GET_DEVICE_INTERFACE_NAME).as_bool()
+  {
+    return None;
+  }
+
+  // The interface path looks like `\\?\DISPLAY#DEL4058#5&2a5d2e4&0&UID4353#{guid}`, the
+  // middle two parts are the hardware and instance ids of the monitor
+  let path = wide_to_string(&monitor.DeviceID);
+  let mut parts = path.split('#').skip(1);
+  let key = format!(
+    "SYSTEM\\CurrentControlSet\\Enum\\DISPLAY\\{}\\{}\\Device Parameters",
+    parts.next()?,
+    parts.next()?
+  );
+
+  let mut len = 0u32;
+  let status = RegGetValueW(
+    HKEY_LOCAL_MACHINE,
+    key.as_str(),
+    "EDID",
+    RRF_RT_REG_BINARY,
+    std::ptr::null_mut(),
+    std::ptr::null_mut(),
+    &mut len,
+  );
+
+  if status.0 != 0 {
+    return None;
+  }
+
+  let mut edid = vec![0u8; len as usize];
+  let status = RegGetValueW(
+    HKEY_LOCAL_MACHINE,
+    key.as_str(),
+    "EDID",
+    RRF_RT_REG_BINARY,
+    std::ptr::null_mut(),
+    edid.as_mut_ptr().cast(),
+    &mut len,
+  );
+
+  match status.0 {
+    0 => {
+      edid.truncate(len as usize);
+      Some(edid)
+    }
+    _ => None,
+  }
+}
+
+/// Read the `MONITORINFOF_*` flags of a monitor, `None` when the monitor is gone
+///
+/// # Safety
+/// Calls to windows API
+unsafe fn monitor_flags(monitor: HMONITOR) -> Option<u32> {
+  let mut info = MONITORINFO {
+    cbSize: mem::size_of::<MONITORINFO>() as u32,
+    ..Default::default()
+  };
+
+  GetMonitorInfoW(monitor, &mut info)
+    .as_bool()
+    .then_some(info.dwFlags)
+}
+
+/// The flag `GetMonitorInfoW` sets on the primary display
+const MONITORINFOF_PRIMARY: u32 = 1;
+
+/// Whether a display is the primary display from the flags of its monitor, falling back
+/// to whether its desktop rectangle starts at the origin when the monitor is gone
+const fn is_primary_display(flags: Option<u32>, rect: &RECT) -> bool {
+  match flags {
+    Some(flags) => flags & MONITORINFOF_PRIMARY != 0,
+    None => is_origin(rect),
+  }
+}
+
+/// Whether a desktop rectangle starts at the origin of the virtual desktop
+const fn is_origin(rect: &RECT) -> bool {
+  rect.left == 0 && rect.top == 0
+}
+
+/// Find the first display matching `predicate` in enumeration order, failing on the first
+/// error
+fn find_first<T, I, P>(displays: I, predicate: P) -> windows::Result<Option<T>>
+where
+  I: IntoIterator<Item = windows::Result<T>>,
+  P: Fn(&T) -> bool,
+{
+  for display in displays {
+    let display = display?;
+    if predicate(&display) {
+      return Ok(Some(display));
+    }
+  }
+
+  Ok(None)
+}
+
+/// Convert a nul terminated wide string buffer into a [`String`]
+pub(crate) fn wide_to_string(buf: &[u16]) -> String {
+  let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+  String::from_utf16_lossy(&buf[..len])
+}
+
+/// A Dxgi display iterator ordered by adapter then output
+///
+/// # Notes
+/// The primary display is usually, but not necessarily, the first display.  Use
+/// [`DxgiDisplays::primary`] to find it.  Outputs of software adapters are skipped, as
+/// are outputs that can't be read, see [`DxgiDisplays::skipped`].
 #[derive(Debug, Clone)]
 pub struct DxgiDisplays {
   factory: IDXGIFactory1,
   adapter: Option<IDXGIAdapter1>,
   adapter_idx: u32,
   display_idx: u32,
+  last_adapter: Option<u32>,
+  skipped: Vec<windows::Error>,
 }
 
 impl DxgiDisplays {
@@ -91,14 +670,132 @@ impl DxgiDisplays {
       adapter: None,
       adapter_idx: 0,
       display_idx: 0,
+      last_adapter: None,
+      skipped: Vec::new(),
     })
   }
 
+  /// Get why each output skipped so far couldn't be read
+  ///
+  /// # Notes
+  /// An output that fails to describe itself, e.g. a monitor that was disconnected while
+  /// still enumerated, is skipped rather than ending enumeration so, one flaky display
+  /// doesn't hide the others.
+  pub fn skipped(&self) -> &[windows::Error] {
+    &self.skipped
+  }
+
+  /// Get every display adapter in enumeration order, including adapters without
+  /// displays attached
+  pub fn adapters(&self) -> windows::Result<Vec<AdapterInfo>> {
+    let mut adapters = Vec::new();
+
+    loop {
+      let index = adapters.len() as u32;
+      let mut adapter = None;
+
+      match unsafe { self.factory.EnumAdapters1(index, &mut adapter) } {
+        result if result == DXGI_ERROR_NOT_FOUND => return Ok(adapters),
+        result => result.ok()?,
+      }
+
+      match adapter {
+        Some(adapter) => adapters.push(unsafe { AdapterInfo::new(index, &adapter)? }),
+        None => return Ok(adapters),
+      }
+    }
+  }
+
+  /// Only enumerate the displays driven by the adapter at `index`
+  ///
+  /// # Notes
+  /// Software adapters are never enumerated, see [`AdapterInfo::is_software`].
+  /// Duplication always uses a device on the adapter driving the display, so on hybrid
+  /// GPU machines this is how capture is kept on a specific GPU.  See
+  /// [`DxgiDisplays::adapters`] for the index of each adapter.
+  pub fn on_adapter(mut self, index: u32) -> Self {
+    self.adapter = None;
+    self.adapter_idx = index;
+    self.display_idx = 0;
+    self.last_adapter = Some(index);
+    self
+  }
+
+  /// Whether the system has at least one display adapter, including adapters without
+  /// displays attached
+  pub fn has_adapters(&self) -> bool {
+    let mut adapter = None;
+    unsafe { self.factory.EnumAdapters1(0, &mut adapter) }.is_ok() && adapter.is_some()
+  }
+
+  /// Find the primary display among the remaining displays
+  pub fn primary(self) -> windows::Result<Option<DxgiDisplay>> {
+    self.find_display(DxgiDisplay::is_primary)
+  }
+
+  /// Find the display with the GDI device name `name` among the remaining displays
+  ///
+  /// # Notes
+  /// Device names look like `\\.\DISPLAY1` and are compared ignoring ASCII case, see
+  /// [`DxgiDisplay::name`].
+  pub fn by_name(self, name: &str) -> windows::Result<Option<DxgiDisplay>> {
+    self.find_display(|display| display.name().eq_ignore_ascii_case(name))
+  }
+
+  /// Find the display shown on the monitor `hmonitor` among the remaining displays
+  ///
+  /// # Notes
+  /// See [`DxgiDisplay::hmonitor`], e.g. pair with `MonitorFromWindow` to capture the
+  /// display a window is on.
+  pub fn by_hmonitor(self, hmonitor: HMONITOR) -> windows::Result<Option<DxgiDisplay>> {
+    self.find_display(|display| display.hmonitor() == hmonitor)
+  }
+
+  /// Find the first of the remaining displays matching `predicate`
+  fn find_display<P>(self, predicate: P) -> windows::Result<Option<DxgiDisplay>>
+  where
+    P: Fn(&DxgiDisplay) -> bool,
+  {
+    find_first(self, predicate)
+  }
+
+  /// Collect the remaining displays in enumeration order, failing on the first error
+  pub fn collect_all(self) -> windows::Result<Vec<DxgiDisplay>> {
+    self.collect()
+  }
+
+  /// Collect the remaining displays in enumeration order, leaving out any that failed
+  ///
+  /// # Notes
+  /// Failures are only dropped, prefer [`DxgiDisplays::collect_all`] when an empty list
+  /// needs explaining.
+  pub fn collect_ok(self) -> Vec<DxgiDisplay> {
+    self.filter_map(Result::ok).collect()
+  }
+
+  /// Collect the remaining displays ordered by their number in Windows Display Settings
+  ///
+  /// # Notes
+  /// Displays without an OS number are placed last in enumeration order.
+  pub fn sorted(self) -> windows::Result<Vec<DxgiDisplay>> {
+    let mut displays = self.collect_all()?;
+    displays.sort_by_cached_key(|display| display.info().os_index.unwrap_or(u32::MAX));
+
+    Ok(displays)
+  }
+
   /// Get the next display
   ///
   /// # Safety
   /// Calls to windows API
   unsafe fn next_display(&mut self) -> windows::Result<Option<DxgiDisplay>> {
+    if self
+      .last_adapter
+      .is_some_and(|last| self.adapter_idx > last)
+    {
+      return Ok(None);
+    }
+
     // Read next adapter if current one is `None`
     if self.adapter.is_none() {
       let result = self
@@ -112,6 +809,17 @@ impl DxgiDisplays {
       }
 
       result.ok()?;
+
+      // Skip software adapters, which show up on VMs and in RDP sessions and only fail
+      // once duplication is attempted
+      if let Some(adapter) = self.adapter.take() {
+        if AdapterInfo::new(self.adapter_idx, &adapter)?.is_software {
+          self.adapter_idx += 1;
+          return self.next_display();
+        }
+
+        self.adapter = Some(adapter);
+      }
     }
 
     match &self.adapter {
@@ -149,18 +857,28 @@ impl DxgiDisplays {
             self.next_display()
           }
           Some(output) => {
-            // Read in output details
-            let mut desc = DXGI_OUTPUT_DESC::default();
-            output.GetDesc(&mut desc).ok()?;
-
             // Move to next display
             self.display_idx += 1;
 
+            // Read in output details, skipping outputs that can't be read
+            let mut desc = DXGI_OUTPUT_DESC::default();
+            let output = match output.GetDesc(&mut desc).ok().and_then(|_| output.cast())
+            {
+              Ok(output) => output,
+              Err(err) => {
+                self.skipped.push(err);
+                return self.next_display();
+              }
+            };
+
             Ok(Some(DxgiDisplay {
               desc,
-              output: output.cast()?,
+              output,
               adapter: adapter.clone(),
+              config: CaptureConfig::default(),
+              generation: 0,
               capturer: None,
+              last: None,
             }))
           }
         }
@@ -173,13 +891,31 @@ impl Iterator for DxgiDisplays {
   type Item = windows::Result<DxgiDisplay>;
 
   fn next(&mut self) -> Option<Self::Item> {
-    unsafe { self.next_display() }.transpose()
+    let next = unsafe { self.next_display() };
+
+    // Skip whatever failed so iterating past an error doesn't return it forever
+    if next.is_err() {
+      match self.adapter {
+        Some(_) => self.display_idx += 1,
+        None => self.adapter_idx += 1,
+      }
+    }
+
+    next.transpose()
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use super::DxgiDisplays;
+  use super::{
+    find_first, is_primary_display, wide_to_string, DxgiDisplays, MONITORINFOF_PRIMARY,
+  };
+  use crate::bindings::Windows::Win32::{
+    Foundation::RECT,
+    Graphics::Dxgi::{CreateDXGIFactory1, IDXGIFactory1},
+  };
+  use crate::driver::dxgi::capture::DxgiDisplayCapturer;
+  use std::time::Duration;
 
   #[test]
   fn test_next_dxgi_display() {
@@ -195,4 +931,263 @@ mod tests {
       );
     }
   }
+
+  #[test]
+  fn test_wide_to_string() {
+    // `DeviceName` is a fixed 32 character buffer padded with NULs
+    let mut name = [0u16; 32];
+    for (dst, src) in name.iter_mut().zip("\\\\.\\DISPLAY1".encode_utf16()) {
+      *dst = src;
+    }
+
+    assert_eq!(wide_to_string(&name), "\\\\.\\DISPLAY1");
+    assert_eq!(wide_to_string(&[0x41, 0x42]), "AB");
+    assert_eq!(wide_to_string(&[0; 4]), "");
+  }
+
+  #[test]
+  fn test_names_without_nul() {
+    for display in DxgiDisplays::new().unwrap() {
+      let name = display.unwrap().name();
+
+      assert!(!name.is_empty());
+      assert!(!name.contains('\0'));
+    }
+  }
+
+  #[test]
+  fn test_primary_os_index() {
+    let primary = DxgiDisplays::new().unwrap().primary().unwrap().unwrap();
+    let info = primary.info();
+
+    // `\\.\DISPLAY1` is numbered 1 by the OS
+    let number = info.name.trim_start_matches(r"\\.\DISPLAY").parse().ok();
+    assert!(info.is_primary);
+    assert_eq!(info.os_index, number);
+  }
+
+  #[test]
+  fn test_primary_not_enumerated_first() {
+    // Primary display right of a display that is enumerated first
+    let rect = |left, right| RECT {
+      left,
+      top: 0,
+      right,
+      bottom: 1080,
+    };
+    let primary = |desktop: &[(Option<u32>, RECT)]| {
+      let found = find_first(desktop.iter().map(Ok), |(flags, rect)| {
+        is_primary_display(*flags, rect)
+      });
+      found.unwrap().map(|(_, rect)| rect.left)
+    };
+
+    // Monitors that are gone fall back to the origin of the virtual desktop
+    let gone = [
+      (None, rect(-1920, 0)),
+      (None, rect(0, 1920)),
+      (None, rect(1920, 3840)),
+    ];
+    assert_eq!(primary(&gone), Some(0));
+
+    // The flags of the monitor win over the layout seen when enumerated
+    let moved = [
+      (Some(0), rect(-1920, 0)),
+      (Some(0), rect(0, 1920)),
+      (Some(MONITORINFOF_PRIMARY), rect(1920, 3840)),
+    ];
+    assert_eq!(primary(&moved), Some(1920));
+  }
+
+  #[test]
+  fn test_primary_matches_os() {
+    for display in DxgiDisplays::new().unwrap() {
+      let display = display.unwrap();
+      assert_eq!(display.is_primary(), display.info().is_primary);
+    }
+  }
+
+  #[test]
+  fn test_has_adapters() {
+    let displays = DxgiDisplays::new().unwrap();
+
+    assert!(displays.has_adapters());
+    assert!(displays.count() > 0);
+  }
+
+  #[test]
+  fn test_displays_on_adapter() {
+    let displays = DxgiDisplays::new().unwrap();
+    let adapters = displays.adapters().unwrap();
+    let total = displays.count();
+
+    assert!(!adapters.is_empty());
+
+    let mut per_adapter = 0;
+    for adapter in &adapters {
+      for display in DxgiDisplays::new().unwrap().on_adapter(adapter.index) {
+        let display = display.unwrap();
+        let capturer = unsafe { DxgiDisplayCapturer::new(&display) };
+
+        if let Ok(capturer) = capturer {
+          assert_eq!(capturer.adapter_luid(), adapter.luid);
+        }
+
+        per_adapter += 1;
+      }
+    }
+
+    assert_eq!(per_adapter, total);
+  }
+
+  #[test]
+  fn test_refresh_rate_and_scale() {
+    for display in DxgiDisplays::new().unwrap() {
+      let display = display.unwrap();
+
+      assert!(display.refresh_rate_hz().is_none_or(|hz| hz > 1.0));
+      assert!(display.scale_factor() >= 1.0);
+    }
+  }
+
+  #[test]
+  fn test_single_primary() {
+    let primaries = DxgiDisplays::new()
+      .unwrap()
+      .filter(|display| display.as_ref().unwrap().is_primary())
+      .count();
+
+    assert_eq!(primaries, 1);
+  }
+
+  #[test]
+  fn test_sorted_by_os_index() {
+    let displays = DxgiDisplays::new().unwrap().sorted().unwrap();
+    let indices: Vec<_> = displays
+      .iter()
+      .map(|display| display.info().os_index)
+      .collect();
+
+    assert!(indices
+      .windows(2)
+      .all(|pair| pair[0] <= pair[1] || pair[1].is_none()));
+  }
+
+  #[test]
+  fn test_by_name() {
+    let primary = DxgiDisplays::new().unwrap().primary().unwrap().unwrap();
+    let name = primary.name();
+    let found = DxgiDisplays::new()
+      .unwrap()
+      .by_name(&name)
+      .unwrap()
+      .unwrap();
+    let lower = DxgiDisplays::new()
+      .unwrap()
+      .by_name(&name.to_lowercase())
+      .unwrap()
+      .unwrap();
+
+    assert!(!name.contains('\0'));
+    assert_eq!(found.hmonitor(), primary.hmonitor());
+    assert_eq!(lower.hmonitor(), primary.hmonitor());
+    assert!(DxgiDisplays::new()
+      .unwrap()
+      .by_name("\\\\.\\DISPLAY0")
+      .unwrap()
+      .is_none());
+  }
+
+  #[test]
+  fn test_by_hmonitor() {
+    for display in DxgiDisplays::new().unwrap() {
+      let display = display.unwrap();
+      let found = DxgiDisplays::new()
+        .unwrap()
+        .by_hmonitor(display.hmonitor())
+        .unwrap()
+        .unwrap();
+
+      assert_eq!(found.name(), display.name());
+    }
+  }
+
+  #[test]
+  fn test_collect() {
+    let all = DxgiDisplays::new().unwrap().collect_all().unwrap();
+    let ok = DxgiDisplays::new().unwrap().collect_ok();
+
+    assert!(!all.is_empty());
+    assert_eq!(all.len(), ok.len());
+  }
+
+  #[test]
+  fn test_hmonitor() {
+    for display in DxgiDisplays::new().unwrap() {
+      let display = display.unwrap();
+
+      if display.desc.AttachedToDesktop.as_bool() {
+        assert!(!display.hmonitor().is_null());
+      }
+    }
+  }
+
+  #[test]
+  fn test_all_adapter_outputs_enumerated() {
+    // Count outputs across every adapter by hand so displays on second and later adapters
+    // can't silently go missing
+    let expected = unsafe {
+      let factory: IDXGIFactory1 = CreateDXGIFactory1().unwrap();
+      let mut count = 0;
+      let mut adapter_idx = 0;
+      let mut adapter = None;
+
+      while factory.EnumAdapters1(adapter_idx, &mut adapter).is_ok() {
+        let adapter = adapter.take().unwrap();
+        let mut output_idx = 0;
+        let mut output = None;
+
+        while adapter.EnumOutputs(output_idx, &mut output).is_ok() {
+          output = None;
+          output_idx += 1;
+          count += 1;
+        }
+
+        adapter_idx += 1;
+      }
+
+      count
+    };
+
+    let displays = DxgiDisplays::new().unwrap();
+    assert_eq!(displays.map(Result::unwrap).count(), expected);
+  }
+
+  #[test]
+  fn test_edid() {
+    for display in DxgiDisplays::new().unwrap() {
+      // Virtual displays may not report an EDID at all
+      if let Some(edid) = display.unwrap().edid() {
+        assert_eq!(edid.manufacturer.len(), 3);
+        assert_eq!(edid.version.0, 1);
+      }
+    }
+  }
+
+  #[test]
+  fn test_snapshot_of_static_desktop() {
+    let mut display = DxgiDisplays::new().unwrap().next().unwrap().unwrap();
+    let first = display.snapshot().unwrap();
+
+    // Nothing new is presented right after the first snapshot on an idle desktop
+    let second = display.snapshot().unwrap();
+    assert_eq!(
+      (first.width(), first.height()),
+      (second.width(), second.height())
+    );
+
+    // Frames handed out elsewhere move the capturer past the kept snapshot
+    let _ = display.frame_with_timeout(Duration::ZERO);
+    assert!(display.last.is_none());
+  }
 }
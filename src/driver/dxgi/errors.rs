@@ -1,3 +1,10 @@
+use crate::DriverType;
+use windows::HRESULT;
+
+/// Returned when the process isn't allowed to duplicate the desktop, e.g. from a service
+/// or the secure desktop
+pub(super) const E_ACCESSDENIED: HRESULT = HRESULT(0x8007_0005);
+
 /// An error that occurs when reading frame information
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum FrameError {
@@ -7,8 +14,48 @@ pub enum FrameError {
   AcquireFrame(windows::Error),
   #[error("Failed to release frame `{0}`")]
   ReleaseFrame(windows::Error),
+  #[error("Access to the duplicated output was lost `{0}`")]
+  AccessLost(windows::Error),
+  #[error("The secure desktop is shown, capture can resume once it's dismissed `{0}`")]
+  SecureDesktop(windows::Error),
   #[error("Unexpected error `{0}`")]
-  Unexpected(#[from] windows::Error),
+  Unexpected(windows::Error),
   #[error("Failed to initialize resource")]
   None,
+  #[error("Display can't be captured with a `{0:?}` device")]
+  DriverType(DriverType),
+  #[error("Frame size doesn't match the shared texture")]
+  SharedSizeMismatch,
+  #[error("Display is no longer part of the desktop")]
+  DisplayOff,
+  #[error("The desktop image is in system memory rather than on the GPU")]
+  NotOnGpu,
+  #[error("Frame changed in {0} rectangles, more than the {1} that are read")]
+  TooManyRects(usize, usize),
+}
+
+impl From<windows::Error> for FrameError {
+  fn from(err: windows::Error) -> Self {
+    // UAC prompts, Ctrl+Alt+Del and the lock screen switch to the secure desktop, which
+    // can't be duplicated
+    match err.code() {
+      E_ACCESSDENIED => Self::SecureDesktop(err),
+      _ => Self::Unexpected(err),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{FrameError, E_ACCESSDENIED};
+  use windows::HRESULT;
+
+  #[test]
+  fn test_secure_desktop() {
+    let denied = FrameError::from(windows::Error::from(E_ACCESSDENIED));
+    let other = FrameError::from(windows::Error::from(HRESULT(0x8000_4005)));
+
+    assert!(matches!(denied, FrameError::SecureDesktop(_)));
+    assert!(matches!(other, FrameError::Unexpected(_)));
+  }
 }
@@ -1,180 +1,823 @@
+use super::{errors::FrameError, shared};
 use crate::{
+  alloc,
   bindings::Windows::Win32::{
-    Foundation::RECT,
-    Graphics::Dxgi::{IDXGIOutputDuplication, DXGI_OUTDUPL_MOVE_RECT},
+    Foundation::{HANDLE, RECT},
+    Graphics::Dxgi::{
+      IDXGIOutputDuplication, DXGI_ERROR_MORE_DATA, DXGI_OUTDUPL_MOVE_RECT,
+    },
+    System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency},
   },
+  compare,
+  convert::{self, Layout, Luma, Rotation},
+  cursor::{self, CursorInfo},
   driver::dx11::frame::Dx11FrameData,
-  DirtyRect, Frame, FrameFormat, MovedPoint, MovedRect,
+  overlay,
+  transform::{Crop, Filter, FrameTransform, Scale},
+  DirtyRect, Frame, FrameFormat, FrameTiming, MovedPoint, MovedRect, OwnedFrame,
 };
-use std::{borrow::Cow, cmp::min};
+use std::{
+  borrow::Cow,
+  fmt, mem,
+  time::{Duration, Instant},
+};
+use windows::HRESULT;
 
-#[derive(Debug, Clone)]
+/// A frame captured with the Desktop Duplication API
+///
+/// # Notes
+/// Frames borrow the duplication and COM objects of their capturer, which are bound to
+/// the thread that created them so, a frame is neither `Send` nor `Sync`.  Use
+/// [`DxgiFrame::into_owned`] to get an [`OwnedFrame`] that can cross threads.
+#[derive(Clone)]
 pub struct DxgiFrame<'a> {
   data: DxgiFrameData<'a>,
+  width: usize,
+  height: usize,
+  format: FrameFormat,
   dirty: Option<Vec<DirtyRect>>,
+  timing: Option<FrameTiming>,
+  grayscale: Option<(Luma, FrameFormat)>,
+  overlay: Option<String>,
+  rotation: Rotation,
+  repeat: bool,
+  present_time: i64,
+  accumulated_frames: u32,
+  sequence: u64,
+  generation: u32,
+  cursor: Option<CursorInfo>,
+  draw_cursor: bool,
+  region: Option<Crop>,
+  scale: Option<(usize, usize)>,
+  scale_filter: Option<Filter>,
   duplication: &'a IDXGIOutputDuplication,
 }
 
 impl<'a> DxgiFrame<'a> {
-  pub fn new<D>(data: D, duplication: &'a IDXGIOutputDuplication) -> Self
+  pub fn new<D>(
+    data: D,
+    width: usize,
+    height: usize,
+    duplication: &'a IDXGIOutputDuplication,
+  ) -> Self
   where
     D: Into<DxgiFrameData<'a>>,
   {
     Self {
       data: data.into(),
+      width,
+      height,
+      format: FrameFormat::B8G8R8A8,
       dirty: None,
+      timing: None,
+      grayscale: None,
+      overlay: None,
+      rotation: Rotation::Identity,
+      repeat: false,
+      present_time: 0,
+      accumulated_frames: 0,
+      sequence: 0,
+      generation: 0,
+      cursor: None,
+      draw_cursor: false,
+      region: None,
+      scale: None,
+      scale_filter: None,
       duplication,
     }
   }
 
+  /// Describe the pixels of the desktop image as `format` rather than `B8G8R8A8`
+  pub(super) fn with_format(mut self, format: FrameFormat) -> Self {
+    self.format = format;
+    self
+  }
+
+  /// Attach stage timings recorded while capturing the frame
+  pub(super) fn with_timing(mut self, timing: Option<FrameTiming>) -> Self {
+    self.timing = timing;
+    self
+  }
+
+  /// Convert pixel data into luminance when it is copied
+  pub(super) fn with_grayscale(mut self, grayscale: Option<(Luma, FrameFormat)>) -> Self {
+    self.grayscale = grayscale;
+    self
+  }
+
+  /// Burn text into the top-left corner when pixel data is copied
+  pub(super) fn with_overlay(mut self, overlay: Option<String>) -> Self {
+    self.overlay = overlay;
+    self
+  }
+
+  /// Rotate pixel data and rectangles to match the orientation of the display
+  pub(super) fn with_rotation(mut self, rotation: Rotation) -> Self {
+    self.rotation = rotation;
+    self
+  }
+
+  /// Flag the frame as showing the same desktop image as the previous frame
+  pub(super) fn with_repeat(mut self, repeat: bool) -> Self {
+    self.repeat = repeat;
+    self
+  }
+
+  /// Stamp the frame with when the desktop image was presented and how many presents it
+  /// covers, `LastPresentTime` and `AccumulatedFrames` of `DXGI_OUTDUPL_FRAME_INFO`
+  pub(super) fn with_present(
+    mut self,
+    present_time: i64,
+    accumulated_frames: u32,
+  ) -> Self {
+    self.present_time = present_time;
+    self.accumulated_frames = accumulated_frames;
+    self
+  }
+
+  /// Stamp the frame with its position in capture order
+  pub(super) fn with_sequence(mut self, sequence: u64, generation: u32) -> Self {
+    self.sequence = sequence;
+    self.generation = generation;
+    self
+  }
+
+  /// Attach the cursor seen when the frame was captured, drawing it when pixels are copied
+  /// if `draw` is set
+  pub(super) fn with_cursor(mut self, cursor: Option<CursorInfo>, draw: bool) -> Self {
+    self.cursor = cursor;
+    self.draw_cursor = draw;
+    self
+  }
+
+  /// Translate rectangles into `region` of the desktop image the pixel data covers
+  pub(super) fn with_region(mut self, region: Option<Crop>) -> Self {
+    self.region = region;
+    self
+  }
+
+  /// Scale rectangles from `source`, the size of the region or desktop image before it was
+  /// scaled, to the size of the frame
+  pub(super) fn with_scale(mut self, source: Option<(usize, usize)>) -> Self {
+    self.scale = source;
+    self
+  }
+
+  /// Resize pixel data from the source size given to [`DxgiFrame::with_scale`] to the size
+  /// of the frame with `filter` when it is copied, `None` when it's already scaled
+  pub(super) fn with_scale_filter(mut self, filter: Option<Filter>) -> Self {
+    self.scale_filter = filter;
+    self
+  }
+
   /// Get reference to underlying data
   pub const fn data(&self) -> &DxgiFrameData<'a> {
     &self.data
   }
 
+  /// Get the width of the frame in pixels
+  pub const fn width(&self) -> usize {
+    self.rotation.dimensions(self.width, self.height).0
+  }
+
+  /// Get the height of the frame in pixels
+  pub const fn height(&self) -> usize {
+    self.rotation.dimensions(self.width, self.height).1
+  }
+
+  /// Get how long each stage of capturing this frame took
+  ///
+  /// # Notes
+  /// Only available when [`crate::CaptureConfig::with_timing`] is enabled.
+  pub const fn timing(&self) -> Option<FrameTiming> {
+    self.timing
+  }
+
+  /// Get the position of the frame in capture order
+  ///
+  /// # Notes
+  /// Incremented for every acquired frame, timeouts don't count.  Restarts at zero when the
+  /// capturer is recreated or duplication is recovered after access was lost, see
+  /// [`DxgiFrame::generation`].
+  pub const fn sequence(&self) -> u64 {
+    self.sequence
+  }
+
+  /// Get the capturer instance the frame came from
+  pub const fn generation(&self) -> u32 {
+    self.generation
+  }
+
+  /// Whether the desktop image is unchanged since the previous frame
+  ///
+  /// # Notes
+  /// DXGI hands out a frame when only the pointer changed, repeats have no dirty or moved
+  /// rectangles.  Pixels are only copied off the GPU when [`DxgiFrame::as_bytes`] is
+  /// called so, skipping repeats avoids the copy entirely.
+  pub const fn is_repeat(&self) -> bool {
+    self.repeat
+  }
+
+  /// Get when the desktop image was last presented in `QueryPerformanceCounter` ticks
+  ///
+  /// # Notes
+  /// Zero for repeats, see [`DxgiFrame::is_repeat`].  Timestamp encoded frames with this
+  /// rather than the time they were captured, which lags behind by however long capturing
+  /// took.
+  pub const fn present_time(&self) -> i64 {
+    self.present_time
+  }
+
+  /// Get when the desktop image was last presented as an [`Instant`]
+  ///
+  /// # Notes
+  /// `None` for repeats or when the performance counter can't be read.
+  pub fn present_instant(&self) -> Option<Instant> {
+    if self.present_time == 0 {
+      return None;
+    }
+
+    let (mut now, mut frequency) = (0, 0);
+    let read = unsafe {
+      QueryPerformanceCounter(&mut now).as_bool()
+        && QueryPerformanceFrequency(&mut frequency).as_bool()
+    };
+
+    // Step back from now by how long ago the present happened in performance counter time
+    let ago = (now - self.present_time).max(0) as u128;
+    let ago =
+      Duration::from_nanos((ago * 1_000_000_000 / frequency.max(1) as u128) as u64);
+
+    read.then(|| Instant::now().checked_sub(ago)).flatten()
+  }
+
+  /// Get how many times the desktop image was presented since the previous frame
+  ///
+  /// # Notes
+  /// More than one means presents happened between acquires and were merged into this
+  /// frame, zero for repeats.
+  pub const fn accumulated_frames(&self) -> u32 {
+    self.accumulated_frames
+  }
+
+  /// Get where the cursor was when the frame was captured
+  ///
+  /// # Notes
+  /// The position is in the native orientation of the display.  The shape is only
+  /// included when [`crate::CaptureConfig::with_cursor_shape`] is enabled.  `None` until
+  /// the cursor has moved over the display once since the capturer was created.
+  pub fn cursor(&self) -> Option<CursorInfo> {
+    self.cursor.clone()
+  }
+
+  /// Get the region of the desktop image the frame covers, clamped to its bounds
+  pub const fn region(&self) -> Option<Crop> {
+    self.region
+  }
+
   /// Get rectangles where pixels have changed since last frame
-  pub fn dirty(&self) -> Vec<DirtyRect> {
-    unsafe { self.get_dirty_rects() }
+  ///
+  /// # Notes
+  /// Fails with [`FrameError::TooManyRects`] rather than dropping rectangles when more
+  /// changed than are read.
+  pub fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    let dirty = match (self.region, self.scale) {
+      (_, Some(source)) => self.scaled_rects(source)?,
+      (None, None) => unsafe { self.get_dirty_rects()? },
+      (Some(region), None) => self.region_rects(region)?.0,
+    };
+
+    Ok(match self.rotation {
+      Rotation::Identity => dirty,
+      rotation => dirty
+        .into_iter()
+        .map(|rect| rotation.rect(rect, self.width, self.height))
+        .collect(),
+    })
   }
 
   /// Get rectangles where pixels have moved since last frame
-  pub fn moved(&self) -> Vec<MovedRect> {
-    unsafe { self.get_moved_rects() }
+  ///
+  /// # Notes
+  /// Fails with [`FrameError::TooManyRects`] rather than dropping rectangles when more
+  /// moved than are read.
+  pub fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+    let moved = match (self.region, self.scale) {
+      (_, Some(_)) => Vec::new(),
+      (None, None) => unsafe { self.get_moved_rects()? },
+      (Some(region), None) => self.region_rects(region)?.1,
+    };
+
+    Ok(match self.rotation {
+      Rotation::Identity => moved,
+      rotation => moved
+        .into_iter()
+        .map(|moved| {
+          // The source is the same size as the destination, rotate it as a rectangle to
+          // find its new top-left corner
+          let to = moved.to;
+          let from = DirtyRect::from_ltrb(
+            moved.from.x,
+            moved.from.y,
+            moved.from.x + to.width(),
+            moved.from.y + to.height(),
+          );
+          let from = rotation.rect(from, self.width, self.height);
+
+          MovedRect::new(
+            rotation.rect(to, self.width, self.height),
+            MovedPoint::new(from.left, from.top),
+          )
+        })
+        .collect(),
+    })
   }
 
   /// Get pixel format of underlying data
   ///
   /// # Notes
   /// Per the Microsoft DesktopDuplication API documentation the format of the desktop
-  /// image is always `DXGI_FORMAT_B8G8R8A8_UNORM` which translates to `B8G8R8A8`.
+  /// image is always `DXGI_FORMAT_B8G8R8A8_UNORM` which translates to `B8G8R8A8`, unless
+  /// grayscale conversion was requested.  With [`crate::CaptureConfig::with_hdr`]
+  /// enabled HDR displays produce [`FrameFormat::R16G16B16A16Float`] (scRGB) or
+  /// [`FrameFormat::R10G10B10A2`] instead.
   ///
   /// https://docs.microsoft.com/en-us/windows/win32/direct3ddxgi/desktop-dup-api#updating-the-desktop-image-data
   pub const fn format(&self) -> FrameFormat {
-    FrameFormat::B8G8R8A8
+    match self.grayscale {
+      Some((_, format)) => format,
+      None => self.format,
+    }
   }
 
   /// Get pixel data
   ///
   /// # Notes
-  /// When frame data is [`DxgiFrameData::DirectX`] texture is copied to CPU memory and
-  /// returned.  No caching occurs so, if you plan on using this multiple times you should
-  /// probably cache the result yourself.  
+  /// When frame data is [`DxgiFrameData::Memory`] and no conversion was requested the
+  /// desktop image is borrowed, it is only valid until the next frame is acquired.  Every
+  /// other case, including all [`DxgiFrameData::DirectX`] frames, copies the pixels into
+  /// an owned buffer so, [`Cow::into_owned`] (or [`DxgiFrame::to_vec`]) is free.  No
+  /// caching occurs so, if you plan on using this multiple times you should probably cache
+  /// the result yourself.
+  ///
+  /// Grayscale, rotated and frames scaled on the CPU are converted while copying so, they
+  /// are always tightly packed.
+  /// Overlays and the cursor are drawn on a copy of the pixel data, never on the mapped
+  /// desktop image.
   pub fn as_bytes(&self) -> anyhow::Result<Cow<'a, [u8]>> {
+    // Size of a font pixel, large enough to stay legible after video compression
+    const OVERLAY_SCALE: usize = 2;
+
+    let bytes = match &self.data {
+      DxgiFrameData::Memory(buf) => Cow::from(*buf),
+      DxgiFrameData::DirectX(texture) => Cow::from(texture.get_bytes()?),
+    };
+
+    // Positions were already scaled so, resizing happens before anything is drawn
+    let bytes = match (self.scale_filter, self.scale) {
+      (Some(filter), Some((width, height))) => {
+        let layout = Layout::infer(width, height, self.format, bytes.len());
+        let mut data = alloc::allocate(layout.row_len() * height);
+        layout
+          .rows(&bytes)
+          .for_each(|row| data.extend_from_slice(row));
+
+        let source = OwnedFrame::new(width, height, self.format, data);
+        let scaled = Scale::new(self.width, self.height)
+          .with_filter(filter)
+          .apply(&&source)?;
+
+        Cow::from(scaled.into_data())
+      }
+      _ => bytes,
+    };
+
+    // The cursor is positioned on the desktop image so, it's drawn before conversions
+    let bytes = match &self.cursor {
+      Some(pointer) if self.draw_cursor => {
+        let mut bytes = bytes.into_owned();
+        let layout = Layout::infer(self.width, self.height, self.format, bytes.len());
+        cursor::draw(&mut bytes, layout, pointer)?;
+
+        Cow::from(bytes)
+      }
+      _ => bytes,
+    };
+
+    let bytes = match self.grayscale {
+      None => bytes,
+      Some((luma, format)) => {
+        let layout = Layout::infer(self.width, self.height, self.format, bytes.len());
+
+        Cow::from(convert::to_luma(&bytes, layout, luma, format)?)
+      }
+    };
+
+    let bytes = match self.rotation {
+      Rotation::Identity => bytes,
+      rotation => {
+        let layout = Layout::infer(self.width, self.height, self.format(), bytes.len());
+
+        Cow::from(convert::rotate(&bytes, layout, rotation)?)
+      }
+    };
+
+    match &self.overlay {
+      None => Ok(bytes),
+      Some(text) => {
+        let mut bytes = bytes.into_owned();
+        let layout =
+          Layout::infer(self.width(), self.height(), self.format(), bytes.len());
+        overlay::draw_text(&mut bytes, layout, text, OVERLAY_SCALE)?;
+
+        Ok(Cow::from(bytes))
+      }
+    }
+  }
+
+  /// Pass pixel data to `f` without copying it into a buffer first
+  ///
+  /// # Arguments
+  /// * `f` - Reads the pixels, which are laid out as described by the [`Layout`] passed
+  ///   along
+  ///
+  /// # Notes
+  /// [`DxgiFrameData::Memory`] frames pass the desktop image and
+  /// [`DxgiFrameData::DirectX`] frames the mapped staging texture, which is unmapped once
+  /// `f` returns.  Both keep their row padding.  Converted frames fall back to
+  /// [`DxgiFrame::as_bytes`].
+  pub fn with_bytes<F, R>(&self, f: F) -> anyhow::Result<R>
+  where
+    F: FnOnce(&[u8], Layout) -> R,
+  {
+    match (&self.data, self.is_converted()) {
+      (DxgiFrameData::DirectX(texture), false) => texture.map(f),
+      _ => {
+        // Borrows the desktop image when nothing needs converting
+        let bytes = self.as_bytes()?;
+        let layout =
+          Layout::new(self.width(), self.height(), self.stride(), self.format());
+        layout.check(&bytes)?;
+
+        Ok(f(&bytes, layout))
+      }
+    }
+  }
+
+  /// Copy pixel data into `buf` without row padding
+  ///
+  /// # Notes
+  /// `buf` is cleared first and only grows when the frame doesn't fit.  Unconverted
+  /// frames are copied straight from the desktop image or staging texture, every other
+  /// case goes through [`DxgiFrame::as_bytes`] first.
+  pub fn copy_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+    self.with_bytes(|bytes, layout| {
+      buf.clear();
+      buf.reserve(layout.row_len() * layout.height);
+      for row in layout.rows(bytes) {
+        buf.extend_from_slice(row);
+      }
+    })
+  }
+
+  /// Whether the pixel data is changed while copying it out of the desktop image
+  fn is_converted(&self) -> bool {
+    self.grayscale.is_some()
+      || self.rotation != Rotation::Identity
+      || self.scale_filter.is_some()
+      || self.overlay.is_some()
+      || (self.draw_cursor && self.cursor.is_some())
+  }
+
+  /// Get the number of bytes between the start of two rows of pixel data
+  ///
+  /// # Notes
+  /// Only [`DxgiFrameData::Memory`] frames keep the pitch of the desktop image, which is
+  /// frequently padded for alignment.  Every other case is tightly packed.
+  pub fn stride(&self) -> usize {
+    let converted = self.grayscale.is_some()
+      || self.rotation != Rotation::Identity
+      || self.scale_filter.is_some();
+
+    match &self.data {
+      DxgiFrameData::Memory(buf) if !converted => {
+        Layout::infer(self.width, self.height, self.format(), buf.len()).stride
+      }
+      _ => self.width() * self.format().bytes_per_pixel(),
+    }
+  }
+
+  /// Copy only the region that changed since the last frame
+  ///
+  /// # Notes
+  /// The crop covers every dirty and moved rectangle, `None` means nothing changed.  The
+  /// first frame of a capturer has nothing to compare against so, it is never cropped.
+  /// Only the rows of the crop are read out of the desktop image or staging texture,
+  /// converted frames are still copied whole first, see [`DxgiFrame::with_bytes`].
+  pub fn dirty_crop(&self) -> anyhow::Result<Option<OwnedFrame>> {
+    let bounds = match self.sequence {
+      0 => Some(DirtyRect::from_ltrb(
+        0,
+        0,
+        self.width() as i32,
+        self.height() as i32,
+      )),
+      _ => {
+        let moved = self.moved()?.into_iter().map(|rect| rect.to);
+        let rects: Vec<_> = self.dirty()?.into_iter().chain(moved).collect();
+
+        compare::bounding_box(rects, self.width(), self.height())
+      }
+    };
+
+    let bounds = match bounds {
+      Some(bounds) => bounds,
+      None => return Ok(None),
+    };
+
+    let format = self.format();
+    let bpp = format.bytes_per_pixel();
+    let (left, right) = (bounds.left as usize * bpp, bounds.right as usize * bpp);
+    let (top, height) = (bounds.top as usize, (bounds.bottom - bounds.top) as usize);
+
+    let data = self.with_bytes(|bytes, layout| {
+      let mut data = alloc::allocate((right - left) * height);
+      for row in layout.rows(bytes).skip(top).take(height) {
+        data.extend_from_slice(&row[left..right]);
+      }
+
+      data
+    })?;
+
+    Ok(Some(OwnedFrame::new(
+      (right - left) / bpp,
+      height,
+      format,
+      data,
+    )))
+  }
+
+  /// Share the frame with another device or process without copying it off the GPU
+  ///
+  /// # Notes
+  /// The desktop texture can't be shared itself so, it's copied into a new texture
+  /// created with `D3D11_RESOURCE_MISC_SHARED_NTHANDLE` on every call.  Open the handle
+  /// with `ID3D11Device1::OpenSharedResource1` and acquire its keyed mutex with
+  /// [`super::shared::READ_KEY`] before reading.  The handle belongs to the caller, close
+  /// it with `CloseHandle`.  Regions, rotation, overlays and the cursor aren't applied.
+  /// Fails with [`FrameError::NotOnGpu`] when the desktop image is in system memory.
+  pub fn shared_handle(&self) -> anyhow::Result<HANDLE> {
     match &self.data {
-      DxgiFrameData::Memory(buf) => Ok(Cow::from(*buf)),
-      DxgiFrameData::DirectX(texture) => Ok(Cow::from(texture.get_bytes()?)),
+      DxgiFrameData::DirectX(texture) => {
+        Ok(unsafe { shared::share_texture(texture.texture())? })
+      }
+      DxgiFrameData::Memory(_) => Err(FrameError::NotOnGpu.into()),
     }
   }
 
+  /// Get pixel data that outlives the frame
+  ///
+  /// # Notes
+  /// Only copies when [`DxgiFrame::as_bytes`] would borrow the desktop image.
+  pub fn to_vec(&self) -> anyhow::Result<Vec<u8>> {
+    Ok(self.as_bytes()?.into_owned())
+  }
+
+  /// Copy the frame into an [`OwnedFrame`] detached from the capturer
+  ///
+  /// # Notes
+  /// The pixels are copied without row padding along with the dimensions, format, dirty
+  /// and moved rectangles so, the result can be kept while the next frame is captured.
+  pub fn into_owned(self) -> anyhow::Result<OwnedFrame> {
+    OwnedFrame::from_frame(&self)
+  }
+
   /// Convert into underlying data
   pub fn into_data(self) -> DxgiFrameData<'a> {
     self.data
   }
 
-  /// Gets dirty rectangles from [`IDXGIOutputDuplication`] while ignoring errors and doing
-  /// best effort minimizing amount of memory while allowing further growth when needed.
+  /// Get dirty and moved rectangles relative to `region`
+  ///
+  /// # Notes
+  /// Rectangles are clipped to the region.  A move with either side leaving the region
+  /// can't be replayed from the frame alone so, its destination is reported as dirty.
+  fn region_rects(
+    &self,
+    region: Crop,
+  ) -> Result<(Vec<DirtyRect>, Vec<MovedRect>), FrameError> {
+    let bounds = region.rect();
+    let offset = |rect: DirtyRect| {
+      DirtyRect::from_ltrb(
+        rect.left - bounds.left,
+        rect.top - bounds.top,
+        rect.right - bounds.left,
+        rect.bottom - bounds.top,
+      )
+    };
+    let clip = |rect: DirtyRect| rect.intersection(&bounds).map(offset);
+    let contains = |rect: DirtyRect| {
+      rect.left >= bounds.left
+        && rect.top >= bounds.top
+        && rect.right <= bounds.right
+        && rect.bottom <= bounds.bottom
+    };
+
+    let mut dirty: Vec<_> = unsafe { self.get_dirty_rects()? }
+      .into_iter()
+      .filter_map(clip)
+      .collect();
+    let mut moved = Vec::new();
+
+    for rect in unsafe { self.get_moved_rects()? } {
+      let to = rect.to;
+      let from = DirtyRect::from_ltrb(
+        rect.from.x,
+        rect.from.y,
+        rect.from.x + to.width(),
+        rect.from.y + to.height(),
+      );
+
+      if contains(to) && contains(from) {
+        moved.push(MovedRect::new(
+          offset(to),
+          MovedPoint::new(rect.from.x - bounds.left, rect.from.y - bounds.top),
+        ));
+      } else if let Some(to) = clip(to) {
+        dirty.push(to);
+      }
+    }
+
+    Ok((dirty, moved))
+  }
+
+  /// Get dirty rectangles scaled from `source` to the size of the frame
+  ///
+  /// # Notes
+  /// Rectangles are grown to whole pixels.  Scaled moves don't land on whole pixels so,
+  /// their destinations are reported as dirty instead.
+  fn scaled_rects(&self, source: (usize, usize)) -> Result<Vec<DirtyRect>, FrameError> {
+    let (dirty, moved) = match self.region {
+      None => unsafe { (self.get_dirty_rects()?, self.get_moved_rects()?) },
+      Some(region) => self.region_rects(region)?,
+    };
+
+    let floor = |value: i32, output: usize, source: usize| {
+      (value as i64 * output as i64 / source as i64) as i32
+    };
+    let ceil = |value: i32, output: usize, source: usize| {
+      ((value as i64 * output as i64 + source as i64 - 1) / source as i64) as i32
+    };
+
+    Ok(
+      dirty
+        .into_iter()
+        .chain(moved.into_iter().map(|moved| moved.to))
+        .map(|rect| {
+          DirtyRect::from_ltrb(
+            floor(rect.left, self.width, source.0),
+            floor(rect.top, self.height, source.1),
+            ceil(rect.right, self.width, source.0),
+            ceil(rect.bottom, self.height, source.1),
+          )
+        })
+        .collect(),
+    )
+  }
+
+  /// Gets dirty rectangles from [`IDXGIOutputDuplication`], growing the buffer until
+  /// every rectangle fits.
   ///
   /// At some point I may consider caching [`RECT`] buffer and translated [`FrameRect`]
   /// items in [`DxgiFrame`] but, for the time being I'll let the end user decide where and
   /// how data is stored (with the exception of the initial allocations ofc)
-  unsafe fn get_dirty_rects(&self) -> Vec<DirtyRect> {
-    // Default rectangle buffer size (comes out to 2KB)
-    const RECT_BUF_LEN: usize = 16;
-    // Maximum rectangle buffer size (comes out to ~1MB)
-    const RECT_BUF_MAX_LEN: usize = 7000 - RECT_BUF_LEN;
-
-    let mut dirty = vec![RECT::default(); RECT_BUF_LEN];
-    let mut dirty_len = 0;
-    let _ = self.duplication.GetFrameDirtyRects(
-      dirty.len() as _,
-      dirty.as_mut_ptr(),
-      &mut dirty_len,
-    );
-
-    let more = (dirty_len as usize).saturating_sub(dirty.len());
-    let more = min(RECT_BUF_MAX_LEN, more);
-
-    // `RECT_LEN` rectangles is not enough, try extending dirty
-    if more > 0 {
-      dirty.extend_from_slice(&vec![RECT::default(); more]);
-
-      let _ = self.duplication.GetFrameDirtyRects(
-        dirty.len() as _,
-        dirty.as_mut_ptr(),
-        &mut dirty_len,
-      );
-    }
+  unsafe fn get_dirty_rects(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    let dirty = read_rects(|size, buf: *mut RECT, required| {
+      self.duplication.GetFrameDirtyRects(size, buf, required)
+    })?;
 
     // I would _love_ if rust/llvm would optimize this away into a transparent type rather
     // than looping over a structure and mapping it into a structure that looks exactly the
     // same. I know Quartz, x11, and Wayland will have different definitions so we need a
     // generic type that will handle this and I _really_ don't want to add another nested
     // type definition to the trait tree for [`Frame`].
-    dirty
-      .into_iter()
-      .take(dirty_len as usize)
-      .map(|rect| DirtyRect::new(rect.top, rect.right, rect.bottom, rect.left))
-      .collect()
-  }
-
-  unsafe fn get_moved_rects(&self) -> Vec<MovedRect> {
-    // Default rectangle buffer size (comes out to 2KB)
-    const RECT_BUF_LEN: usize = 16;
-    // Maximum rectangle buffer size (comes out to ~1MB)
-    const RECT_BUF_MAX_LEN: usize = 7000 - RECT_BUF_LEN;
-
-    let mut moved = vec![DXGI_OUTDUPL_MOVE_RECT::default(); RECT_BUF_LEN];
-    let mut moved_len = 0;
-    let _ = self.duplication.GetFrameMoveRects(
-      moved.len() as _,
-      moved.as_mut_ptr(),
-      &mut moved_len,
-    );
-
-    let more = (moved_len as usize).saturating_sub(moved.len());
-    let more = min(RECT_BUF_MAX_LEN, more);
-
-    // `RECT_LEN` rectangles is not enough, try extending dirty
-    if more > 0 {
-      moved.extend_from_slice(&vec![DXGI_OUTDUPL_MOVE_RECT::default(); more]);
-
-      let _ = self.duplication.GetFrameMoveRects(
-        moved.len() as _,
-        moved.as_mut_ptr(),
-        &mut moved_len,
-      );
+    Ok(
+      dirty
+        .into_iter()
+        .map(|rect| DirtyRect::from_ltrb(rect.left, rect.top, rect.right, rect.bottom))
+        .collect(),
+    )
+  }
+
+  /// Gets moved rectangles from [`IDXGIOutputDuplication`], growing the buffer until
+  /// every rectangle fits.
+  unsafe fn get_moved_rects(&self) -> Result<Vec<MovedRect>, FrameError> {
+    let moved = read_rects(|size, buf: *mut DXGI_OUTDUPL_MOVE_RECT, required| {
+      self.duplication.GetFrameMoveRects(size, buf, required)
+    })?;
+
+    Ok(
+      moved
+        .into_iter()
+        .map(|moved| {
+          MovedRect::new(
+            DirtyRect::from_ltrb(
+              moved.DestinationRect.left,
+              moved.DestinationRect.top,
+              moved.DestinationRect.right,
+              moved.DestinationRect.bottom,
+            ),
+            MovedPoint::new(moved.SourcePoint.x, moved.SourcePoint.y),
+          )
+        })
+        .collect(),
+    )
+  }
+}
+
+/// Read rectangles with `read`, growing the buffer until they all fit
+///
+/// # Arguments
+/// * `read` - Calls `GetFrameDirtyRects` or `GetFrameMoveRects` with the size of the
+///   buffer in bytes, the buffer and where to write the size it needs in bytes
+///
+/// # Notes
+/// Fails with [`FrameError::TooManyRects`] when more than `RECT_BUF_MAX_LEN` rectangles
+/// would have to be read, so rectangles are never silently dropped.
+///
+/// # Safety
+/// Calls to DXGI
+unsafe fn read_rects<T, F>(mut read: F) -> Result<Vec<T>, FrameError>
+where
+  T: Clone + Default,
+  F: FnMut(u32, *mut T, *mut u32) -> HRESULT,
+{
+  // Default rectangle buffer size (comes out to 256B)
+  const RECT_BUF_LEN: usize = 16;
+  // Maximum rectangle buffer size (comes out to ~110KB)
+  const RECT_BUF_MAX_LEN: usize = 7000;
+
+  let mut buf = vec![T::default(); RECT_BUF_LEN];
+
+  // The required size can change between calls so, keep growing until a call succeeds
+  loop {
+    let mut required = 0;
+    let size = (buf.len() * mem::size_of::<T>()) as u32;
+    let hr = read(size, buf.as_mut_ptr(), &mut required);
+    let len = required as usize / mem::size_of::<T>();
+
+    if hr == DXGI_ERROR_MORE_DATA {
+      if len > RECT_BUF_MAX_LEN {
+        return Err(FrameError::TooManyRects(len, RECT_BUF_MAX_LEN));
+      }
+
+      buf.resize(len.max(buf.len() + 1), T::default());
+      continue;
     }
 
-    // I would _love_ if rust/llvm would optimize this away into a transparent type rather
-    // than looping over a structure and mapping it into a structure that looks exactly the
-    // same. I know Quartz, x11, and Wayland will have different definitions so we need a
-    // generic type that will handle this and I _really_ don't want to add another nested
-    // type definition to the trait tree for [`Frame`].
-    moved
-      .into_iter()
-      .take(moved_len as usize)
-      .map(|moved| {
-        MovedRect::new(
-          DirtyRect::new(
-            moved.DestinationRect.top,
-            moved.DestinationRect.right,
-            moved.DestinationRect.bottom,
-            moved.DestinationRect.left,
-          ),
-          MovedPoint::new(moved.SourcePoint.x, moved.SourcePoint.y),
-        )
-      })
-      .collect()
+    hr.ok()?;
+    buf.truncate(len);
+
+    return Ok(buf);
+  }
+}
+
+/// Summarizes the frame rather than printing the pixels of frames in system memory
+impl fmt::Debug for DxgiFrame<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let on_gpu = matches!(self.data, DxgiFrameData::DirectX(_));
+
+    f.debug_struct("DxgiFrame")
+      .field("width", &self.width())
+      .field("height", &self.height())
+      .field("format", &self.format())
+      .field("stride", &self.stride())
+      .field("on_gpu", &on_gpu)
+      .field("dirty", &self.dirty().map(|dirty| dirty.len()).ok())
+      .field("rotation", &self.rotation)
+      .field("region", &self.region)
+      .field("repeat", &self.repeat)
+      .field("sequence", &self.sequence)
+      .field("generation", &self.generation)
+      .finish()
   }
 }
 
 impl<'frame> Frame<'frame> for DxgiFrame<'frame> {
-  fn dirty(&self) -> Vec<DirtyRect> {
-    self.dirty()
+  fn width(&self) -> usize {
+    self.width()
+  }
+
+  fn height(&self) -> usize {
+    self.height()
   }
 
-  fn moved(&self) -> Vec<MovedRect> {
-    self.moved()
+  fn dirty(&self) -> Result<Vec<DirtyRect>, crate::errors::FrameError> {
+    Ok(self.dirty()?)
+  }
+
+  fn moved(&self) -> Result<Vec<MovedRect>, crate::errors::FrameError> {
+    Ok(self.moved()?)
   }
 
   fn format(&self) -> FrameFormat {
@@ -184,6 +827,30 @@ impl<'frame> Frame<'frame> for DxgiFrame<'frame> {
   fn as_bytes(&self) -> anyhow::Result<Cow<'frame, [u8]>> {
     self.as_bytes()
   }
+
+  fn stride(&self) -> usize {
+    self.stride()
+  }
+
+  fn copy_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+    self.copy_into(buf)
+  }
+
+  fn sequence(&self) -> u64 {
+    self.sequence()
+  }
+
+  fn generation(&self) -> u32 {
+    self.generation()
+  }
+
+  fn is_repeat(&self) -> bool {
+    self.is_repeat()
+  }
+
+  fn cursor(&self) -> Option<CursorInfo> {
+    self.cursor()
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -203,3 +870,55 @@ impl<'frame> From<Dx11FrameData<'frame>> for DxgiFrameData<'frame> {
     Self::DirectX(data)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::read_rects;
+  use crate::{
+    bindings::Windows::Win32::{Foundation::RECT, Graphics::Dxgi::DXGI_ERROR_MORE_DATA},
+    driver::dxgi::errors::FrameError,
+  };
+  use std::mem;
+  use windows::HRESULT;
+
+  #[test]
+  fn test_read_rects_grows() {
+    // More rectangles are needed on each of the first two calls
+    let mut needed = vec![20, 40, 40].into_iter();
+    let rects = unsafe {
+      read_rects(|size, buf: *mut RECT, required| {
+        let len = needed.next().unwrap();
+        *required = (len * mem::size_of::<RECT>()) as u32;
+
+        if (size as usize) < len * mem::size_of::<RECT>() {
+          return DXGI_ERROR_MORE_DATA;
+        }
+
+        for i in 0..len {
+          *buf.add(i) = RECT {
+            left: i as i32,
+            ..Default::default()
+          };
+        }
+
+        HRESULT(0)
+      })
+    }
+    .unwrap();
+
+    assert_eq!(rects.len(), 40);
+    assert_eq!(rects[39].left, 39);
+  }
+
+  #[test]
+  fn test_read_rects_too_many() {
+    let result = unsafe {
+      read_rects(|_, _: *mut RECT, required| {
+        *required = (10_000 * mem::size_of::<RECT>()) as u32;
+        DXGI_ERROR_MORE_DATA
+      })
+    };
+
+    assert!(matches!(result, Err(FrameError::TooManyRects(10_000, _))));
+  }
+}
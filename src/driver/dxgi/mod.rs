@@ -2,5 +2,138 @@ pub mod capture;
 pub mod display;
 pub mod errors;
 pub mod frame;
+pub mod probe;
+pub mod processor;
+pub mod shared;
 
+use crate::{
+  bindings::Windows::Win32::Graphics::Gdi::HMONITOR, errors::DisplayError, DisplayDriver,
+  OwnedFrame,
+};
+use display::{DxgiDisplay, DxgiDisplays};
+
+/// The Desktop Duplication API display driver
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Dxgi;
+
+impl<'buf> DisplayDriver<'buf> for Dxgi {
+  type Display = DxgiDisplay;
+
+  fn name(&self) -> &'static str {
+    "dxgi"
+  }
+
+  fn all(&self) -> Result<Vec<Self::Display>, DisplayError> {
+    Ok(displays()?.collect::<windows::Result<_>>()?)
+  }
+
+  fn primary(&self) -> Result<Option<Self::Display>, DisplayError> {
+    Ok(displays()?.primary()?)
+  }
+}
+
+impl Dxgi {
+  /// Gets all displays along with why any outputs were skipped
+  ///
+  /// # Notes
+  /// See [`DxgiDisplays::skipped`], [`DisplayDriver::all`] leaves the skipped outputs out.
+  pub fn all_with_skipped(
+    &self,
+  ) -> Result<(Vec<DxgiDisplay>, Vec<windows::Error>), DisplayError> {
+    let mut displays = displays()?;
+    let all = (&mut displays).collect::<windows::Result<_>>()?;
+
+    Ok((all, displays.skipped().to_vec()))
+  }
+
+  /// Find the display with the GDI device name `name`, e.g. `\\.\DISPLAY1`
+  ///
+  /// # Notes
+  /// Fails with [`DisplayError::NotFound`] when no display is named `name`.
+  pub fn by_name(&self, name: &str) -> Result<DxgiDisplay, DisplayError> {
+    displays()?
+      .by_name(name)?
+      .ok_or_else(|| DisplayError::NotFound(name.to_owned()))
+  }
+
+  /// Find the display shown on the monitor `hmonitor`
+  ///
+  /// # Notes
+  /// Fails with [`DisplayError::NotFound`] when no display is shown on `hmonitor`.
+  pub fn by_hmonitor(&self, hmonitor: HMONITOR) -> Result<DxgiDisplay, DisplayError> {
+    displays()?
+      .by_hmonitor(hmonitor)?
+      .ok_or_else(|| DisplayError::NotFound(format!("HMONITOR {:#x}", hmonitor.0)))
+  }
+}
+
+/// Capture every display into a single frame laid out as the displays are arranged
+///
+/// # Notes
+/// The frame covers the bounding box of every display on the virtual desktop, see
+/// [`OwnedFrame::stitch`].  Displays are captured with rotation applied so, their frames
+/// match their desktop coordinates.
+pub fn capture_virtual_desktop() -> anyhow::Result<OwnedFrame> {
+  let mut frames = Vec::new();
+
+  for mut display in Dxgi.all()? {
+    let config = display.config().clone().with_apply_rotation(true);
+    display.set_config(config);
+
+    frames.push((display.position(), display.snapshot()?));
+  }
+
+  OwnedFrame::stitch(&frames)
+}
+
+/// Start enumerating displays, failing when there isn't any adapter to enumerate
+fn displays() -> Result<DxgiDisplays, DisplayError> {
+  let displays = DxgiDisplays::new()?;
+
+  match displays.has_adapters() {
+    true => Ok(displays),
+    false => Err(DisplayError::NoAdapters),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{capture_virtual_desktop, Dxgi};
+  use crate::{errors::DisplayError, DisplayDriver};
+
+  #[test]
+  fn test_dxgi_driver() {
+    let displays = Dxgi.all().unwrap();
+    let primary = Dxgi.primary().unwrap().unwrap();
+
+    assert!(!displays.is_empty());
+    assert!(displays
+      .iter()
+      .any(|display| display.name() == primary.name()));
+
+    let found = Dxgi.by_hmonitor(primary.hmonitor()).unwrap();
+    assert_eq!(found.name(), primary.name());
+    assert!(Dxgi.by_name(&primary.name()).is_ok());
+    assert_eq!(
+      Dxgi.by_name("\\\\.\\DISPLAY0").err(),
+      Some(DisplayError::NotFound("\\\\.\\DISPLAY0".to_owned()))
+    );
+
+    let (all, _) = Dxgi.all_with_skipped().unwrap();
+    assert_eq!(all.len(), displays.len());
+  }
+
+  #[test]
+  fn test_capture_virtual_desktop() {
+    let displays = Dxgi.all().unwrap();
+    let frame = capture_virtual_desktop().unwrap();
+
+    // Every display fits in the stitched frame
+    assert!(displays
+      .iter()
+      .all(|display| display.width() <= frame.width()));
+    assert!(displays
+      .iter()
+      .all(|display| display.height() <= frame.height()));
+  }
+}
@@ -0,0 +1,84 @@
+//! Provides checks of what the current environment allows capturing with DXGI
+
+use super::{
+  capture::{create_device, create_device_with, DxgiDisplayCapturer},
+  display::DxgiDisplay,
+  errors::{FrameError, E_ACCESSDENIED},
+};
+use crate::bindings::Windows::Win32::{
+  Graphics::{
+    Direct3D11::{D3D11_CREATE_DEVICE_DEBUG, D3D_DRIVER_TYPE_NULL, D3D_DRIVER_TYPE_WARP},
+    Dxgi::{DXGI_ERROR_NOT_CURRENTLY_AVAILABLE, DXGI_ERROR_SDK_COMPONENT_MISSING},
+  },
+  UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION},
+};
+/// Whether the process runs in a Remote Desktop session
+///
+/// # Notes
+/// Duplication of a remote session captures the remote desktop, which is often slow or
+/// unavailable depending on the RDP client.
+pub fn is_remote_session() -> bool {
+  unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+}
+
+/// Whether the WARP software rasterizer can create a device
+pub fn warp_available() -> bool {
+  unsafe { create_device(None, D3D_DRIVER_TYPE_WARP).is_ok() }
+}
+
+/// Whether the Direct3D debug layer is installed, `None` when it couldn't be determined
+///
+/// # Notes
+/// Debug builds create capture devices with the debug layer when it's installed, which
+/// takes the Graphics Tools optional feature, and without it otherwise.
+pub fn debug_layer_installed() -> Option<bool> {
+  // The null driver needs no hardware so, it only fails on the debug layer.  Capture's own
+  // device creation falls back without the layer so, it can't tell
+  let created =
+    unsafe { create_device_with(None, D3D_DRIVER_TYPE_NULL, D3D11_CREATE_DEVICE_DEBUG) };
+
+  match created {
+    Ok(_) => Some(true),
+    Err(FrameError::Unexpected(err))
+      if err.code() == DXGI_ERROR_SDK_COMPONENT_MISSING =>
+    {
+      Some(false)
+    }
+    Err(_) => None,
+  }
+}
+
+/// Check whether `display` can be duplicated by creating and dropping a capturer
+pub fn duplication(display: &DxgiDisplay) -> Result<(), FrameError> {
+  unsafe { DxgiDisplayCapturer::new(display) }.map(drop)
+}
+
+/// Whether an error means capture is blocked by access rights or policy rather than
+/// failing outright
+pub fn is_blocked(err: &FrameError) -> bool {
+  match err {
+    FrameError::SecureDesktop(_) => true,
+    FrameError::AcquireFrame(err) | FrameError::Unexpected(err) => {
+      err.code() == E_ACCESSDENIED || err.code() == DXGI_ERROR_NOT_CURRENTLY_AVAILABLE
+    }
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{debug_layer_installed, duplication, is_remote_session};
+  use crate::driver::dxgi::display::DxgiDisplays;
+
+  #[test]
+  fn test_probe() {
+    let _ = is_remote_session();
+
+    // Whether or not the debug layer is installed, the null driver answers either way
+    assert!(debug_layer_installed().is_some());
+
+    for display in DxgiDisplays::new().unwrap() {
+      duplication(&display.unwrap()).unwrap();
+    }
+  }
+}
@@ -0,0 +1,243 @@
+//! Provides converting and scaling captured frames on the GPU
+//!
+//! Conversions run on the video processor of the capture device, the same fixed function
+//! hardware video players use for color conversion and scaling, so pixels never leave the
+//! GPU and no shader has to be compiled or shipped.
+
+use super::errors::FrameError;
+use crate::bindings::Windows::Win32::{
+  Foundation::RECT,
+  Graphics::{
+    Direct3D11::{
+      ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, ID3D11VideoContext,
+      ID3D11VideoDevice, ID3D11VideoProcessor, ID3D11VideoProcessorEnumerator,
+      ID3D11VideoProcessorOutputView, D3D11_BIND_RENDER_TARGET,
+      D3D11_BIND_SHADER_RESOURCE, D3D11_TEX2D_VPIV, D3D11_TEX2D_VPOV,
+      D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT, D3D11_VIDEO_FRAME_FORMAT_PROGRESSIVE,
+      D3D11_VIDEO_PROCESSOR_COLOR_SPACE, D3D11_VIDEO_PROCESSOR_CONTENT_DESC,
+      D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC, D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC_0,
+      D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC, D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC_0,
+      D3D11_VIDEO_PROCESSOR_STREAM, D3D11_VIDEO_USAGE_OPTIMAL_SPEED,
+      D3D11_VPIV_DIMENSION_TEXTURE2D, D3D11_VPOV_DIMENSION_TEXTURE2D,
+    },
+    Dxgi::{DXGI_FORMAT, DXGI_FORMAT_NV12, DXGI_RATIONAL, DXGI_SAMPLE_DESC},
+  },
+};
+use crate::transform::Crop;
+use std::ptr;
+use windows::Interface;
+
+/// `D3D11_VIDEO_PROCESSOR_COLOR_SPACE` with `YCbCr_Matrix` set to BT.709 and
+/// `Nominal_Range` set to 16-235, matching [`crate::convert::to_nv12`]
+const BT709_LIMITED: u32 = 1 << 2 | 1 << 4;
+
+/// Converts and scales desktop textures on the GPU
+#[derive(Debug, Clone)]
+pub struct GpuConverter {
+  video_device: ID3D11VideoDevice,
+  video_context: ID3D11VideoContext,
+  enumerator: ID3D11VideoProcessorEnumerator,
+  processor: ID3D11VideoProcessor,
+  output: ID3D11Texture2D,
+  output_view: ID3D11VideoProcessorOutputView,
+  input_size: (u32, u32),
+  output_size: (u32, u32),
+  format: DXGI_FORMAT,
+}
+
+impl GpuConverter {
+  /// Create [`GpuConverter`] for desktop textures of `input_size`
+  ///
+  /// # Arguments
+  /// * `device` - The device the desktop textures live on
+  /// * `context` - The immediate context of `device`
+  /// * `input_size` - The width and height of desktop textures in pixels
+  /// * `output_size` - The width and height of converted textures in pixels
+  /// * `format` - The format of converted textures, `DXGI_FORMAT_NV12` produces limited
+  ///   range BT.709 and RGB formats full range RGB
+  ///
+  /// # Notes
+  /// NV12 needs even dimensions so, odd output sizes are rounded up.
+  ///
+  /// # Safety
+  /// Calls to DirectX 11
+  pub unsafe fn new(
+    device: &ID3D11Device,
+    context: &ID3D11DeviceContext,
+    input_size: (u32, u32),
+    output_size: (u32, u32),
+    format: DXGI_FORMAT,
+  ) -> Result<Self, FrameError> {
+    let video_device: ID3D11VideoDevice = device.cast()?;
+    let video_context: ID3D11VideoContext = context.cast()?;
+
+    let (width, height) = match format {
+      DXGI_FORMAT_NV12 => ((output_size.0 + 1) & !1, (output_size.1 + 1) & !1),
+      _ => output_size,
+    };
+
+    let content = D3D11_VIDEO_PROCESSOR_CONTENT_DESC {
+      InputFrameFormat: D3D11_VIDEO_FRAME_FORMAT_PROGRESSIVE,
+      InputFrameRate: DXGI_RATIONAL::default(),
+      InputWidth: input_size.0,
+      InputHeight: input_size.1,
+      OutputFrameRate: DXGI_RATIONAL::default(),
+      OutputWidth: width,
+      OutputHeight: height,
+      Usage: D3D11_VIDEO_USAGE_OPTIMAL_SPEED,
+    };
+
+    let mut enumerator = None;
+    video_device
+      .CreateVideoProcessorEnumerator(&content, &mut enumerator)
+      .ok()?;
+    let enumerator = enumerator.ok_or(FrameError::None)?;
+
+    let mut processor = None;
+    video_device
+      .CreateVideoProcessor(&enumerator, 0, &mut processor)
+      .ok()?;
+    let processor = processor.ok_or(FrameError::None)?;
+
+    // Desktop images are full range RGB, the default input color space.  Drivers may
+    // otherwise apply their own enhancements, e.g. denoising, to the desktop
+    let color_space = D3D11_VIDEO_PROCESSOR_COLOR_SPACE {
+      _bitfield: match format {
+        DXGI_FORMAT_NV12 => BT709_LIMITED,
+        _ => 0,
+      },
+    };
+    video_context.VideoProcessorSetOutputColorSpace(&processor, &color_space);
+    video_context.VideoProcessorSetStreamAutoProcessingMode(&processor, 0, false);
+
+    let desc = D3D11_TEXTURE2D_DESC {
+      Width: width,
+      Height: height,
+      MipLevels: 1,
+      ArraySize: 1,
+      Format: format,
+      SampleDesc: DXGI_SAMPLE_DESC {
+        Count: 1,
+        Quality: 0,
+      },
+      Usage: D3D11_USAGE_DEFAULT,
+      BindFlags: match format {
+        DXGI_FORMAT_NV12 => D3D11_BIND_RENDER_TARGET,
+        _ => D3D11_BIND_RENDER_TARGET | D3D11_BIND_SHADER_RESOURCE,
+      },
+      CPUAccessFlags: 0.into(),
+      MiscFlags: 0.into(),
+    };
+
+    let mut output = None;
+    device
+      .CreateTexture2D(&desc, ptr::null(), &mut output)
+      .ok()?;
+    let output = output.ok_or(FrameError::None)?;
+
+    let view_desc = D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC {
+      ViewDimension: D3D11_VPOV_DIMENSION_TEXTURE2D,
+      Anonymous: D3D11_VIDEO_PROCESSOR_OUTPUT_VIEW_DESC_0 {
+        Texture2D: D3D11_TEX2D_VPOV { MipSlice: 0 },
+      },
+    };
+
+    let mut output_view = None;
+    video_device
+      .CreateVideoProcessorOutputView(&output, &enumerator, &view_desc, &mut output_view)
+      .ok()?;
+    let output_view = output_view.ok_or(FrameError::None)?;
+
+    Ok(Self {
+      video_device,
+      video_context,
+      enumerator,
+      processor,
+      output,
+      output_view,
+      input_size,
+      output_size,
+      format,
+    })
+  }
+
+  /// Whether the converter was created with the same sizes and format
+  pub fn fits(
+    &self,
+    input_size: (u32, u32),
+    output_size: (u32, u32),
+    format: DXGI_FORMAT,
+  ) -> bool {
+    self.input_size == input_size
+      && self.output_size == output_size
+      && self.format == format
+  }
+
+  /// Queue converting `texture`
+  ///
+  /// # Arguments
+  /// * `texture` - A desktop texture matching the input size of the converter
+  /// * `source` - Only convert this region of `texture`, stretched over the whole output
+  ///
+  /// # Notes
+  /// Returns the output texture, which is reused and overwritten by the next conversion.
+  ///
+  /// # Safety
+  /// Calls to DirectX 11
+  pub unsafe fn convert(
+    &self,
+    texture: &ID3D11Texture2D,
+    source: Option<Crop>,
+  ) -> Result<ID3D11Texture2D, FrameError> {
+    let view_desc = D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC {
+      FourCC: 0,
+      ViewDimension: D3D11_VPIV_DIMENSION_TEXTURE2D,
+      Anonymous: D3D11_VIDEO_PROCESSOR_INPUT_VIEW_DESC_0 {
+        Texture2D: D3D11_TEX2D_VPIV {
+          MipSlice: 0,
+          ArraySlice: 0,
+        },
+      },
+    };
+
+    // Duplication may hand out a different texture every frame so, the view isn't kept
+    let mut input_view = None;
+    self
+      .video_device
+      .CreateVideoProcessorInputView(
+        texture,
+        &self.enumerator,
+        &view_desc,
+        &mut input_view,
+      )
+      .ok()?;
+
+    let rect = source.map(|source| RECT {
+      left: source.x as i32,
+      top: source.y as i32,
+      right: (source.x + source.width) as i32,
+      bottom: (source.y + source.height) as i32,
+    });
+    self.video_context.VideoProcessorSetStreamSourceRect(
+      &self.processor,
+      0,
+      rect.is_some(),
+      rect
+        .as_ref()
+        .map_or(ptr::null(), |rect| rect as *const RECT),
+    );
+
+    let stream = D3D11_VIDEO_PROCESSOR_STREAM {
+      Enable: true.into(),
+      pInputSurface: Some(input_view.ok_or(FrameError::None)?),
+      ..Default::default()
+    };
+
+    self
+      .video_context
+      .VideoProcessorBlt(&self.processor, &self.output_view, 0, 1, &stream)
+      .ok()?;
+
+    Ok(self.output.clone())
+  }
+}
@@ -0,0 +1,628 @@
+//! Provides handing captured frames to a separate encoder process through a shared texture
+//!
+//! [`CaptureService`] copies every frame into a texture created with
+//! `D3D11_RESOURCE_MISC_SHARED_NTHANDLE` and `D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX`.  The
+//! NT handle of that texture only means something inside the capturing process so, it's
+//! copied into the encoder process with `DuplicateHandle` (which requires
+//! `PROCESS_DUP_HANDLE` access to the encoder) and the duplicated value is sent over a
+//! named pipe along with the size of the texture.  [`SharedFrameReader`] then opens it
+//! with `ID3D11Device1::OpenSharedResource1`.
+//!
+//! The keyed mutex hands the texture back and forth.  Capture writes while holding
+//! [`WRITE_KEY`] and releases [`READ_KEY`], the encoder reads while holding [`READ_KEY`]
+//! and releases [`WRITE_KEY`].  Frames captured while the encoder still holds the texture
+//! are dropped so, a slow or crashed encoder never stalls capture.
+
+use super::{
+  capture::{create_device, DxgiDisplayCapturer},
+  display::DxgiDisplay,
+  errors::FrameError,
+  frame::DxgiFrameData,
+};
+use crate::{
+  bindings::Windows::Win32::{
+    Foundation::{CloseHandle, DuplicateHandle, DUPLICATE_SAME_ACCESS, HANDLE, PWSTR},
+    Graphics::{
+      Direct3D11::{
+        ID3D11Device, ID3D11Device1, ID3D11DeviceContext, ID3D11Texture2D,
+        D3D11_BIND_RENDER_TARGET, D3D11_BIND_SHADER_RESOURCE, D3D11_CPU_ACCESS_READ,
+        D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX,
+        D3D11_RESOURCE_MISC_SHARED_NTHANDLE, D3D11_TEXTURE2D_DESC, D3D11_USAGE_DEFAULT,
+        D3D11_USAGE_STAGING, D3D_DRIVER_TYPE_HARDWARE,
+      },
+      Dxgi::{
+        IDXGIKeyedMutex, IDXGIResource1, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_SAMPLE_DESC,
+        DXGI_SHARED_RESOURCE_READ, DXGI_SHARED_RESOURCE_WRITE,
+      },
+    },
+    System::{
+      Pipes::{ConnectNamedPipe, CreateNamedPipeW, GetNamedPipeClientProcessId},
+      Threading::{GetCurrentProcess, OpenProcess, PROCESS_DUP_HANDLE},
+    },
+  },
+  convert::Layout,
+  FrameFormat,
+};
+use std::{
+  fs::File,
+  io::{Read, Write},
+  iter,
+  os::windows::io::FromRawHandle,
+  ptr, slice,
+  time::Duration,
+};
+use windows::{Interface, HRESULT};
+
+/// The keyed mutex key held while the capturing process writes a frame
+pub const WRITE_KEY: u64 = 0;
+/// The keyed mutex key held while the encoder process reads a frame
+pub const READ_KEY: u64 = 1;
+
+/// Returned by `AcquireSync` when the key wasn't released in time, a success code
+const WAIT_TIMEOUT: HRESULT = HRESULT(0x102);
+
+/// Describes a shared texture handed to another process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedTextureDesc {
+  /// The handle of the texture, valid in the process it was duplicated into
+  pub handle: isize,
+  /// The width of the texture in pixels
+  pub width: u32,
+  /// The height of the texture in pixels
+  pub height: u32,
+}
+
+impl SharedTextureDesc {
+  /// The size of a serialized [`SharedTextureDesc`]
+  pub const LEN: usize = 16;
+
+  /// Serialize for sending over a pipe
+  pub fn to_bytes(&self) -> [u8; Self::LEN] {
+    let mut buf = [0; Self::LEN];
+    buf[..8].copy_from_slice(&(self.handle as i64).to_le_bytes());
+    buf[8..12].copy_from_slice(&self.width.to_le_bytes());
+    buf[12..].copy_from_slice(&self.height.to_le_bytes());
+    buf
+  }
+
+  /// Deserialize a [`SharedTextureDesc`] received over a pipe
+  pub fn from_bytes(buf: [u8; Self::LEN]) -> Self {
+    let mut handle = [0; 8];
+    let mut width = [0; 4];
+    let mut height = [0; 4];
+    handle.copy_from_slice(&buf[..8]);
+    width.copy_from_slice(&buf[8..12]);
+    height.copy_from_slice(&buf[12..]);
+
+    Self {
+      handle: i64::from_le_bytes(handle) as isize,
+      width: u32::from_le_bytes(width),
+      height: u32::from_le_bytes(height),
+    }
+  }
+}
+
+/// Captures a display into a texture shared with another process
+///
+/// # Notes
+/// Frames are shared as captured, `B8G8R8A8` without grayscale conversion or overlays.
+/// [`crate::CaptureConfig::with_hdr`] is ignored.
+pub struct CaptureService {
+  capturer: DxgiDisplayCapturer,
+  texture: ID3D11Texture2D,
+  mutex: IDXGIKeyedMutex,
+  handle: HANDLE,
+  width: u32,
+  height: u32,
+}
+
+impl CaptureService {
+  /// Create [`CaptureService`] sharing frames of `display`
+  ///
+  /// # Safety
+  /// Calls to DirectX 11 and DXGI
+  pub unsafe fn new(display: &DxgiDisplay) -> Result<Self, FrameError> {
+    let width = display.width() as u32;
+    let height = display.height() as u32;
+    let desc = D3D11_TEXTURE2D_DESC {
+      Width: width,
+      Height: height,
+      MipLevels: 1,
+      ArraySize: 1,
+      Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+      SampleDesc: DXGI_SAMPLE_DESC {
+        Count: 1,
+        Quality: 0,
+      },
+      Usage: D3D11_USAGE_DEFAULT,
+      BindFlags: D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET,
+      CPUAccessFlags: 0.into(),
+      MiscFlags: D3D11_RESOURCE_MISC_SHARED_NTHANDLE
+        | D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX,
+    };
+
+    // The shared texture is `B8G8R8A8` so, HDR displays are duplicated in that format
+    let mut display = display.clone();
+    display.config = display.config.clone().with_hdr(false);
+
+    // The texture must live on the capture device for frames to be copied on the GPU
+    let mut texture = None;
+    let capturer = DxgiDisplayCapturer::new(&display)?;
+    capturer
+      .device()
+      .CreateTexture2D(&desc, ptr::null(), &mut texture)
+      .ok()?;
+
+    let texture = texture.ok_or(FrameError::None)?;
+    let mutex = texture.cast()?;
+    let mut handle = HANDLE::default();
+    texture
+      .cast::<IDXGIResource1>()?
+      .CreateSharedHandle(
+        ptr::null(),
+        DXGI_SHARED_RESOURCE_READ | DXGI_SHARED_RESOURCE_WRITE,
+        PWSTR::default(),
+        &mut handle,
+      )
+      .ok()?;
+
+    Ok(Self {
+      capturer,
+      texture,
+      mutex,
+      handle,
+      width,
+      height,
+    })
+  }
+
+  /// Describe the shared texture for a process
+  ///
+  /// # Arguments
+  /// * `process` - The process the texture is shared with, opened with
+  ///   `PROCESS_DUP_HANDLE` access
+  ///
+  /// # Safety
+  /// Calls to windows API
+  pub unsafe fn share_with(
+    &self,
+    process: HANDLE,
+  ) -> Result<SharedTextureDesc, FrameError> {
+    let mut handle = HANDLE::default();
+    DuplicateHandle(
+      GetCurrentProcess(),
+      self.handle,
+      process,
+      &mut handle,
+      0,
+      false,
+      DUPLICATE_SAME_ACCESS,
+    )
+    .ok()?;
+
+    Ok(SharedTextureDesc {
+      handle: handle.0,
+      width: self.width,
+      height: self.height,
+    })
+  }
+
+  /// Wait for an encoder to connect to `pipe_name` and hand it the shared texture
+  ///
+  /// # Safety
+  /// Calls to windows API
+  pub unsafe fn serve(&self, pipe_name: &str) -> anyhow::Result<()> {
+    // Only the server writes, a single message describing the texture
+    const PIPE_ACCESS_OUTBOUND: u32 = 0x2;
+    const ERROR_PIPE_CONNECTED: u32 = 535;
+
+    let mut name = pipe_path(pipe_name)
+      .encode_utf16()
+      .chain(iter::once(0))
+      .collect::<Vec<_>>();
+
+    let pipe = CreateNamedPipeW(
+      PWSTR(name.as_mut_ptr()),
+      PIPE_ACCESS_OUTBOUND,
+      0,
+      1,
+      SharedTextureDesc::LEN as u32,
+      0,
+      0,
+      ptr::null_mut(),
+    );
+
+    // `INVALID_HANDLE_VALUE`
+    if pipe.0 == -1 {
+      HRESULT::from_thread().ok()?;
+    }
+
+    // From here on the pipe is closed when `File` is dropped
+    let mut file = File::from_raw_handle(pipe.0 as _);
+
+    // The encoder may connect between creating the pipe and waiting for it
+    if !ConnectNamedPipe(pipe, ptr::null_mut()).as_bool() {
+      let err = HRESULT::from_thread();
+      if err != HRESULT::from_win32(ERROR_PIPE_CONNECTED) {
+        err.ok()?;
+      }
+    }
+
+    let mut pid = 0;
+    GetNamedPipeClientProcessId(pipe, &mut pid).ok()?;
+
+    let process = OpenProcess(PROCESS_DUP_HANDLE, false, pid);
+    if process.is_null() {
+      HRESULT::from_thread().ok()?;
+    }
+
+    let desc = self.share_with(process);
+    CloseHandle(process);
+
+    file.write_all(&desc?.to_bytes())?;
+    file.flush()?;
+
+    Ok(())
+  }
+
+  /// Capture the next frame into the shared texture and hand it to the encoder
+  ///
+  /// # Notes
+  /// Returns [`FrameError::WouldBlock`] when no new frame is ready or the encoder is still
+  /// reading the previous one, in which case the frame is dropped.
+  ///
+  /// # Safety
+  /// Calls to DirectX 11 and DXGI
+  pub unsafe fn publish(&mut self) -> Result<(), FrameError> {
+    let acquired = self.mutex.AcquireSync(WRITE_KEY, 0);
+    if acquired == WAIT_TIMEOUT {
+      return Err(FrameError::WouldBlock);
+    }
+
+    acquired.ok()?;
+
+    // Only hand the texture over when it holds a new frame
+    let copied = self.copy_next_frame();
+    let key = if copied.is_ok() { READ_KEY } else { WRITE_KEY };
+    self.mutex.ReleaseSync(key).ok()?;
+
+    copied
+  }
+
+  /// Copy the next frame into the shared texture
+  unsafe fn copy_next_frame(&mut self) -> Result<(), FrameError> {
+    let texture = &self.texture;
+    let context = self.capturer.context().clone();
+    let timeout = self.capturer.frame_timeout();
+    let frame = self.capturer.get_frame(timeout)?;
+
+    if (frame.width(), frame.height()) != (self.width as usize, self.height as usize) {
+      return Err(FrameError::SharedSizeMismatch);
+    }
+
+    match frame.data() {
+      DxgiFrameData::DirectX(data) => context.CopyResource(texture, data.texture()),
+      DxgiFrameData::Memory(buf) => context.UpdateSubresource(
+        texture,
+        0,
+        ptr::null(),
+        buf.as_ptr() as _,
+        (buf.len() / frame.height()) as u32,
+        0,
+      ),
+    }
+
+    Ok(())
+  }
+}
+
+impl Drop for CaptureService {
+  fn drop(&mut self) {
+    unsafe { CloseHandle(self.handle) };
+  }
+}
+
+/// Reads frames shared by a [`CaptureService`], usually from another process
+///
+/// # Notes
+/// Shared textures can only be opened on the adapter they were created on, the reader
+/// uses the default hardware adapter.
+pub struct SharedFrameReader {
+  context: ID3D11DeviceContext,
+  texture: ID3D11Texture2D,
+  staging: ID3D11Texture2D,
+  mutex: IDXGIKeyedMutex,
+  width: u32,
+  height: u32,
+}
+
+impl SharedFrameReader {
+  /// Connect to a [`CaptureService`] serving on `pipe_name`
+  ///
+  /// # Safety
+  /// Calls to DirectX 11 and windows API
+  pub unsafe fn connect(pipe_name: &str) -> anyhow::Result<Self> {
+    let mut buf = [0; SharedTextureDesc::LEN];
+    File::open(pipe_path(pipe_name))?.read_exact(&mut buf)?;
+
+    Ok(Self::open(SharedTextureDesc::from_bytes(buf))?)
+  }
+
+  /// Open a shared texture whose handle is valid in this process
+  ///
+  /// # Safety
+  /// Calls to DirectX 11
+  pub unsafe fn open(desc: SharedTextureDesc) -> Result<Self, FrameError> {
+    let (device, context, _) = create_device(None, D3D_DRIVER_TYPE_HARDWARE)?;
+    let texture: ID3D11Texture2D = device
+      .cast::<ID3D11Device1>()?
+      .OpenSharedResource1(HANDLE(desc.handle))?;
+
+    // The handle was duplicated for us and the texture holds its own reference
+    CloseHandle(HANDLE(desc.handle));
+
+    let mutex = texture.cast()?;
+    let staging = create_staging(&device, &texture)?;
+
+    Ok(Self {
+      context,
+      texture,
+      staging,
+      mutex,
+      width: desc.width,
+      height: desc.height,
+    })
+  }
+
+  /// The width of shared frames in pixels
+  pub const fn width(&self) -> usize {
+    self.width as usize
+  }
+
+  /// The height of shared frames in pixels
+  pub const fn height(&self) -> usize {
+    self.height as usize
+  }
+
+  /// Wait for the next frame and copy it into tightly packed `B8G8R8A8` pixels
+  ///
+  /// # Notes
+  /// Returns [`FrameError::WouldBlock`] when no frame was published within `timeout`.
+  /// The texture is handed back to capture as soon as it's copied, before the copy is
+  /// read back.
+  ///
+  /// # Safety
+  /// Calls to DirectX 11 and DXGI
+  pub unsafe fn read(&self, timeout: Duration) -> Result<Vec<u8>, FrameError> {
+    let acquired = self.mutex.AcquireSync(READ_KEY, timeout.as_millis() as u32);
+    if acquired == WAIT_TIMEOUT {
+      return Err(FrameError::WouldBlock);
+    }
+
+    acquired.ok()?;
+    self.context.CopyResource(&self.staging, &self.texture);
+    self.mutex.ReleaseSync(WRITE_KEY).ok()?;
+
+    let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+    self
+      .context
+      .Map(&self.staging, 0, D3D11_MAP_READ, 0, &mut mapped)
+      .ok()?;
+
+    let layout = Layout::new(
+      self.width(),
+      self.height(),
+      mapped.RowPitch as usize,
+      FrameFormat::B8G8R8A8,
+    );
+
+    let buf = slice::from_raw_parts(
+      mapped.pData as *const u8,
+      layout.stride * layout.height.saturating_sub(1) + layout.row_len(),
+    );
+
+    let mut data = Vec::with_capacity(layout.row_len() * layout.height);
+    for row in layout.rows(buf) {
+      data.extend_from_slice(row);
+    }
+
+    self.context.Unmap(&self.staging, 0);
+
+    Ok(data)
+  }
+}
+
+/// Create a CPU readable copy of `texture`'s description
+///
+/// # Safety
+/// Calls to DirectX 11
+pub(super) unsafe fn create_staging(
+  device: &ID3D11Device,
+  texture: &ID3D11Texture2D,
+) -> Result<ID3D11Texture2D, FrameError> {
+  let mut desc = D3D11_TEXTURE2D_DESC::default();
+  texture.GetDesc(&mut desc);
+  desc.Usage = D3D11_USAGE_STAGING;
+  desc.BindFlags = 0.into();
+  desc.MiscFlags = 0.into();
+  desc.CPUAccessFlags = D3D11_CPU_ACCESS_READ;
+
+  let mut staging = None;
+  device
+    .CreateTexture2D(&desc, ptr::null(), &mut staging)
+    .ok()?;
+
+  staging.ok_or(FrameError::None)
+}
+
+/// Copy `texture` into a new texture shared through an NT handle
+///
+/// # Notes
+/// The copy is guarded by a keyed mutex released with [`READ_KEY`] so, whoever opens the
+/// handle acquires [`READ_KEY`] before reading it.  The handle belongs to the caller and
+/// must be closed with `CloseHandle`, the texture lives until every handle to it is
+/// closed and every device that opened it releases it.
+///
+/// # Safety
+/// Calls to DirectX 11 and DXGI
+pub(super) unsafe fn share_texture(
+  texture: &ID3D11Texture2D,
+) -> Result<HANDLE, FrameError> {
+  let mut device = None;
+  texture.GetDevice(&mut device);
+  let device = device.ok_or(FrameError::None)?;
+
+  let mut context = None;
+  device.GetImmediateContext(&mut context);
+  let context = context.ok_or(FrameError::None)?;
+
+  let mut desc = D3D11_TEXTURE2D_DESC::default();
+  texture.GetDesc(&mut desc);
+  desc.Usage = D3D11_USAGE_DEFAULT;
+  desc.BindFlags = D3D11_BIND_SHADER_RESOURCE | D3D11_BIND_RENDER_TARGET;
+  desc.CPUAccessFlags = 0.into();
+  desc.MiscFlags =
+    D3D11_RESOURCE_MISC_SHARED_NTHANDLE | D3D11_RESOURCE_MISC_SHARED_KEYEDMUTEX;
+
+  let mut shared = None;
+  device
+    .CreateTexture2D(&desc, ptr::null(), &mut shared)
+    .ok()?;
+  let shared = shared.ok_or(FrameError::None)?;
+
+  // A new keyed mutex starts out released with `WRITE_KEY`
+  let mutex: IDXGIKeyedMutex = shared.cast()?;
+  mutex.AcquireSync(WRITE_KEY, 0).ok()?;
+  context.CopyResource(&shared, texture);
+  mutex.ReleaseSync(READ_KEY).ok()?;
+
+  let mut handle = HANDLE::default();
+  shared
+    .cast::<IDXGIResource1>()?
+    .CreateSharedHandle(
+      ptr::null(),
+      DXGI_SHARED_RESOURCE_READ | DXGI_SHARED_RESOURCE_WRITE,
+      PWSTR::default(),
+      &mut handle,
+    )
+    .ok()?;
+
+  Ok(handle)
+}
+
+/// Get the path of a named pipe
+fn pipe_path(name: &str) -> String {
+  format!(r"\\.\pipe\{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{CaptureService, SharedFrameReader, SharedTextureDesc};
+  use crate::{
+    bindings::Windows::Win32::System::Threading::GetCurrentProcess,
+    driver::dxgi::{
+      capture::DxgiDisplayCapturer, display::DxgiDisplays, errors::FrameError,
+      frame::DxgiFrameData,
+    },
+    FrameFormat,
+  };
+  use std::{thread, time::Duration};
+
+  #[test]
+  fn test_shared_texture_desc_roundtrip() {
+    let desc = SharedTextureDesc {
+      handle: 0x1234,
+      width: 1920,
+      height: 1080,
+    };
+
+    assert_eq!(SharedTextureDesc::from_bytes(desc.to_bytes()), desc);
+  }
+
+  #[test]
+  fn test_share_texture_between_devices() {
+    unsafe {
+      let display = DxgiDisplays::new().unwrap().next().unwrap().unwrap();
+      let mut service = CaptureService::new(&display).unwrap();
+      let desc = service.share_with(GetCurrentProcess()).unwrap();
+      let reader = SharedFrameReader::open(desc).unwrap();
+
+      for _ in 0..100 {
+        match service.publish() {
+          Ok(()) => {
+            let frame = reader.read(Duration::from_secs(1)).unwrap();
+            let bpp = FrameFormat::B8G8R8A8.bytes_per_pixel();
+            assert_eq!(frame.len(), reader.width() * reader.height() * bpp);
+            return;
+          }
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        }
+      }
+
+      panic!("No frame was published");
+    }
+  }
+
+  #[test]
+  fn test_frame_shared_handle() {
+    unsafe {
+      let display = DxgiDisplays::new().unwrap().next().unwrap().unwrap();
+      let mut capturer = DxgiDisplayCapturer::new(&display).unwrap();
+
+      for _ in 0..100 {
+        let frame = match capturer.get_frame(Duration::from_millis(16)) {
+          Ok(frame) => frame,
+          Err(FrameError::WouldBlock) => continue,
+          Err(err) => panic!("{:?}", err),
+        };
+
+        let handle = match frame.shared_handle() {
+          Ok(handle) => handle,
+          // Desktop images in system memory have no texture to share
+          Err(_) if matches!(frame.data(), DxgiFrameData::Memory(_)) => return,
+          Err(err) => panic!("{:?}", err),
+        };
+
+        let reader = SharedFrameReader::open(SharedTextureDesc {
+          handle: handle.0,
+          width: frame.width() as u32,
+          height: frame.height() as u32,
+        })
+        .unwrap();
+
+        let shared = reader.read(Duration::from_secs(1)).unwrap();
+        assert_eq!(shared, frame.to_vec().unwrap());
+        return;
+      }
+
+      panic!("No frame was captured");
+    }
+  }
+
+  #[test]
+  fn test_share_texture_over_pipe() {
+    const PIPE: &str = "scraptor-test-share-texture-over-pipe";
+
+    unsafe {
+      let display = DxgiDisplays::new().unwrap().next().unwrap().unwrap();
+      let service = CaptureService::new(&display).unwrap();
+
+      let reader = thread::spawn(|| loop {
+        // Wait for the pipe to be created
+        match SharedFrameReader::connect(PIPE) {
+          Ok(reader) => return (reader.width(), reader.height()),
+          Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+      });
+
+      service.serve(PIPE).unwrap();
+
+      let (width, height) = reader.join().unwrap();
+      assert_eq!(
+        (width as u32, height as u32),
+        (service.width, service.height)
+      );
+    }
+  }
+}
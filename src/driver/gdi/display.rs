@@ -0,0 +1,266 @@
+//! Provides interface to get display information for GDI screen capture.
+
+use super::{errors::FrameError as GdiFrameError, frame::GdiFrame};
+use crate::{
+  bindings::Windows::Win32::{
+    Foundation::{HWND, PWSTR},
+    Graphics::Gdi::{
+      BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, CreatedHDC, DeleteDC,
+      DeleteObject, EnumDisplaySettingsW, GetDC, GetDIBits, ReleaseDC, SelectObject,
+      BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CAPTUREBLT, DIB_RGB_COLORS,
+      ENUM_CURRENT_SETTINGS, HBITMAP, HDC, ROP_CODE, SRCCOPY,
+    },
+    UI::DisplayDevices::DEVMODEW,
+  },
+  driver::dxgi::display::{display_devices, wide_to_string},
+  errors::{DisplayError, FrameError},
+  Display,
+};
+use std::{ffi::c_void, mem};
+
+/// Flag of `DISPLAY_DEVICEW::StateFlags` marking a display that's part of the desktop
+const DISPLAY_DEVICE_ATTACHED_TO_DESKTOP: u32 = 0x1;
+/// Flag of `DISPLAY_DEVICEW::StateFlags` marking the primary display
+const DISPLAY_DEVICE_PRIMARY_DEVICE: u32 = 0x4;
+
+/// Get the displays that are part of the desktop
+///
+/// # Safety
+/// Calls to windows API
+pub(super) unsafe fn displays() -> Vec<GdiDisplay> {
+  display_devices()
+    .into_iter()
+    .filter(|device| device.StateFlags & DISPLAY_DEVICE_ATTACHED_TO_DESKTOP != 0)
+    .filter_map(|device| {
+      let mut name = device.DeviceName;
+      let mut mode: DEVMODEW = mem::zeroed();
+      mode.dmSize = mem::size_of::<DEVMODEW>() as u16;
+
+      if !EnumDisplaySettingsW(PWSTR(name.as_mut_ptr()), ENUM_CURRENT_SETTINGS, &mut mode)
+        .as_bool()
+      {
+        return None;
+      }
+
+      let position = mode.Anonymous1.Anonymous2.dmPosition;
+
+      Some(GdiDisplay::new(
+        wide_to_string(&device.DeviceName),
+        (position.x, position.y),
+        mode.dmPelsWidth as usize,
+        mode.dmPelsHeight as usize,
+        device.StateFlags & DISPLAY_DEVICE_PRIMARY_DEVICE != 0,
+      ))
+    })
+    .collect()
+}
+
+/// A GDI display, the area of the virtual screen covered by a monitor
+///
+/// # Notes
+/// Positions and sizes are in physical pixels so, the process should be DPI aware or
+/// Windows scales the copied screen of high DPI displays.
+#[derive(Debug)]
+pub struct GdiDisplay {
+  name: String,
+  position: (i32, i32),
+  width: usize,
+  height: usize,
+  primary: bool,
+  surface: Option<Surface>,
+  buf: Vec<u8>,
+  sequence: u64,
+}
+
+impl GdiDisplay {
+  fn new(
+    name: String,
+    position: (i32, i32),
+    width: usize,
+    height: usize,
+    primary: bool,
+  ) -> Self {
+    Self {
+      name,
+      position,
+      width,
+      height,
+      primary,
+      surface: None,
+      buf: Vec::new(),
+      sequence: 0,
+    }
+  }
+
+  /// The GDI device name of the display, e.g. `\\.\DISPLAY1`
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// The position of the display on the virtual screen
+  pub const fn position(&self) -> (i32, i32) {
+    self.position
+  }
+
+  /// The width of the display
+  pub const fn width(&self) -> usize {
+    self.width
+  }
+
+  /// The height of the display
+  pub const fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Whether the display is the primary display
+  pub const fn is_primary(&self) -> bool {
+    self.primary
+  }
+
+  /// Copy the display's area of the screen into `buf`
+  ///
+  /// # Notes
+  /// The device contexts and bitmap are created on the first copy and reused after.
+  ///
+  /// # Safety
+  /// Calls to GDI
+  unsafe fn capture(&mut self) -> Result<(), GdiFrameError> {
+    let (width, height) = (self.width as i32, self.height as i32);
+
+    if self.surface.is_none() {
+      self.surface = Some(Surface::new(width, height)?);
+    }
+
+    let surface = self.surface.as_ref().ok_or(GdiFrameError::ScreenDc)?;
+    let (x, y) = self.position;
+
+    // `CAPTUREBLT` includes layered windows, e.g. tooltips and overlays.  `GetDIBits`
+    // needs the bitmap deselected so, it's only selected while copying
+    let previous = SelectObject(surface.memory, surface.bitmap);
+    let copied = BitBlt(
+      surface.memory,
+      0,
+      0,
+      width,
+      height,
+      surface.screen,
+      x,
+      y,
+      ROP_CODE(SRCCOPY.0 | CAPTUREBLT.0),
+    );
+    SelectObject(surface.memory, previous);
+
+    if !copied.as_bool() {
+      return Err(GdiFrameError::BitBlt);
+    }
+
+    let mut info = BITMAPINFO {
+      bmiHeader: BITMAPINFOHEADER {
+        biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: width,
+        // A negative height asks for rows top to bottom
+        biHeight: -height,
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: BI_RGB as u32,
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+
+    self.buf.resize(self.width * self.height * 4, 0);
+    let lines = GetDIBits(
+      surface.screen,
+      surface.bitmap,
+      0,
+      height as u32,
+      self.buf.as_mut_ptr() as *mut c_void,
+      &mut info,
+      DIB_RGB_COLORS,
+    );
+
+    if lines != height {
+      return Err(GdiFrameError::GetDIBits);
+    }
+
+    Ok(())
+  }
+}
+
+impl<'frame> Display<'frame> for GdiDisplay {
+  type Frame = GdiFrame<'frame>;
+
+  fn width(&self) -> Result<usize, DisplayError> {
+    Ok(self.width())
+  }
+
+  fn height(&self) -> Result<usize, DisplayError> {
+    Ok(self.height())
+  }
+
+  fn frame(&'frame mut self) -> Result<Self::Frame, FrameError> {
+    unsafe { self.capture()? };
+
+    let sequence = self.sequence;
+    self.sequence += 1;
+
+    Ok(GdiFrame::new(&self.buf, self.width, self.height, sequence))
+  }
+}
+
+/// The device contexts and bitmap a display is copied through
+#[derive(Debug)]
+struct Surface {
+  screen: HDC,
+  memory: CreatedHDC,
+  bitmap: HBITMAP,
+}
+
+impl Surface {
+  /// Create a bitmap of `width` by `height` compatible with the screen
+  ///
+  /// # Safety
+  /// Calls to GDI
+  unsafe fn new(width: i32, height: i32) -> Result<Self, GdiFrameError> {
+    let mut surface = Self {
+      screen: GetDC(HWND::default()),
+      memory: CreatedHDC::default(),
+      bitmap: HBITMAP::default(),
+    };
+
+    // Handles are freed on drop so, a partially created surface cleans up after itself
+    if surface.screen.is_null() {
+      return Err(GdiFrameError::ScreenDc);
+    }
+
+    surface.memory = CreateCompatibleDC(surface.screen);
+    if surface.memory.is_null() {
+      return Err(GdiFrameError::CreateBitmap);
+    }
+
+    surface.bitmap = CreateCompatibleBitmap(surface.screen, width, height);
+    if surface.bitmap.is_null() {
+      return Err(GdiFrameError::CreateBitmap);
+    }
+
+    Ok(surface)
+  }
+}
+
+impl Drop for Surface {
+  fn drop(&mut self) {
+    unsafe {
+      if !self.bitmap.is_null() {
+        DeleteObject(self.bitmap);
+      }
+
+      if !self.memory.is_null() {
+        DeleteDC(self.memory);
+      }
+
+      if !self.screen.is_null() {
+        ReleaseDC(HWND::default(), self.screen);
+      }
+    }
+  }
+}
@@ -0,0 +1,12 @@
+/// An error that occurs when copying the screen with GDI
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+  #[error("Failed to get the device context of the screen")]
+  ScreenDc,
+  #[error("Failed to create the bitmap the screen is copied into")]
+  CreateBitmap,
+  #[error("Failed to copy the screen with BitBlt")]
+  BitBlt,
+  #[error("Failed to read the pixels of the copied screen")]
+  GetDIBits,
+}
@@ -0,0 +1,62 @@
+use crate::{errors::FrameError, DirtyRect, Frame, FrameFormat, MovedRect};
+use std::borrow::Cow;
+
+/// A copy of the screen area covered by a GDI display
+#[derive(Debug, Clone)]
+pub struct GdiFrame<'a> {
+  data: &'a [u8],
+  width: usize,
+  height: usize,
+  sequence: u64,
+}
+
+impl<'a> GdiFrame<'a> {
+  pub fn new(data: &'a [u8], width: usize, height: usize, sequence: u64) -> Self {
+    Self {
+      data,
+      width,
+      height,
+      sequence,
+    }
+  }
+}
+
+impl<'frame> Frame<'frame> for GdiFrame<'frame> {
+  fn width(&self) -> usize {
+    self.width
+  }
+
+  fn height(&self) -> usize {
+    self.height
+  }
+
+  /// GDI doesn't report what changed between copies so, the whole frame is always dirty
+  fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    Ok(vec![DirtyRect::from_ltrb(
+      0,
+      0,
+      self.width as i32,
+      self.height as i32,
+    )])
+  }
+
+  fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+    Ok(Vec::new())
+  }
+
+  /// The layout of a 32 bit DIB
+  ///
+  /// # Notes
+  /// The alpha byte is padding and usually zero.
+  fn format(&self) -> FrameFormat {
+    FrameFormat::B8G8R8A8
+  }
+
+  fn as_bytes(&self) -> anyhow::Result<Cow<'frame, [u8]>> {
+    Ok(Cow::Borrowed(self.data))
+  }
+
+  fn sequence(&self) -> u64 {
+    self.sequence
+  }
+}
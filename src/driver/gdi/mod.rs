@@ -0,0 +1,56 @@
+pub mod display;
+pub mod errors;
+pub mod frame;
+
+use crate::{errors::DisplayError, DisplayDriver};
+use display::GdiDisplay;
+
+/// The GDI `BitBlt` display driver
+///
+/// # Notes
+/// Slower than [`super::dxgi::Dxgi`] since every frame is copied in full through system
+/// memory but, works where desktop duplication doesn't, e.g. on Windows 7 and in some
+/// remote or virtualized sessions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Gdi;
+
+impl<'buf> DisplayDriver<'buf> for Gdi {
+  type Display = GdiDisplay;
+
+  fn name(&self) -> &'static str {
+    "gdi"
+  }
+
+  fn all(&self) -> Result<Vec<Self::Display>, DisplayError> {
+    Ok(unsafe { display::displays() })
+  }
+
+  /// Gets the primary display, the first display when none is marked as primary
+  fn primary(&self) -> Result<Option<Self::Display>, DisplayError> {
+    let mut displays = self.all()?;
+
+    match displays.iter().position(GdiDisplay::is_primary) {
+      Some(idx) => Ok(Some(displays.swap_remove(idx))),
+      None => Ok(displays.into_iter().next()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Gdi;
+  use crate::{Display, DisplayDriver, Frame};
+
+  #[test]
+  fn test_gdi_frame() {
+    let mut display = Gdi.primary().unwrap().unwrap();
+
+    let (width, height) = (display.width(), display.height());
+    let frame = Display::frame(&mut display).unwrap();
+
+    assert_eq!(frame.width(), width);
+    assert_eq!(frame.height(), height);
+    assert_eq!(frame.as_bytes().unwrap().len(), width * height * 4);
+    assert_eq!(frame.sequence(), 0);
+  }
+}
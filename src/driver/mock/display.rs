@@ -0,0 +1,230 @@
+//! Provides a display serving synthetic frames
+
+use super::frame::MockFrame;
+use crate::{
+  errors::{DisplayError, FrameError},
+  DirtyRect, Display, FrameFormat,
+};
+use std::{collections::VecDeque, thread, time::Duration};
+
+/// The pixels a [`MockDisplay`] serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockPattern {
+  /// Every pixel is the same `B8G8R8A8` color
+  Solid([u8; 4]),
+  /// A gradient scrolling one pixel to the left every frame so, consecutive frames differ
+  Gradient,
+}
+
+/// A display serving synthetic `B8G8R8A8` frames
+#[derive(Debug, Clone)]
+pub struct MockDisplay {
+  width: usize,
+  height: usize,
+  pattern: MockPattern,
+  dirty: VecDeque<Vec<DirtyRect>>,
+  latency: Duration,
+  timeout_every: u64,
+  idle_after: Option<u64>,
+  requests: u64,
+  sequence: u64,
+  buf: Vec<u8>,
+}
+
+impl MockDisplay {
+  /// Create [`MockDisplay`] showing a [`MockPattern::Gradient`]
+  ///
+  /// # Arguments
+  /// * `width` - The width of frames in pixels
+  /// * `height` - The height of frames in pixels
+  pub fn new(width: usize, height: usize) -> Self {
+    Self {
+      width,
+      height,
+      pattern: MockPattern::Gradient,
+      dirty: VecDeque::new(),
+      latency: Duration::ZERO,
+      timeout_every: 0,
+      idle_after: None,
+      requests: 0,
+      sequence: 0,
+      buf: Vec::new(),
+    }
+  }
+
+  /// Wait `latency` before answering every frame request, like a display refreshing
+  pub fn with_latency(mut self, latency: Duration) -> Self {
+    self.latency = latency;
+    self
+  }
+
+  /// Report `WouldBlock` once `frames` frames were served, like an idle desktop
+  pub fn with_idle_after(mut self, frames: u64) -> Self {
+    self.idle_after = Some(frames);
+    self
+  }
+
+  /// Report `WouldBlock` on every `every`th frame request, like a display without new
+  /// content
+  ///
+  /// # Notes
+  /// Zero, the default, never times out.
+  pub fn with_timeout_every(mut self, every: u64) -> Self {
+    self.timeout_every = every;
+    self
+  }
+
+  /// Serve `pattern` instead of a gradient
+  pub fn with_pattern(mut self, pattern: MockPattern) -> Self {
+    self.pattern = pattern;
+    self
+  }
+
+  /// Report `rects` as dirty on the next frame without queued rectangles
+  pub fn with_dirty(mut self, rects: Vec<DirtyRect>) -> Self {
+    self.push_dirty(rects);
+    self
+  }
+
+  /// The pixels served
+  pub const fn pattern(&self) -> MockPattern {
+    self.pattern
+  }
+
+  /// Serve `pattern` starting from the next frame
+  pub fn set_pattern(&mut self, pattern: MockPattern) {
+    self.pattern = pattern;
+  }
+
+  /// Report `rects` as dirty on the next frame without queued rectangles
+  ///
+  /// # Notes
+  /// Each frame takes the oldest queued rectangles.  Once the queue is empty the whole
+  /// frame is reported as dirty, the same as drivers without damage tracking.
+  pub fn push_dirty(&mut self, rects: Vec<DirtyRect>) {
+    self.dirty.push_back(rects);
+  }
+
+  /// Draw the pattern for the current frame into the buffer
+  fn render(&mut self) {
+    let bpp = FrameFormat::B8G8R8A8.bytes_per_pixel();
+    let shift = self.sequence as usize;
+
+    self.buf.resize(self.width * self.height * bpp, 0);
+
+    for (idx, pixel) in self.buf.chunks_exact_mut(bpp).enumerate() {
+      let (x, y) = (idx % self.width + shift, idx / self.width);
+
+      let color = match self.pattern {
+        MockPattern::Solid(color) => color,
+        MockPattern::Gradient => [x as u8, y as u8, (x + y) as u8, 255],
+      };
+
+      pixel.copy_from_slice(&color);
+    }
+  }
+}
+
+impl<'a> Display<'a> for MockDisplay {
+  type Frame = MockFrame<'a>;
+
+  fn width(&self) -> Result<usize, DisplayError> {
+    Ok(self.width)
+  }
+
+  fn height(&self) -> Result<usize, DisplayError> {
+    Ok(self.height)
+  }
+
+  /// Gets the next synthetic frame, a frame is always ready unless
+  /// [`MockDisplay::with_timeout_every`] or [`MockDisplay::with_idle_after`] say otherwise
+  fn frame(&'a mut self) -> Result<Self::Frame, FrameError> {
+    thread::sleep(self.latency);
+
+    self.requests += 1;
+    let timeout =
+      self.timeout_every > 0 && self.requests.is_multiple_of(self.timeout_every);
+    let idle = self
+      .idle_after
+      .is_some_and(|frames| self.sequence >= frames);
+
+    if timeout || idle {
+      return Err(FrameError::WouldBlock);
+    }
+
+    self.render();
+
+    let dirty = self.dirty.pop_front().unwrap_or_else(|| {
+      vec![DirtyRect::new(0, self.width as i32, self.height as i32, 0)]
+    });
+
+    let sequence = self.sequence;
+    self.sequence += 1;
+
+    Ok(MockFrame::new(
+      &self.buf,
+      self.width,
+      self.height,
+      dirty,
+      sequence,
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{MockDisplay, MockPattern};
+  use crate::{errors::FrameError, DirtyRect, Display, Frame};
+
+  #[test]
+  fn test_solid() {
+    let mut display =
+      MockDisplay::new(2, 2).with_pattern(MockPattern::Solid([1, 2, 3, 4]));
+    let frame = display.frame().unwrap();
+
+    assert_eq!(frame.as_bytes().unwrap().as_ref(), [1, 2, 3, 4].repeat(4));
+  }
+
+  #[test]
+  fn test_gradient_scrolls() {
+    let mut display = MockDisplay::new(3, 1);
+
+    let first = display.frame().unwrap().as_bytes().unwrap().into_owned();
+    let second = display.frame().unwrap().as_bytes().unwrap().into_owned();
+
+    assert_eq!(first[4..], second[..8]);
+    assert_ne!(first, second);
+  }
+
+  #[test]
+  fn test_timeout_every() {
+    let mut display = MockDisplay::new(1, 1).with_timeout_every(2);
+
+    assert!(display.frame().is_ok());
+    assert!(matches!(display.frame(), Err(FrameError::WouldBlock)));
+    assert_eq!(display.frame().unwrap().sequence(), 1);
+  }
+
+  #[test]
+  fn test_idle_after() {
+    let mut display = MockDisplay::new(1, 1).with_idle_after(1);
+
+    assert!(display.frame().is_ok());
+    assert!(matches!(display.frame(), Err(FrameError::WouldBlock)));
+    assert!(matches!(display.frame(), Err(FrameError::WouldBlock)));
+  }
+
+  #[test]
+  fn test_dirty_queue() {
+    let rect = DirtyRect::new(0, 1, 1, 0);
+    let mut display = MockDisplay::new(4, 2).with_dirty(vec![rect]);
+    display.push_dirty(Vec::new());
+
+    assert_eq!(display.frame().unwrap().dirty().unwrap(), vec![rect]);
+    assert!(display.frame().unwrap().dirty().unwrap().is_empty());
+    assert_eq!(
+      display.frame().unwrap().dirty().unwrap(),
+      vec![DirtyRect::new(0, 4, 2, 0)]
+    );
+  }
+}
@@ -0,0 +1,60 @@
+use crate::{errors::FrameError, DirtyRect, Frame, FrameFormat, MovedRect};
+use std::borrow::Cow;
+
+/// A synthetic frame served by a [`super::display::MockDisplay`]
+#[derive(Debug, Clone)]
+pub struct MockFrame<'a> {
+  data: &'a [u8],
+  width: usize,
+  height: usize,
+  dirty: Vec<DirtyRect>,
+  sequence: u64,
+}
+
+impl<'a> MockFrame<'a> {
+  pub fn new(
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    dirty: Vec<DirtyRect>,
+    sequence: u64,
+  ) -> Self {
+    Self {
+      data,
+      width,
+      height,
+      dirty,
+      sequence,
+    }
+  }
+}
+
+impl<'frame> Frame<'frame> for MockFrame<'frame> {
+  fn width(&self) -> usize {
+    self.width
+  }
+
+  fn height(&self) -> usize {
+    self.height
+  }
+
+  fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    Ok(self.dirty.clone())
+  }
+
+  fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+    Ok(Vec::new())
+  }
+
+  fn format(&self) -> FrameFormat {
+    FrameFormat::B8G8R8A8
+  }
+
+  fn as_bytes(&self) -> anyhow::Result<Cow<'frame, [u8]>> {
+    Ok(Cow::Borrowed(self.data))
+  }
+
+  fn sequence(&self) -> u64 {
+    self.sequence
+  }
+}
@@ -0,0 +1,60 @@
+pub mod display;
+pub mod frame;
+
+use crate::{errors::DisplayError, DisplayDriver};
+use display::MockDisplay;
+
+/// A display driver serving synthetic frames, for testing without a display or GPU
+#[derive(Debug, Clone)]
+pub struct MockDriver {
+  displays: Vec<MockDisplay>,
+}
+
+impl MockDriver {
+  /// Create [`MockDriver`] serving `displays`, the first of which is the primary display
+  pub fn new(displays: Vec<MockDisplay>) -> Self {
+    Self { displays }
+  }
+}
+
+impl Default for MockDriver {
+  /// A single 1920x1080 display showing a scrolling gradient
+  fn default() -> Self {
+    Self::new(vec![MockDisplay::new(1920, 1080)])
+  }
+}
+
+impl<'buf> DisplayDriver<'buf> for MockDriver {
+  type Display = MockDisplay;
+
+  fn name(&self) -> &'static str {
+    "mock"
+  }
+
+  /// Gets a copy of every display, frames captured from them are independent of each
+  /// other
+  fn all(&self) -> Result<Vec<Self::Display>, DisplayError> {
+    Ok(self.displays.clone())
+  }
+
+  fn primary(&self) -> Result<Option<Self::Display>, DisplayError> {
+    Ok(self.displays.first().cloned())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{display::MockDisplay, MockDriver};
+  use crate::{Display, DisplayDriver, Frame};
+
+  #[test]
+  fn test_mock_driver() {
+    let driver = MockDriver::new(vec![MockDisplay::new(4, 2), MockDisplay::new(8, 8)]);
+    let mut primary = driver.primary().unwrap().unwrap();
+
+    assert_eq!(driver.all().unwrap().len(), 2);
+    assert_eq!(primary.width().unwrap(), 4);
+    assert_eq!(Display::frame(&mut primary).unwrap().height(), 2);
+    assert!(MockDriver::new(Vec::new()).primary().unwrap().is_none());
+  }
+}
@@ -2,3 +2,364 @@
 pub mod dx11;
 #[cfg(target_os = "windows")]
 pub mod dxgi;
+#[cfg(target_os = "windows")]
+pub mod gdi;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+#[cfg(target_os = "macos")]
+pub mod quartz;
+#[cfg(target_os = "linux")]
+pub mod wayland;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub mod x11;
+
+use crate::{
+  errors::{DisplayError, DriverError, FrameError},
+  Display, OwnedFrame,
+};
+use std::{
+  thread,
+  time::{Duration, Instant},
+};
+
+/// A display driver that can be tried by [`capture`]
+#[derive(Debug, Clone, Copy)]
+pub struct Driver {
+  /// The name of the driver
+  pub name: &'static str,
+  /// Opens the primary display of the driver
+  pub open: fn() -> anyhow::Result<Capture>,
+}
+
+impl Driver {
+  /// Open the primary display and request a frame from it
+  ///
+  /// # Notes
+  /// A frame not being ready yet still counts as working.  The frame is kept and handed
+  /// out first by [`Capture::frame`] since, for DXGI, it's the only frame guaranteed on
+  /// an idle desktop.
+  pub fn probe(&self) -> anyhow::Result<Capture> {
+    let mut capture = (self.open)()?;
+    capture.pending = capture.display.next_frame()?;
+
+    Ok(capture)
+  }
+
+  /// Same as [`Driver::probe`] but, failures are reported as
+  /// [`DriverError::InitFailed`] naming the driver and keeping the error as its source
+  pub fn init(&self) -> Result<Capture, DriverError> {
+    self.probe().map_err(|err| DriverError::InitFailed {
+      name: self.name,
+      source: Box::<dyn std::error::Error + Send + Sync>::from(err).into(),
+    })
+  }
+}
+
+/// Get the drivers available on this platform in priority order
+///
+/// # Notes
+/// DXGI desktop duplication is preferred, GDI follows it as a fallback for sessions where
+/// duplication is unavailable (RDP, locked down policies, Windows 7).
+/// macOS uses `CGDisplayStream`.  On Linux wlr-screencopy is preferred over X11, which
+/// only sees X clients under a Wayland compositor.  X11 links against Xlib so, it's only
+/// available with the `x11` feature, which is enabled by default.
+pub fn drivers() -> Vec<Driver> {
+  #[allow(unused_mut)]
+  let mut drivers = Vec::new();
+
+  #[cfg(target_os = "windows")]
+  drivers.push(Driver {
+    name: "dxgi",
+    open: || {
+      let display = crate::DisplayDriver::primary(&dxgi::Dxgi)?
+        .ok_or_else(|| anyhow::anyhow!("No primary display attached"))?;
+
+      Ok(Capture::new("dxgi", display))
+    },
+  });
+
+  #[cfg(target_os = "windows")]
+  drivers.push(Driver {
+    name: "gdi",
+    open: || {
+      let display = crate::DisplayDriver::primary(&gdi::Gdi)?
+        .ok_or_else(|| anyhow::anyhow!("No primary display attached"))?;
+
+      Ok(Capture::new("gdi", display))
+    },
+  });
+
+  #[cfg(target_os = "macos")]
+  drivers.push(Driver {
+    name: "quartz",
+    open: || {
+      let display = crate::DisplayDriver::primary(&quartz::Quartz)?
+        .ok_or_else(|| anyhow::anyhow!("No main display attached"))?;
+
+      Ok(Capture::new("quartz", display))
+    },
+  });
+
+  #[cfg(target_os = "linux")]
+  drivers.push(Driver {
+    name: "wayland",
+    open: || {
+      let display = crate::DisplayDriver::primary(&wayland::Wayland)?
+        .ok_or_else(|| anyhow::anyhow!("No Wayland output attached"))?;
+
+      Ok(Capture::new("wayland", display))
+    },
+  });
+
+  #[cfg(all(target_os = "linux", feature = "x11"))]
+  drivers.push(Driver {
+    name: "x11",
+    open: || {
+      let display = crate::DisplayDriver::primary(&x11::X11)?
+        .ok_or_else(|| anyhow::anyhow!("No X11 display attached"))?;
+
+      Ok(Capture::new("x11", display))
+    },
+  });
+
+  drivers
+}
+
+/// Capture the primary display with the first of [`drivers`] that works
+///
+/// # Notes
+/// Drivers are tried by opening them and requesting a frame, not by checking the OS
+/// version, so failures at runtime count too.  On Windows, when DXGI duplication is denied
+/// (e.g. in an RDP session) or unsupported, capture falls back to GDI.  The returned
+/// [`Capture`] boxes the display of whichever driver worked.
+pub fn capture() -> Result<Capture, DriverError> {
+  capture_with(&drivers())
+}
+
+/// Take a single screenshot of the primary display
+///
+/// # Notes
+/// The display is opened with [`capture`] and the frame requested while probing it is
+/// returned so, a static desktop doesn't have to present again.  When that frame wasn't
+/// ready yet see [`Capture::snapshot`] for how long it waits for one.
+pub fn capture_primary() -> anyhow::Result<OwnedFrame> {
+  capture()?.snapshot()
+}
+
+/// Capture the primary display with the first of `drivers` that works
+///
+/// # Notes
+/// A driver works when its display opens and a frame can be requested from it, drivers
+/// failing either are skipped.  When every driver fails the error lists why each one did.
+pub fn capture_with(drivers: &[Driver]) -> Result<Capture, DriverError> {
+  let mut errors = Vec::new();
+
+  for driver in drivers {
+    match driver.init() {
+      Ok(capture) => return Ok(capture),
+      Err(err) => errors.push(err),
+    }
+  }
+
+  match errors.is_empty() {
+    true => Err(DriverError::NoDriverAvailable),
+    false => Err(DriverError::AllFailed(errors)),
+  }
+}
+
+/// A display of any driver
+///
+/// # Notes
+/// Frames are copied into an [`OwnedFrame`] since the frame type of each driver differs.
+pub struct Capture {
+  driver: &'static str,
+  display: Box<dyn AnyDisplay>,
+  pending: Option<OwnedFrame>,
+}
+
+impl Capture {
+  /// Create [`Capture`] from the display of a driver
+  pub fn new<D>(driver: &'static str, display: D) -> Self
+  where
+    D: for<'a> Display<'a> + 'static,
+  {
+    Self {
+      driver,
+      display: Box::new(display),
+      pending: None,
+    }
+  }
+
+  /// The name of the driver frames are captured with
+  pub const fn driver(&self) -> &'static str {
+    self.driver
+  }
+
+  /// The width of the display
+  pub fn width(&self) -> Result<usize, DisplayError> {
+    self.display.width()
+  }
+
+  /// The height of the display
+  pub fn height(&self) -> Result<usize, DisplayError> {
+    self.display.height()
+  }
+
+  /// Copy the next frame, `None` when no new frame is ready yet
+  ///
+  /// # Notes
+  /// The frame requested by [`Driver::probe`] is returned first.
+  pub fn frame(&mut self) -> anyhow::Result<Option<OwnedFrame>> {
+    match self.pending.take() {
+      Some(frame) => Ok(Some(frame)),
+      None => self.display.next_frame(),
+    }
+  }
+
+  /// Copy the next frame, retrying until one is ready
+  ///
+  /// # Notes
+  /// Right after opening a display the first frame may not be ready yet so, frames are
+  /// requested until one is, failing after a second without one.
+  pub fn snapshot(&mut self) -> anyhow::Result<OwnedFrame> {
+    const DEADLINE: Duration = Duration::from_secs(1);
+
+    let start = Instant::now();
+
+    loop {
+      match self.frame()? {
+        Some(frame) => return Ok(frame),
+        None if start.elapsed() < DEADLINE => thread::sleep(Duration::from_millis(1)),
+        None => anyhow::bail!("No frame was ready within {:?}", DEADLINE),
+      }
+    }
+  }
+}
+
+/// Object safe view of a [`Display`]
+trait AnyDisplay {
+  fn width(&self) -> Result<usize, DisplayError>;
+  fn height(&self) -> Result<usize, DisplayError>;
+  fn next_frame(&mut self) -> anyhow::Result<Option<OwnedFrame>>;
+}
+
+impl<D> AnyDisplay for D
+where
+  D: for<'a> Display<'a>,
+{
+  fn width(&self) -> Result<usize, DisplayError> {
+    Display::width(self)
+  }
+
+  fn height(&self) -> Result<usize, DisplayError> {
+    Display::height(self)
+  }
+
+  fn next_frame(&mut self) -> anyhow::Result<Option<OwnedFrame>> {
+    match self.frame() {
+      Ok(frame) => OwnedFrame::from_frame(&frame).map(Some),
+      Err(FrameError::WouldBlock) => Ok(None),
+      Err(err) => Err(err.into()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    capture_with,
+    mock::display::{MockDisplay, MockPattern},
+    Capture, Driver,
+  };
+  use crate::errors::DriverError;
+
+  fn broken() -> Driver {
+    Driver {
+      name: "broken",
+      open: || anyhow::bail!("duplication is unavailable"),
+    }
+  }
+
+  fn mock() -> Driver {
+    Driver {
+      name: "mock",
+      open: || {
+        let display = MockDisplay::new(1, 1).with_pattern(MockPattern::Solid([7; 4]));
+        Ok(Capture::new("mock", display))
+      },
+    }
+  }
+
+  #[test]
+  fn test_capture_falls_back() {
+    let mut capture = capture_with(&[broken(), mock()]).unwrap();
+
+    assert_eq!(capture.driver(), "mock");
+    assert_eq!(capture.frame().unwrap().unwrap().data(), &[7; 4]);
+    assert_eq!(capture.snapshot().unwrap().data(), &[7; 4]);
+  }
+
+  /// A display that only ever presents one frame, like a static desktop over DXGI
+  fn idle() -> Driver {
+    Driver {
+      name: "idle",
+      open: || {
+        let display = MockDisplay::new(1, 1).with_idle_after(1);
+        Ok(Capture::new("idle", display))
+      },
+    }
+  }
+
+  #[test]
+  fn test_probe_frame_is_kept() {
+    let mut capture = idle().probe().unwrap();
+
+    assert_eq!(capture.frame().unwrap().unwrap().sequence(), 0);
+    assert!(capture.frame().unwrap().is_none());
+  }
+
+  #[test]
+  fn test_snapshot_of_static_desktop() {
+    // What `capture_primary` does with the drivers of the platform
+    let frame = capture_with(&[broken(), idle()])
+      .unwrap()
+      .snapshot()
+      .unwrap();
+
+    assert_eq!((frame.width(), frame.height()), (1, 1));
+  }
+
+  #[test]
+  fn test_capture_reports_every_failure() {
+    let err = capture_with(&[broken(), broken()]).err().unwrap();
+
+    assert!(err
+      .to_string()
+      .contains("broken: duplication is unavailable"));
+
+    let errors = match err {
+      DriverError::AllFailed(errors) => errors,
+      err => panic!("{:?}", err),
+    };
+    assert_eq!(errors.len(), 2);
+
+    // The error the driver failed with is kept as the source
+    for err in &errors {
+      let source = std::error::Error::source(err).unwrap();
+
+      assert!(matches!(
+        err,
+        DriverError::InitFailed { name: "broken", .. }
+      ));
+      assert_eq!(source.to_string(), "duplication is unavailable");
+
+      // The source is only reported through the chain, not repeated in the message
+      assert_eq!(err.to_string(), "Failed to initialize broken");
+    }
+
+    assert!(matches!(
+      capture_with(&[]).err(),
+      Some(DriverError::NoDriverAvailable)
+    ));
+  }
+}
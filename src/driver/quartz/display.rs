@@ -0,0 +1,438 @@
+//! Provides interface to get display information for CGDisplayStream frame capture.
+
+use super::{
+  errors::FrameError as QuartzFrameError,
+  ffi::{
+    _NSConcreteStackBlock, dispatch_queue_create, dispatch_release, Block,
+    BlockDescriptor, CFRelease, CFRetain, CGDirectDisplayID, CGDisplayCopyDisplayMode,
+    CGDisplayModeGetPixelHeight, CGDisplayModeGetPixelWidth, CGDisplayModeGetRefreshRate,
+    CGDisplayModeRelease, CGDisplayStreamCreateWithDispatchQueue, CGDisplayStreamRef,
+    CGDisplayStreamStart, CGDisplayStreamStop, CGDisplayStreamUpdateGetRects,
+    CGDisplayStreamUpdateRef, CGGetActiveDisplayList, CGMainDisplayID, CGRect,
+    DispatchQueue, IOSurfaceDecrementUseCount, IOSurfaceGetBaseAddress,
+    IOSurfaceGetBytesPerRow, IOSurfaceGetHeight, IOSurfaceGetPixelFormat,
+    IOSurfaceGetWidth, IOSurfaceIncrementUseCount, IOSurfaceLock, IOSurfaceRef,
+    IOSurfaceUnlock, BLOCK_HAS_COPY_DISPOSE, CG_ERROR_SUCCESS, FRAME_COMPLETE,
+    FRAME_STOPPED, LOCK_READ_ONLY, PIXEL_FORMAT_BGRA, UPDATE_DIRTY_RECTS,
+  },
+  frame::QuartzFrame,
+};
+use crate::{
+  alloc,
+  errors::{DisplayError, FrameError},
+  CaptureConfig, DirtyRect, Display, FrameFormat,
+};
+use std::{
+  mem,
+  os::raw::{c_int, c_void},
+  ptr, slice,
+  sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+/// Get every active display
+pub(super) fn displays() -> Result<Vec<QuartzDisplay>, DisplayError> {
+  unsafe {
+    let mut count = 0;
+    match CGGetActiveDisplayList(0, ptr::null_mut(), &mut count) {
+      CG_ERROR_SUCCESS => {}
+      err => return Err(DisplayError::DisplayList(err)),
+    }
+
+    let mut ids = vec![0; count as usize];
+    match CGGetActiveDisplayList(count, ids.as_mut_ptr(), &mut count) {
+      CG_ERROR_SUCCESS => {}
+      err => return Err(DisplayError::DisplayList(err)),
+    }
+
+    ids.truncate(count as usize);
+
+    let main = CGMainDisplayID();
+    Ok(
+      ids
+        .into_iter()
+        .map(|id| QuartzDisplay::new(id, id == main))
+        .collect(),
+    )
+  }
+}
+
+/// A macOS display captured through a `CGDisplayStream`
+#[derive(Debug)]
+pub struct QuartzDisplay {
+  id: CGDirectDisplayID,
+  primary: bool,
+  width: usize,
+  height: usize,
+  refresh_rate: Option<f32>,
+  config: CaptureConfig,
+  stream: Option<Stream>,
+  data: Vec<u8>,
+  dirty: Vec<DirtyRect>,
+  sequence: u64,
+}
+
+impl QuartzDisplay {
+  /// Create [`QuartzDisplay`] sized to the pixels, not points, of the current mode
+  unsafe fn new(id: CGDirectDisplayID, primary: bool) -> Self {
+    let mode = CGDisplayCopyDisplayMode(id);
+    let (width, height, refresh_rate) = match mode.is_null() {
+      true => (0, 0, None),
+      false => {
+        let size = (
+          CGDisplayModeGetPixelWidth(mode),
+          CGDisplayModeGetPixelHeight(mode),
+        );
+
+        // Built-in panels report a refresh rate of zero
+        let refresh_rate = CGDisplayModeGetRefreshRate(mode);
+        CGDisplayModeRelease(mode);
+
+        (
+          size.0,
+          size.1,
+          (refresh_rate > 0.0).then_some(refresh_rate as f32),
+        )
+      }
+    };
+
+    Self {
+      id,
+      primary,
+      width,
+      height,
+      refresh_rate,
+      config: CaptureConfig::default(),
+      stream: None,
+      data: Vec::new(),
+      dirty: Vec::new(),
+      sequence: 0,
+    }
+  }
+
+  /// The `CGDirectDisplayID` of the display
+  pub const fn id(&self) -> CGDirectDisplayID {
+    self.id
+  }
+
+  /// The width of the display in pixels
+  pub const fn width(&self) -> usize {
+    self.width
+  }
+
+  /// The height of the display in pixels
+  pub const fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Whether the display is the main display
+  pub const fn is_primary(&self) -> bool {
+    self.primary
+  }
+
+  /// Set the capture options used for subsequent frames
+  ///
+  /// # Notes
+  /// Only the timeout applies.
+  pub fn set_config(&mut self, config: CaptureConfig) {
+    self.config = config;
+  }
+
+  /// Wait for the stream to deliver a surface and copy it, `None` when the timeout
+  /// passed first
+  ///
+  /// # Notes
+  /// The stream is started on the first call and restarted after it stops.
+  ///
+  /// # Safety
+  /// Calls to CoreGraphics and IOSurface
+  unsafe fn capture(&mut self) -> Result<Option<FrameFormat>, QuartzFrameError> {
+    if self.stream.is_none() {
+      self.stream = Some(Stream::start(self.id, self.width, self.height)?);
+    }
+
+    let timeout = self.config.frame_timeout(self.refresh_rate);
+    let shared = match &self.stream {
+      Some(stream) => stream.shared.clone(),
+      None => return Err(QuartzFrameError::Stopped),
+    };
+
+    let (mut latest, _) = shared
+      .ready
+      .wait_timeout_while(shared.lock(), timeout, |latest| {
+        latest.surface.is_none() && !latest.stopped
+      })
+      .unwrap_or_else(|err| err.into_inner());
+
+    if latest.stopped {
+      drop(latest);
+      self.stream = None;
+
+      return Err(QuartzFrameError::Stopped);
+    }
+
+    let surface = match latest.surface.take() {
+      Some(surface) => surface,
+      None => return Ok(None),
+    };
+
+    self.dirty = mem::take(&mut latest.dirty);
+    drop(latest);
+
+    let format = match IOSurfaceGetPixelFormat(surface.0) {
+      PIXEL_FORMAT_BGRA => FrameFormat::B8G8R8A8,
+      format => return Err(QuartzFrameError::UnsupportedFormat(format)),
+    };
+
+    match IOSurfaceLock(surface.0, LOCK_READ_ONLY, ptr::null_mut()) {
+      0 => {}
+      err => return Err(QuartzFrameError::Lock(err)),
+    }
+
+    let len = IOSurfaceGetBytesPerRow(surface.0) * IOSurfaceGetHeight(surface.0);
+    let bytes =
+      slice::from_raw_parts(IOSurfaceGetBaseAddress(surface.0) as *const u8, len);
+
+    // Copied so, the surface goes back to the stream's pool right away
+    let mut data = alloc::allocate(len);
+    data.extend_from_slice(bytes);
+    alloc::release(mem::replace(&mut self.data, data));
+
+    IOSurfaceUnlock(surface.0, LOCK_READ_ONLY, ptr::null_mut());
+
+    self.width = IOSurfaceGetWidth(surface.0);
+    self.height = IOSurfaceGetHeight(surface.0);
+
+    Ok(Some(format))
+  }
+}
+
+impl<'frame> Display<'frame> for QuartzDisplay {
+  type Frame = QuartzFrame<'frame>;
+
+  fn width(&self) -> Result<usize, DisplayError> {
+    Ok(self.width())
+  }
+
+  fn height(&self) -> Result<usize, DisplayError> {
+    Ok(self.height())
+  }
+
+  fn frame(&'frame mut self) -> Result<Self::Frame, FrameError> {
+    let format = match unsafe { self.capture()? } {
+      Some(format) => format,
+      None => return Err(FrameError::WouldBlock),
+    };
+
+    let sequence = self.sequence;
+    self.sequence += 1;
+
+    Ok(QuartzFrame::new(
+      &self.data,
+      self.width,
+      self.height,
+      format,
+      &self.dirty,
+      sequence,
+    ))
+  }
+}
+
+/// A running `CGDisplayStream`
+#[derive(Debug)]
+struct Stream {
+  stream: CGDisplayStreamRef,
+  queue: DispatchQueue,
+  shared: Arc<Shared>,
+}
+
+impl Stream {
+  /// Create and start a stream of `display` delivering BGRA surfaces
+  ///
+  /// # Safety
+  /// Calls to CoreGraphics and libdispatch
+  unsafe fn start(
+    display: CGDirectDisplayID,
+    width: usize,
+    height: usize,
+  ) -> Result<Self, QuartzFrameError> {
+    static DESCRIPTOR: BlockDescriptor = BlockDescriptor {
+      reserved: 0,
+      size: mem::size_of::<Block>() as _,
+      copy: copy_block,
+      dispose: dispose_block,
+    };
+
+    let shared = Arc::new(Shared::default());
+    let queue = dispatch_queue_create(b"scraptor.quartz\0".as_ptr() as _, ptr::null());
+
+    // The stream keeps a heap copy of the block, which holds its own reference to
+    // `shared` through the copy and dispose helpers
+    let mut handler = Block {
+      isa: &_NSConcreteStackBlock as *const _ as *const c_void,
+      flags: BLOCK_HAS_COPY_DISPOSE,
+      reserved: 0,
+      invoke: on_frame,
+      descriptor: &DESCRIPTOR,
+      context: Arc::as_ptr(&shared) as *const c_void,
+    };
+
+    let stream = CGDisplayStreamCreateWithDispatchQueue(
+      display,
+      width,
+      height,
+      PIXEL_FORMAT_BGRA as i32,
+      ptr::null(),
+      queue,
+      &mut handler,
+    );
+
+    if stream.is_null() {
+      dispatch_release(queue);
+      return Err(QuartzFrameError::CreateStream);
+    }
+
+    let stream = Self {
+      stream,
+      queue,
+      shared,
+    };
+
+    match CGDisplayStreamStart(stream.stream) {
+      CG_ERROR_SUCCESS => Ok(stream),
+      err => Err(QuartzFrameError::StartStream(err)),
+    }
+  }
+}
+
+impl Drop for Stream {
+  fn drop(&mut self) {
+    unsafe {
+      CGDisplayStreamStop(self.stream);
+      CFRelease(self.stream as *const c_void);
+      dispatch_release(self.queue);
+    }
+  }
+}
+
+/// State handed from the stream's dispatch queue to the capturing thread
+#[derive(Debug, Default)]
+struct Shared {
+  latest: Mutex<Latest>,
+  ready: Condvar,
+}
+
+impl Shared {
+  fn lock(&self) -> MutexGuard<'_, Latest> {
+    self.latest.lock().unwrap_or_else(|err| err.into_inner())
+  }
+}
+
+#[derive(Debug, Default)]
+struct Latest {
+  surface: Option<Surface>,
+  dirty: Vec<DirtyRect>,
+  stopped: bool,
+}
+
+/// A retained IOSurface kept out of the stream's pool while held
+#[derive(Debug)]
+struct Surface(IOSurfaceRef);
+
+// IOSurfaces are safe to use from any thread
+unsafe impl Send for Surface {}
+
+impl Drop for Surface {
+  fn drop(&mut self) {
+    unsafe {
+      IOSurfaceDecrementUseCount(self.0);
+      CFRelease(self.0 as *const c_void);
+    }
+  }
+}
+
+/// Handle a stream update on the stream's dispatch queue
+unsafe extern "C" fn on_frame(
+  block: *mut c_void,
+  status: c_int,
+  _: u64,
+  surface: IOSurfaceRef,
+  update: CGDisplayStreamUpdateRef,
+) {
+  let shared = &*((*(block as *mut Block)).context as *const Shared);
+  let mut latest = shared.lock();
+
+  match status {
+    FRAME_COMPLETE if !surface.is_null() => {
+      CFRetain(surface as *const c_void);
+      IOSurfaceIncrementUseCount(surface);
+
+      // Updates replaced before being read still changed pixels
+      latest.surface = Some(Surface(surface));
+      latest
+        .dirty
+        .extend(update_rects(update).iter().map(dirty_rect));
+    }
+    FRAME_STOPPED => latest.stopped = true,
+    _ => return,
+  }
+
+  shared.ready.notify_all();
+}
+
+/// Get the dirty rects of a stream update
+///
+/// # Safety
+/// `update` must be null or a valid update
+unsafe fn update_rects<'a>(update: CGDisplayStreamUpdateRef) -> &'a [CGRect] {
+  let mut count = 0;
+  let rects = match update.is_null() {
+    true => ptr::null(),
+    false => CGDisplayStreamUpdateGetRects(update, UPDATE_DIRTY_RECTS, &mut count),
+  };
+
+  match rects.is_null() {
+    true => &[],
+    false => slice::from_raw_parts(rects, count),
+  }
+}
+
+/// Convert a Quartz rect, an origin and size with a top-left origin in stream pixels,
+/// into a [`DirtyRect`] covering every pixel it touches
+fn dirty_rect(rect: &CGRect) -> DirtyRect {
+  DirtyRect::new(
+    rect.origin.y.floor() as i32,
+    (rect.origin.x + rect.size.width).ceil() as i32,
+    (rect.origin.y + rect.size.height).ceil() as i32,
+    rect.origin.x.floor() as i32,
+  )
+}
+
+unsafe extern "C" fn copy_block(dst: *mut Block, _: *const Block) {
+  Arc::increment_strong_count((*dst).context as *const Shared);
+}
+
+unsafe extern "C" fn dispose_block(block: *mut Block) {
+  Arc::decrement_strong_count((*block).context as *const Shared);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::dirty_rect;
+  use crate::{
+    driver::quartz::ffi::{CGPoint, CGRect, CGSize},
+    DirtyRect,
+  };
+
+  #[test]
+  fn test_dirty_rect_from_quartz() {
+    let rect = CGRect {
+      origin: CGPoint { x: 10.5, y: 20.0 },
+      size: CGSize {
+        width: 100.0,
+        height: 50.25,
+      },
+    };
+
+    assert_eq!(dirty_rect(&rect), DirtyRect::new(20, 111, 71, 10));
+  }
+}
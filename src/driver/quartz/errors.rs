@@ -0,0 +1,14 @@
+/// An error that occurs when streaming a display
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+  #[error("Failed to create the display stream")]
+  CreateStream,
+  #[error("Failed to start the display stream, CGError `{0}`")]
+  StartStream(i32),
+  #[error("The display stream stopped")]
+  Stopped,
+  #[error("Failed to lock the frame surface, IOReturn `{0:#x}`")]
+  Lock(i32),
+  #[error("Unsupported surface pixel format `{0:#x}`")]
+  UnsupportedFormat(u32),
+}
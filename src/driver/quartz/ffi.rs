@@ -0,0 +1,138 @@
+//! Provides the subset of CoreGraphics, IOSurface and libdispatch used for capture
+
+use std::os::raw::{c_char, c_double, c_int, c_ulong, c_void};
+
+pub type CGDirectDisplayID = u32;
+pub type CGError = i32;
+pub type CGDisplayStreamRef = *mut c_void;
+pub type CGDisplayStreamUpdateRef = *const c_void;
+pub type CGDisplayModeRef = *mut c_void;
+pub type CFTypeRef = *const c_void;
+pub type IOSurfaceRef = *mut c_void;
+pub type DispatchQueue = *mut c_void;
+
+pub const CG_ERROR_SUCCESS: CGError = 0;
+
+/// `kCGDisplayStreamFrameStatusFrameComplete`
+pub const FRAME_COMPLETE: c_int = 0;
+/// `kCGDisplayStreamFrameStatusStopped`
+pub const FRAME_STOPPED: c_int = 3;
+/// `kCGDisplayStreamUpdateDirtyRects`, the union of refreshed and moved rects
+pub const UPDATE_DIRTY_RECTS: c_int = 2;
+/// `kIOSurfaceLockReadOnly`
+pub const LOCK_READ_ONLY: u32 = 0x1;
+/// `kCVPixelFormatType_32BGRA`
+pub const PIXEL_FORMAT_BGRA: u32 = u32::from_be_bytes(*b"BGRA");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CGPoint {
+  pub x: c_double,
+  pub y: c_double,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CGSize {
+  pub width: c_double,
+  pub height: c_double,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CGRect {
+  pub origin: CGPoint,
+  pub size: CGSize,
+}
+
+/// The handler of a display stream
+pub type FrameHandler = unsafe extern "C" fn(
+  block: *mut c_void,
+  status: c_int,
+  display_time: u64,
+  surface: IOSurfaceRef,
+  update: CGDisplayStreamUpdateRef,
+);
+
+/// The flag of a block with copy and dispose helpers
+pub const BLOCK_HAS_COPY_DISPOSE: c_int = 1 << 25;
+
+/// The layout of a block literal capturing a single pointer
+#[repr(C)]
+pub struct Block {
+  pub isa: *const c_void,
+  pub flags: c_int,
+  pub reserved: c_int,
+  pub invoke: FrameHandler,
+  pub descriptor: *const BlockDescriptor,
+  pub context: *const c_void,
+}
+
+#[repr(C)]
+pub struct BlockDescriptor {
+  pub reserved: c_ulong,
+  pub size: c_ulong,
+  pub copy: unsafe extern "C" fn(dst: *mut Block, src: *const Block),
+  pub dispose: unsafe extern "C" fn(block: *mut Block),
+}
+
+#[link(name = "System", kind = "dylib")]
+extern "C" {
+  pub static _NSConcreteStackBlock: [*const c_void; 32];
+
+  pub fn dispatch_queue_create(
+    label: *const c_char,
+    attr: *const c_void,
+  ) -> DispatchQueue;
+  pub fn dispatch_release(object: *mut c_void);
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+  pub fn CFRetain(object: CFTypeRef) -> CFTypeRef;
+  pub fn CFRelease(object: CFTypeRef);
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+  pub fn CGGetActiveDisplayList(
+    max_displays: u32,
+    displays: *mut CGDirectDisplayID,
+    count: *mut u32,
+  ) -> CGError;
+  pub fn CGMainDisplayID() -> CGDirectDisplayID;
+  pub fn CGDisplayCopyDisplayMode(display: CGDirectDisplayID) -> CGDisplayModeRef;
+  pub fn CGDisplayModeGetPixelWidth(mode: CGDisplayModeRef) -> usize;
+  pub fn CGDisplayModeGetPixelHeight(mode: CGDisplayModeRef) -> usize;
+  pub fn CGDisplayModeGetRefreshRate(mode: CGDisplayModeRef) -> c_double;
+  pub fn CGDisplayModeRelease(mode: CGDisplayModeRef);
+  pub fn CGDisplayStreamCreateWithDispatchQueue(
+    display: CGDirectDisplayID,
+    output_width: usize,
+    output_height: usize,
+    pixel_format: i32,
+    properties: CFTypeRef,
+    queue: DispatchQueue,
+    handler: *mut Block,
+  ) -> CGDisplayStreamRef;
+  pub fn CGDisplayStreamStart(stream: CGDisplayStreamRef) -> CGError;
+  pub fn CGDisplayStreamStop(stream: CGDisplayStreamRef) -> CGError;
+  pub fn CGDisplayStreamUpdateGetRects(
+    update: CGDisplayStreamUpdateRef,
+    rect_type: c_int,
+    count: *mut usize,
+  ) -> *const CGRect;
+}
+
+#[link(name = "IOSurface", kind = "framework")]
+extern "C" {
+  pub fn IOSurfaceLock(surface: IOSurfaceRef, options: u32, seed: *mut u32) -> i32;
+  pub fn IOSurfaceUnlock(surface: IOSurfaceRef, options: u32, seed: *mut u32) -> i32;
+  pub fn IOSurfaceGetBaseAddress(surface: IOSurfaceRef) -> *mut c_void;
+  pub fn IOSurfaceGetBytesPerRow(surface: IOSurfaceRef) -> usize;
+  pub fn IOSurfaceGetWidth(surface: IOSurfaceRef) -> usize;
+  pub fn IOSurfaceGetHeight(surface: IOSurfaceRef) -> usize;
+  pub fn IOSurfaceGetPixelFormat(surface: IOSurfaceRef) -> u32;
+  pub fn IOSurfaceIncrementUseCount(surface: IOSurfaceRef);
+  pub fn IOSurfaceDecrementUseCount(surface: IOSurfaceRef);
+}
@@ -0,0 +1,71 @@
+use crate::{
+  convert::Layout, errors::FrameError, DirtyRect, Frame, FrameFormat, MovedRect,
+};
+use std::borrow::Cow;
+
+/// A copy of the IOSurface delivered by a display stream
+#[derive(Debug, Clone)]
+pub struct QuartzFrame<'a> {
+  data: &'a [u8],
+  width: usize,
+  height: usize,
+  format: FrameFormat,
+  dirty: &'a [DirtyRect],
+  sequence: u64,
+}
+
+impl<'a> QuartzFrame<'a> {
+  pub fn new(
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    format: FrameFormat,
+    dirty: &'a [DirtyRect],
+    sequence: u64,
+  ) -> Self {
+    Self {
+      data,
+      width,
+      height,
+      format,
+      dirty,
+      sequence,
+    }
+  }
+}
+
+impl<'frame> Frame<'frame> for QuartzFrame<'frame> {
+  fn width(&self) -> usize {
+    self.width
+  }
+
+  fn height(&self) -> usize {
+    self.height
+  }
+
+  /// The rects refreshed or moved by every stream update since the last frame
+  fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    Ok(self.dirty.to_vec())
+  }
+
+  /// Moved rects are reported as part of [`Frame::dirty`]
+  fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+    Ok(Vec::new())
+  }
+
+  fn format(&self) -> FrameFormat {
+    self.format
+  }
+
+  fn as_bytes(&self) -> anyhow::Result<Cow<'frame, [u8]>> {
+    Ok(Cow::Borrowed(self.data))
+  }
+
+  fn stride(&self) -> usize {
+    Layout::infer(self.width, self.height, self.format, self.data.len()).stride
+  }
+
+  fn sequence(&self) -> u64 {
+    self.sequence
+  }
+}
@@ -0,0 +1,54 @@
+pub mod display;
+pub mod errors;
+pub mod ffi;
+pub mod frame;
+
+use crate::{errors::DisplayError, DisplayDriver};
+use display::QuartzDisplay;
+
+/// The `CGDisplayStream` display driver
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quartz;
+
+impl<'buf> DisplayDriver<'buf> for Quartz {
+  type Display = QuartzDisplay;
+
+  fn name(&self) -> &'static str {
+    "quartz"
+  }
+
+  fn all(&self) -> Result<Vec<Self::Display>, DisplayError> {
+    display::displays()
+  }
+
+  fn primary(&self) -> Result<Option<Self::Display>, DisplayError> {
+    Ok(self.all()?.into_iter().find(QuartzDisplay::is_primary))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Quartz;
+  use crate::{errors::FrameError, Display, DisplayDriver, Frame};
+
+  #[test]
+  fn test_quartz_frame() {
+    let mut display = Quartz.primary().unwrap().unwrap();
+
+    // The stream may take a few refresh intervals to deliver its first surface
+    for _ in 0..100 {
+      let frame = match Display::frame(&mut display) {
+        Ok(frame) => frame,
+        Err(FrameError::WouldBlock) => continue,
+        Err(err) => panic!("{}", err),
+      };
+
+      let bytes = frame.as_bytes().unwrap();
+      let bpp = frame.format().bytes_per_pixel();
+      assert!(bytes.len() >= frame.width() * frame.height() * bpp);
+      return;
+    }
+
+    panic!("No frame was delivered");
+  }
+}
@@ -0,0 +1,636 @@
+//! Provides interface to get display information for wlr-screencopy output capture.
+
+use super::{
+  errors::FrameError as WaylandFrameError,
+  frame::WaylandFrame,
+  wire::{Connection, Event, Request, DISPLAY},
+};
+use crate::{
+  errors::{DisplayError, FrameError},
+  CaptureConfig, Display, FrameFormat,
+};
+use std::{
+  cell::RefCell,
+  fs::File,
+  os::{
+    raw::c_void,
+    unix::io::{AsRawFd, FromRawFd},
+  },
+  ptr,
+  rc::Rc,
+  slice,
+  time::Instant,
+};
+
+/// `wl_display.get_registry`
+const GET_REGISTRY: u16 = 1;
+/// `wl_registry.global`
+const GLOBAL: u16 = 0;
+/// `wl_registry.bind`
+const BIND: u16 = 0;
+
+/// `wl_output.geometry`
+const GEOMETRY: u16 = 0;
+/// `wl_output.mode`
+const MODE: u16 = 1;
+/// `wl_output.name`
+const NAME: u16 = 4;
+/// The `wl_output.mode` flag of the current mode
+const MODE_CURRENT: u32 = 0x1;
+
+/// `wl_shm.create_pool`
+const CREATE_POOL: u16 = 0;
+/// `wl_shm_pool.create_buffer`
+const CREATE_BUFFER: u16 = 0;
+/// `wl_shm_pool.destroy`
+const POOL_DESTROY: u16 = 1;
+/// `wl_buffer.destroy`
+const BUFFER_DESTROY: u16 = 0;
+
+/// `zwlr_screencopy_manager_v1.capture_output`
+const CAPTURE_OUTPUT: u16 = 0;
+/// `zwlr_screencopy_frame_v1.copy`
+const COPY: u16 = 0;
+/// `zwlr_screencopy_frame_v1.destroy`
+const FRAME_DESTROY: u16 = 1;
+/// `zwlr_screencopy_frame_v1.buffer`
+const FRAME_BUFFER: u16 = 0;
+/// `zwlr_screencopy_frame_v1.flags`
+const FRAME_FLAGS: u16 = 1;
+/// `zwlr_screencopy_frame_v1.ready`
+const FRAME_READY: u16 = 2;
+/// `zwlr_screencopy_frame_v1.failed`
+const FRAME_FAILED: u16 = 3;
+/// `zwlr_screencopy_frame_v1.buffer_done`
+const FRAME_BUFFER_DONE: u16 = 6;
+/// The `zwlr_screencopy_frame_v1.flags` flag of an upside down copy
+const FLAG_Y_INVERT: u32 = 0x1;
+
+/// `wl_shm` format `ARGB8888`
+const ARGB8888: u32 = 0;
+/// `wl_shm` format `XRGB8888`, the alpha byte is padding
+const XRGB8888: u32 = 1;
+/// `wl_shm` format `ABGR8888`
+const ABGR8888: u32 = u32::from_le_bytes(*b"AB24");
+/// `wl_shm` format `XBGR8888`, the alpha byte is padding
+const XBGR8888: u32 = u32::from_le_bytes(*b"XB24");
+/// `wl_shm` format `RGB888`
+const RGB888: u32 = u32::from_le_bytes(*b"RG24");
+/// `wl_shm` format `BGR888`
+const BGR888: u32 = u32::from_le_bytes(*b"BG24");
+
+/// The newest `zwlr_screencopy_manager_v1` version understood
+const SCREENCOPY_VERSION: u32 = 3;
+/// The newest `wl_output` version understood
+const OUTPUT_VERSION: u32 = 4;
+
+/// The globals bound from the compositor, shared by every display enumerated from it
+#[derive(Debug)]
+pub(super) struct Client {
+  conn: Connection,
+  shm: u32,
+  screencopy: u32,
+  screencopy_version: u32,
+}
+
+/// A global announced by the compositor
+struct Global {
+  name: u32,
+  interface: String,
+  version: u32,
+}
+
+/// What a `wl_output` reported about itself
+#[derive(Default)]
+struct OutputInfo {
+  name: Option<String>,
+  position: (i32, i32),
+  size: (usize, usize),
+}
+
+/// Connect to the compositor and get a display for each of its outputs
+///
+/// # Notes
+/// Wayland has no notion of a primary output so, the first output is treated as the
+/// primary display.
+pub(super) fn displays() -> Result<Vec<WaylandDisplay>, WaylandFrameError> {
+  let mut conn = Connection::connect()?;
+  let registry = conn.new_id();
+  let mut globals = Vec::new();
+
+  conn.send(Request::new(DISPLAY, GET_REGISTRY).uint(registry))?;
+  conn.roundtrip(|event| {
+    if event.object == registry && event.opcode == GLOBAL {
+      let mut args = event.args();
+
+      globals.push(Global {
+        name: args.uint()?,
+        interface: args.string()?,
+        version: args.uint()?,
+      });
+    }
+
+    Ok(())
+  })?;
+
+  let find = |interface: &'static str| {
+    globals
+      .iter()
+      .find(|global| global.interface == interface)
+      .ok_or(WaylandFrameError::Unsupported(interface))
+  };
+
+  let shm = bind(&mut conn, registry, find("wl_shm")?, 1)?;
+  let screencopy = find("zwlr_screencopy_manager_v1")?;
+  let screencopy_version = screencopy.version.min(SCREENCOPY_VERSION);
+  let screencopy = bind(&mut conn, registry, screencopy, SCREENCOPY_VERSION)?;
+
+  let mut outputs = Vec::new();
+  for global in globals
+    .iter()
+    .filter(|global| global.interface == "wl_output")
+  {
+    let id = bind(&mut conn, registry, global, OUTPUT_VERSION)?;
+    outputs.push((id, global.name, OutputInfo::default()));
+  }
+
+  // Outputs describe themselves once bound
+  conn.roundtrip(|event| {
+    if let Some((_, _, info)) = outputs.iter_mut().find(|(id, ..)| *id == event.object) {
+      info.update(event)?;
+    }
+
+    Ok(())
+  })?;
+
+  let client = Rc::new(RefCell::new(Client {
+    conn,
+    shm,
+    screencopy,
+    screencopy_version,
+  }));
+
+  let displays = outputs
+    .into_iter()
+    .enumerate()
+    .map(|(idx, (output, global, info))| WaylandDisplay {
+      client: client.clone(),
+      output,
+      name: info.name.unwrap_or_else(|| format!("wl_output-{}", global)),
+      position: info.position,
+      width: info.size.0,
+      height: info.size.1,
+      primary: idx == 0,
+      config: CaptureConfig::default(),
+      pending: None,
+      buffer: None,
+      flags: 0,
+      sequence: 0,
+    })
+    .collect();
+
+  Ok(displays)
+}
+
+/// Bind `global` at the newest version both sides understand
+fn bind(
+  conn: &mut Connection,
+  registry: u32,
+  global: &Global,
+  version: u32,
+) -> Result<u32, WaylandFrameError> {
+  let id = conn.new_id();
+
+  conn.send(
+    Request::new(registry, BIND)
+      .uint(global.name)
+      .string(&global.interface)
+      .uint(global.version.min(version))
+      .uint(id),
+  )?;
+
+  Ok(id)
+}
+
+impl OutputInfo {
+  /// Apply a `wl_output` event
+  fn update(&mut self, event: &Event) -> Result<(), WaylandFrameError> {
+    let mut args = event.args();
+
+    match event.opcode {
+      GEOMETRY => self.position = (args.int()?, args.int()?),
+      MODE if args.uint()? & MODE_CURRENT != 0 => {
+        self.size = (args.int()? as usize, args.int()? as usize)
+      }
+      NAME => self.name = Some(args.string()?),
+      _ => {}
+    }
+
+    Ok(())
+  }
+}
+
+/// The buffer the compositor asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BufferInfo {
+  format: u32,
+  width: u32,
+  height: u32,
+  stride: u32,
+}
+
+impl BufferInfo {
+  /// The number of bytes of the buffer
+  fn len(&self) -> usize {
+    self.stride as usize * self.height as usize
+  }
+}
+
+/// A copy requested from the compositor that isn't ready yet
+#[derive(Debug)]
+struct Pending {
+  frame: u32,
+  info: Option<BufferInfo>,
+  unsupported: Option<u32>,
+  buffer_done: bool,
+  copied: bool,
+  flags: u32,
+}
+
+/// A Wayland output captured through the wlr-screencopy protocol
+#[derive(Debug)]
+pub struct WaylandDisplay {
+  client: Rc<RefCell<Client>>,
+  output: u32,
+  name: String,
+  position: (i32, i32),
+  width: usize,
+  height: usize,
+  primary: bool,
+  config: CaptureConfig,
+  pending: Option<Pending>,
+  buffer: Option<ShmBuffer>,
+  flags: u32,
+  sequence: u64,
+}
+
+impl WaylandDisplay {
+  /// The name of the output, e.g. `DP-1`
+  ///
+  /// # Notes
+  /// Compositors older than `wl_output` version 4 don't name outputs, those are named
+  /// after their registry global.
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// The position of the output in the compositor's global space
+  pub const fn position(&self) -> (i32, i32) {
+    self.position
+  }
+
+  /// The width of the current mode of the output
+  pub const fn width(&self) -> usize {
+    self.width
+  }
+
+  /// The height of the current mode of the output
+  pub const fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Whether the display is the primary display
+  pub const fn is_primary(&self) -> bool {
+    self.primary
+  }
+
+  /// Set the capture options used for subsequent frames
+  ///
+  /// # Notes
+  /// Only the timeout applies, `None` (the default) blocks until the compositor copied
+  /// the output.  A copy still in flight when the timeout passes carries over to the
+  /// next frame request.
+  pub fn set_config(&mut self, config: CaptureConfig) {
+    self.config = config;
+  }
+
+  /// Request a copy of the output and wait for it, `false` when the timeout passed first
+  ///
+  /// # Safety
+  /// Calls to libc when the buffer is (re-)created
+  unsafe fn capture(&mut self) -> Result<bool, WaylandFrameError> {
+    let deadline = self
+      .config
+      .timeout()
+      .map(|timeout| Instant::now() + timeout);
+    let mut client = self.client.borrow_mut();
+    let client = &mut *client;
+
+    // A copy left over from a request that timed out is still on its way
+    let mut pending = match self.pending.take() {
+      Some(pending) => pending,
+      None => {
+        let frame = client.conn.new_id();
+
+        client.conn.send(
+          Request::new(client.screencopy, CAPTURE_OUTPUT)
+            .uint(frame)
+            .int(0)
+            .uint(self.output),
+        )?;
+
+        Pending {
+          frame,
+          info: None,
+          unsupported: None,
+          buffer_done: false,
+          copied: false,
+          flags: 0,
+        }
+      }
+    };
+
+    loop {
+      let event = match client.conn.next_event(deadline)? {
+        Some(event) if event.object == pending.frame => event,
+        Some(_) => continue,
+        None => {
+          self.pending = Some(pending);
+          return Ok(false);
+        }
+      };
+
+      let mut args = event.args();
+
+      match event.opcode {
+        FRAME_BUFFER => {
+          let info = BufferInfo {
+            format: args.uint()?,
+            width: args.uint()?,
+            height: args.uint()?,
+            stride: args.uint()?,
+          };
+
+          match format(info.format) {
+            Some(_) if pending.info.is_none() => pending.info = Some(info),
+            Some(_) => {}
+            None => pending.unsupported = Some(info.format),
+          }
+        }
+        FRAME_FLAGS => pending.flags = args.uint()?,
+        FRAME_BUFFER_DONE => pending.buffer_done = true,
+        FRAME_READY | FRAME_FAILED => {
+          client
+            .conn
+            .send(Request::new(pending.frame, FRAME_DESTROY))?;
+          self.flags = pending.flags;
+
+          return match event.opcode {
+            FRAME_READY => Ok(true),
+            _ => Err(WaylandFrameError::Failed),
+          };
+        }
+        _ => {}
+      }
+
+      // Version 3 lists every buffer type before `buffer_done`, older versions only
+      // send a single `buffer` event
+      let described = pending.buffer_done
+        || client.screencopy_version < 3
+          && (pending.info.is_some() || pending.unsupported.is_some());
+
+      if pending.copied || !described {
+        continue;
+      }
+
+      let info = match pending.info {
+        Some(info) => info,
+        None => {
+          let format = pending.unsupported.unwrap_or_default();
+
+          client
+            .conn
+            .send(Request::new(pending.frame, FRAME_DESTROY))?;
+
+          return Err(WaylandFrameError::UnsupportedFormat(format));
+        }
+      };
+
+      if self.buffer.as_ref().map(|buffer| buffer.info) != Some(info) {
+        if let Some(buffer) = self.buffer.take() {
+          client.conn.send(Request::new(buffer.id, BUFFER_DESTROY))?;
+        }
+
+        self.buffer = Some(ShmBuffer::new(client, info)?);
+      }
+
+      if let Some(buffer) = &self.buffer {
+        client
+          .conn
+          .send(Request::new(pending.frame, COPY).uint(buffer.id))?;
+        pending.copied = true;
+      }
+    }
+  }
+}
+
+impl<'frame> Display<'frame> for WaylandDisplay {
+  type Frame = WaylandFrame<'frame>;
+
+  fn width(&self) -> Result<usize, DisplayError> {
+    Ok(self.width())
+  }
+
+  fn height(&self) -> Result<usize, DisplayError> {
+    Ok(self.height())
+  }
+
+  fn frame(&'frame mut self) -> Result<Self::Frame, FrameError> {
+    if !unsafe { self.capture()? } {
+      return Err(FrameError::WouldBlock);
+    }
+
+    let buffer = match &mut self.buffer {
+      Some(buffer) => buffer,
+      None => return Err(WaylandFrameError::Failed.into()),
+    };
+
+    if self.flags & FLAG_Y_INVERT != 0 {
+      buffer.flip();
+    }
+
+    buffer.fill_alpha();
+
+    let sequence = self.sequence;
+    self.sequence += 1;
+
+    let info = buffer.info;
+    let format =
+      format(info.format).ok_or(WaylandFrameError::UnsupportedFormat(info.format))?;
+
+    Ok(WaylandFrame::new(
+      buffer.bytes(),
+      info.width as usize,
+      info.height as usize,
+      format,
+      sequence,
+    ))
+  }
+}
+
+impl Drop for WaylandDisplay {
+  fn drop(&mut self) {
+    if let Ok(mut client) = self.client.try_borrow_mut() {
+      if let Some(pending) = self.pending.take() {
+        let _ = client.conn.send(Request::new(pending.frame, FRAME_DESTROY));
+      }
+
+      if let Some(buffer) = self.buffer.take() {
+        let _ = client.conn.send(Request::new(buffer.id, BUFFER_DESTROY));
+      }
+    }
+  }
+}
+
+/// A `wl_buffer` backed by memory shared with the compositor
+#[derive(Debug)]
+struct ShmBuffer {
+  id: u32,
+  info: BufferInfo,
+  map: *mut c_void,
+}
+
+impl ShmBuffer {
+  /// Create a buffer laid out as `info` asks
+  ///
+  /// # Safety
+  /// Calls to libc
+  unsafe fn new(
+    client: &mut Client,
+    info: BufferInfo,
+  ) -> Result<Self, WaylandFrameError> {
+    let fd = libc::memfd_create(b"scraptor\0".as_ptr() as _, libc::MFD_CLOEXEC);
+    if fd < 0 {
+      return Err(std::io::Error::last_os_error().into());
+    }
+
+    // Closed once the pool is sent, the compositor receives its own descriptor
+    let file = File::from_raw_fd(fd);
+    file.set_len(info.len() as u64)?;
+
+    let map = libc::mmap(
+      ptr::null_mut(),
+      info.len(),
+      libc::PROT_READ | libc::PROT_WRITE,
+      libc::MAP_SHARED,
+      file.as_raw_fd(),
+      0,
+    );
+
+    if map == libc::MAP_FAILED {
+      return Err(std::io::Error::last_os_error().into());
+    }
+
+    let conn = &mut client.conn;
+    let buffer = Self {
+      id: conn.new_id(),
+      info,
+      map,
+    };
+
+    let pool = conn.new_id();
+    conn.send(
+      Request::new(client.shm, CREATE_POOL)
+        .uint(pool)
+        .fd(file.as_raw_fd())
+        .int(info.len() as i32),
+    )?;
+
+    conn.send(
+      Request::new(pool, CREATE_BUFFER)
+        .uint(buffer.id)
+        .int(0)
+        .int(info.width as i32)
+        .int(info.height as i32)
+        .int(info.stride as i32)
+        .uint(info.format),
+    )?;
+
+    // The buffer keeps the memory of the pool alive
+    conn.send(Request::new(pool, POOL_DESTROY))?;
+
+    Ok(buffer)
+  }
+
+  /// The pixel data of the buffer
+  fn bytes(&self) -> &[u8] {
+    unsafe { slice::from_raw_parts(self.map as *const u8, self.info.len()) }
+  }
+
+  /// Make the padding byte of `XRGB8888` and `XBGR8888` pixels opaque
+  ///
+  /// # Notes
+  /// Compositors are free to leave the padding byte undefined, usually zero so, reading
+  /// it as alpha would make the frame transparent.
+  fn fill_alpha(&mut self) {
+    if !matches!(self.info.format, XRGB8888 | XBGR8888) {
+      return;
+    }
+
+    let data = unsafe { slice::from_raw_parts_mut(self.map as *mut u8, self.info.len()) };
+    data.chunks_exact_mut(4).for_each(|px| px[3] = 0xff);
+  }
+
+  /// Flip the rows of the buffer upside down
+  fn flip(&mut self) {
+    let stride = self.info.stride as usize;
+    let data = unsafe { slice::from_raw_parts_mut(self.map as *mut u8, self.info.len()) };
+    let (mut top, mut bottom) = (0, self.info.height as usize);
+
+    while top + 1 < bottom {
+      bottom -= 1;
+
+      let (head, tail) = data.split_at_mut(bottom * stride);
+      head[top * stride..(top + 1) * stride].swap_with_slice(&mut tail[..stride]);
+      top += 1;
+    }
+  }
+}
+
+impl Drop for ShmBuffer {
+  fn drop(&mut self) {
+    unsafe {
+      libc::munmap(self.map, self.info.len());
+    }
+  }
+}
+
+/// Get the [`FrameFormat`] matching the layout of a `wl_shm` format
+///
+/// # Notes
+/// `wl_shm` formats are little endian, e.g. `ARGB8888` is stored as `B, G, R, A`.
+fn format(format: u32) -> Option<FrameFormat> {
+  match format {
+    ARGB8888 | XRGB8888 => Some(FrameFormat::B8G8R8A8),
+    ABGR8888 | XBGR8888 => Some(FrameFormat::R8G8B8A8),
+    RGB888 => Some(FrameFormat::B8G8R8),
+    BGR888 => Some(FrameFormat::R8G8B8),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::format;
+  use crate::FrameFormat;
+
+  #[test]
+  fn test_format_from_shm() {
+    assert_eq!(format(0), Some(FrameFormat::B8G8R8A8));
+    assert_eq!(format(1), Some(FrameFormat::B8G8R8A8));
+    assert_eq!(format(0x3432_4258), Some(FrameFormat::R8G8B8A8));
+    assert_eq!(format(0x3432_4752), Some(FrameFormat::B8G8R8));
+    // RGB565
+    assert_eq!(format(0x3631_4752), None);
+  }
+}
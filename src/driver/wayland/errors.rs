@@ -0,0 +1,28 @@
+use std::{io, sync::Arc};
+
+/// An error that occurs when talking to the Wayland compositor
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum FrameError {
+  #[error("Wayland connection failed `{0}`")]
+  Io(Arc<io::Error>),
+  #[error("Received a malformed Wayland message")]
+  Malformed,
+  #[error("Wayland protocol error {code} on object {object} `{message}`")]
+  Protocol {
+    object: u32,
+    code: u32,
+    message: String,
+  },
+  #[error("The compositor doesn't support `{0}`")]
+  Unsupported(&'static str),
+  #[error("The compositor offered no supported buffer format, last was `{0:#x}`")]
+  UnsupportedFormat(u32),
+  #[error("The compositor failed to copy the output")]
+  Failed,
+}
+
+impl From<io::Error> for FrameError {
+  fn from(err: io::Error) -> Self {
+    Self::Io(Arc::new(err))
+  }
+}
@@ -0,0 +1,77 @@
+use crate::{
+  convert::Layout, errors::FrameError, DirtyRect, Frame, FrameFormat, MovedRect,
+};
+use std::borrow::Cow;
+
+/// A copy of a Wayland output
+#[derive(Debug, Clone)]
+pub struct WaylandFrame<'a> {
+  data: &'a [u8],
+  width: usize,
+  height: usize,
+  format: FrameFormat,
+  sequence: u64,
+}
+
+impl<'a> WaylandFrame<'a> {
+  pub fn new(
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    format: FrameFormat,
+    sequence: u64,
+  ) -> Self {
+    Self {
+      data,
+      width,
+      height,
+      format,
+      sequence,
+    }
+  }
+}
+
+impl<'frame> Frame<'frame> for WaylandFrame<'frame> {
+  fn width(&self) -> usize {
+    self.width
+  }
+
+  fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Damage is only reported when waiting for it so, the whole frame is always dirty
+  fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    Ok(vec![DirtyRect::new(
+      0,
+      self.width as i32,
+      self.height as i32,
+      0,
+    )])
+  }
+
+  fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+    Ok(Vec::new())
+  }
+
+  /// The layout of the `wl_shm` format picked by the compositor
+  ///
+  /// # Notes
+  /// The alpha byte of `XRGB8888` and `XBGR8888` buffers is padding so, it's always
+  /// opaque.
+  fn format(&self) -> FrameFormat {
+    self.format
+  }
+
+  fn as_bytes(&self) -> anyhow::Result<Cow<'frame, [u8]>> {
+    Ok(Cow::Borrowed(self.data))
+  }
+
+  fn stride(&self) -> usize {
+    Layout::infer(self.width, self.height, self.format, self.data.len()).stride
+  }
+
+  fn sequence(&self) -> u64 {
+    self.sequence
+  }
+}
@@ -0,0 +1,50 @@
+pub mod display;
+pub mod errors;
+pub mod frame;
+pub mod wire;
+
+use crate::{errors::DisplayError, DisplayDriver};
+use display::WaylandDisplay;
+
+/// The wlr-screencopy display driver for wlroots based compositors (Sway, Hyprland)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Wayland;
+
+impl<'buf> DisplayDriver<'buf> for Wayland {
+  type Display = WaylandDisplay;
+
+  fn name(&self) -> &'static str {
+    "wayland"
+  }
+
+  fn all(&self) -> Result<Vec<Self::Display>, DisplayError> {
+    display::displays().map_err(|err| DisplayError::Wayland(err.to_string()))
+  }
+
+  fn primary(&self) -> Result<Option<Self::Display>, DisplayError> {
+    Ok(self.all()?.into_iter().find(WaylandDisplay::is_primary))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Wayland;
+  use crate::{Display, DisplayDriver, Frame};
+
+  #[test]
+  fn test_wayland_frame() {
+    // Skip when no wlroots compositor is reachable, e.g. on headless CI
+    let mut display = match Wayland.primary() {
+      Ok(display) => display.unwrap(),
+      Err(_) => return,
+    };
+
+    let frame = Display::frame(&mut display).unwrap();
+    let bytes = frame.as_bytes().unwrap();
+
+    assert!(frame.width() > 0 && frame.height() > 0);
+    assert!(
+      bytes.len() >= frame.width() * frame.height() * frame.format().bytes_per_pixel()
+    );
+  }
+}
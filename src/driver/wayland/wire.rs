@@ -0,0 +1,366 @@
+//! Provides the Wayland wire protocol over the compositor socket
+//!
+//! # Notes
+//! Only what capture needs is implemented, no file descriptors are ever received.
+
+use super::errors::FrameError;
+use std::{
+  env,
+  io::{self, Read, Write},
+  mem,
+  os::{
+    raw::{c_int, c_void},
+    unix::{
+      io::{AsRawFd, RawFd},
+      net::UnixStream,
+    },
+  },
+  path::PathBuf,
+  ptr,
+  time::Instant,
+};
+
+/// The object id of `wl_display`
+pub const DISPLAY: u32 = 1;
+
+/// The length of a message header in bytes
+const HEADER_LEN: usize = 8;
+
+/// A message sent to the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+  object: u32,
+  opcode: u16,
+  args: Vec<u8>,
+  fd: Option<RawFd>,
+}
+
+impl Request {
+  /// Create [`Request`] calling `opcode` on `object`
+  pub fn new(object: u32, opcode: u16) -> Self {
+    Self {
+      object,
+      opcode,
+      args: Vec::new(),
+      fd: None,
+    }
+  }
+
+  /// Append an `uint`, `object` or `new_id` argument
+  pub fn uint(mut self, value: u32) -> Self {
+    self.args.extend_from_slice(&value.to_ne_bytes());
+    self
+  }
+
+  /// Append an `int` argument
+  pub fn int(mut self, value: i32) -> Self {
+    self.args.extend_from_slice(&value.to_ne_bytes());
+    self
+  }
+
+  /// Append a `string` argument, nul terminated and padded to 32 bits
+  pub fn string(mut self, value: &str) -> Self {
+    self = self.uint(value.len() as u32 + 1);
+    self.args.extend_from_slice(value.as_bytes());
+    self.args.push(0);
+
+    while !self.args.len().is_multiple_of(4) {
+      self.args.push(0);
+    }
+
+    self
+  }
+
+  /// Pass a `fd` argument, sent alongside the message rather than in it
+  pub fn fd(mut self, fd: RawFd) -> Self {
+    self.fd = Some(fd);
+    self
+  }
+
+  /// The bytes of the message
+  pub fn encode(&self) -> Vec<u8> {
+    let size = (HEADER_LEN + self.args.len()) as u32;
+    let mut buf = Vec::with_capacity(size as usize);
+
+    buf.extend_from_slice(&self.object.to_ne_bytes());
+    buf.extend_from_slice(&(size << 16 | self.opcode as u32).to_ne_bytes());
+    buf.extend_from_slice(&self.args);
+    buf
+  }
+}
+
+/// A message received from the compositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+  pub object: u32,
+  pub opcode: u16,
+  pub args: Vec<u8>,
+}
+
+impl Event {
+  /// Read the arguments of the event in order
+  pub fn args(&self) -> Args<'_> {
+    Args(&self.args)
+  }
+}
+
+/// Reads the arguments of an [`Event`]
+#[derive(Debug, Clone)]
+pub struct Args<'a>(&'a [u8]);
+
+impl<'a> Args<'a> {
+  /// Read an `uint`, `object` or `new_id` argument
+  pub fn uint(&mut self) -> Result<u32, FrameError> {
+    let word = self.0.get(..4).ok_or(FrameError::Malformed)?;
+    self.0 = &self.0[4..];
+
+    Ok(u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+  }
+
+  /// Read an `int` argument
+  pub fn int(&mut self) -> Result<i32, FrameError> {
+    self.uint().map(|value| value as i32)
+  }
+
+  /// Read a `string` argument
+  pub fn string(&mut self) -> Result<String, FrameError> {
+    let len = self.uint()? as usize;
+    let padded = (len + 3) & !3;
+    let bytes = self.0.get(..padded).ok_or(FrameError::Malformed)?;
+    self.0 = &self.0[padded..];
+
+    // A null string has no length and no terminator
+    let text = &bytes[..len.saturating_sub(1)];
+
+    Ok(String::from_utf8_lossy(text).into_owned())
+  }
+}
+
+/// A client connection to the compositor
+#[derive(Debug)]
+pub struct Connection {
+  socket: UnixStream,
+  incoming: Vec<u8>,
+  next_id: u32,
+}
+
+impl Connection {
+  /// Connect to the compositor named by `$WAYLAND_DISPLAY` (`wayland-0` when unset)
+  pub fn connect() -> Result<Self, FrameError> {
+    let name = env::var_os("WAYLAND_DISPLAY").unwrap_or_else(|| "wayland-0".into());
+    let mut path = PathBuf::from(&name);
+
+    if path.is_relative() {
+      let runtime = env::var_os("XDG_RUNTIME_DIR").ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set")
+      })?;
+
+      path = PathBuf::from(runtime).join(name);
+    }
+
+    Ok(Self {
+      socket: UnixStream::connect(path)?,
+      incoming: Vec::new(),
+      next_id: DISPLAY + 1,
+    })
+  }
+
+  /// Allocate the id of a new object
+  pub fn new_id(&mut self) -> u32 {
+    let id = self.next_id;
+    self.next_id += 1;
+    id
+  }
+
+  /// Send `request` to the compositor
+  pub fn send(&mut self, request: Request) -> Result<(), FrameError> {
+    let buf = request.encode();
+
+    match request.fd {
+      None => self.socket.write_all(&buf)?,
+      Some(fd) => unsafe { self.send_with_fd(&buf, fd)? },
+    }
+
+    Ok(())
+  }
+
+  /// Send `buf` with `fd` attached as `SCM_RIGHTS` ancillary data
+  ///
+  /// # Safety
+  /// Calls to libc
+  unsafe fn send_with_fd(&self, buf: &[u8], fd: RawFd) -> io::Result<()> {
+    let fd_len = mem::size_of::<RawFd>() as u32;
+    let space = libc::CMSG_SPACE(fd_len) as usize;
+
+    // Aligned for `cmsghdr`
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut iov = libc::iovec {
+      iov_base: buf.as_ptr() as *mut c_void,
+      iov_len: buf.len(),
+    };
+
+    let mut msg: libc::msghdr = mem::zeroed();
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = space as _;
+
+    let cmsg = libc::CMSG_FIRSTHDR(&msg);
+    (*cmsg).cmsg_level = libc::SOL_SOCKET;
+    (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+    (*cmsg).cmsg_len = libc::CMSG_LEN(fd_len) as _;
+    ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+
+    match libc::sendmsg(self.socket.as_raw_fd(), &msg, 0) {
+      -1 => Err(io::Error::last_os_error()),
+      sent if sent as usize == buf.len() => Ok(()),
+      _ => Err(io::ErrorKind::WriteZero.into()),
+    }
+  }
+
+  /// Get the next event, `None` when `deadline` passes first
+  ///
+  /// # Notes
+  /// Blocks indefinitely when `deadline` is `None`.  A `wl_display.error` event is
+  /// returned as [`FrameError::Protocol`].
+  pub fn next_event(
+    &mut self,
+    deadline: Option<Instant>,
+  ) -> Result<Option<Event>, FrameError> {
+    loop {
+      if let Some(event) = self.parse()? {
+        if event.object == DISPLAY && event.opcode == 0 {
+          let mut args = event.args();
+
+          return Err(FrameError::Protocol {
+            object: args.uint()?,
+            code: args.uint()?,
+            message: args.string()?,
+          });
+        }
+
+        return Ok(Some(event));
+      }
+
+      if !self.wait(deadline)? {
+        return Ok(None);
+      }
+
+      let mut chunk = [0; 4096];
+      match self.socket.read(&mut chunk)? {
+        0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        len => self.incoming.extend_from_slice(&chunk[..len]),
+      }
+    }
+  }
+
+  /// Handle every event until the compositor processed the requests sent so far
+  pub fn roundtrip<F>(&mut self, mut on_event: F) -> Result<(), FrameError>
+  where
+    F: FnMut(&Event) -> Result<(), FrameError>,
+  {
+    let callback = self.new_id();
+    self.send(Request::new(DISPLAY, 0).uint(callback))?;
+
+    loop {
+      match self.next_event(None)? {
+        Some(event) if event.object == callback => return Ok(()),
+        Some(event) => on_event(&event)?,
+        None => {}
+      }
+    }
+  }
+
+  /// Take the next complete event from the incoming buffer
+  fn parse(&mut self) -> Result<Option<Event>, FrameError> {
+    if self.incoming.len() < HEADER_LEN {
+      return Ok(None);
+    }
+
+    let mut header = Args(&self.incoming[..HEADER_LEN]);
+    let object = header.uint()?;
+    let word = header.uint()?;
+    let size = (word >> 16) as usize;
+
+    if size < HEADER_LEN {
+      return Err(FrameError::Malformed);
+    }
+
+    if self.incoming.len() < size {
+      return Ok(None);
+    }
+
+    let args = self.incoming[HEADER_LEN..size].to_vec();
+    self.incoming.drain(..size);
+
+    Ok(Some(Event {
+      object,
+      opcode: word as u16,
+      args,
+    }))
+  }
+
+  /// Wait for the socket to be readable, `false` when `deadline` passes first
+  fn wait(&self, deadline: Option<Instant>) -> io::Result<bool> {
+    loop {
+      let timeout = match deadline {
+        None => -1,
+        Some(deadline) => {
+          let left = deadline.saturating_duration_since(Instant::now());
+          left.as_millis().min(c_int::MAX as u128) as c_int
+        }
+      };
+
+      let mut fds = libc::pollfd {
+        fd: self.socket.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+      };
+
+      match unsafe { libc::poll(&mut fds, 1, timeout) } {
+        -1 => match io::Error::last_os_error() {
+          err if err.kind() == io::ErrorKind::Interrupted => continue,
+          err => return Err(err),
+        },
+        0 => return Ok(false),
+        _ => return Ok(true),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Event, Request};
+
+  #[test]
+  fn test_request_encoding() {
+    let buf = Request::new(2, 0).uint(7).string("wl_shm").uint(1).encode();
+    let word = |idx: usize| {
+      u32::from_ne_bytes([buf[idx], buf[idx + 1], buf[idx + 2], buf[idx + 3]])
+    };
+
+    // Header, name, string length, "wl_shm\0" padded to 8 bytes, version
+    assert_eq!(buf.len(), 8 + 4 + 4 + 8 + 4);
+    assert_eq!(word(0), 2);
+    assert_eq!(word(4), (buf.len() as u32) << 16);
+    assert_eq!(word(12), 7);
+    assert_eq!(&buf[16..24], b"wl_shm\0\0");
+  }
+
+  #[test]
+  fn test_event_args() {
+    let request = Request::new(3, 1).int(-5).string("DP-1").uint(9);
+    let event = Event {
+      object: 3,
+      opcode: 1,
+      args: request.encode()[8..].to_vec(),
+    };
+
+    let mut args = event.args();
+    assert_eq!(args.int().unwrap(), -5);
+    assert_eq!(args.string().unwrap(), "DP-1");
+    assert_eq!(args.uint().unwrap(), 9);
+    assert!(args.uint().is_err());
+  }
+}
@@ -0,0 +1,555 @@
+//! Provides interface to get display information for X11 root window capture.
+
+use super::{
+  errors::FrameError as X11FrameError,
+  ffi::{
+    Window, XCloseDisplay, XDefaultDepth, XDefaultRootWindow, XDefaultScreen,
+    XDefaultVisual, XDisplay, XDisplayHeight, XDisplayWidth, XErrorEvent, XFree,
+    XGetAtomName, XGetImage, XImage, XOpenDisplay, XRRFreeMonitors, XRRGetMonitors,
+    XRRQueryExtension, XSetErrorHandler, XShmAttach, XShmCreateImage, XShmDetach,
+    XShmGetImage, XShmQueryExtension, XShmSegmentInfo, XSync, ALL_PLANES, FALSE,
+    LSB_FIRST, TRUE, Z_PIXMAP,
+  },
+  frame::X11Frame,
+};
+use crate::{
+  errors::{DisplayError, FrameError},
+  Display, FrameFormat,
+};
+use std::{
+  ffi::CStr,
+  io,
+  os::raw::{c_char, c_int, c_ulong, c_void},
+  ptr,
+  rc::Rc,
+  slice,
+  sync::{
+    atomic::{AtomicU8, Ordering},
+    Once,
+  },
+};
+
+/// The code of the last X error reported to [`on_error`], zero when none
+static LAST_ERROR: AtomicU8 = AtomicU8::new(0);
+
+/// Record X errors rather than letting the default handler exit the process
+///
+/// # Notes
+/// Xlib error handlers are process wide so, errors of every connection end up here.
+unsafe extern "C" fn on_error(_: *mut XDisplay, event: *mut XErrorEvent) -> c_int {
+  LAST_ERROR.store((*event).error_code, Ordering::SeqCst);
+  0
+}
+
+/// A connection to the X server shared by the displays enumerated from it
+#[derive(Debug)]
+pub(super) struct Connection {
+  display: *mut XDisplay,
+  root: Window,
+  shm: bool,
+}
+
+impl Connection {
+  /// Connect to the X server named by `$DISPLAY`
+  pub fn open() -> Result<Rc<Self>, DisplayError> {
+    static HANDLER: Once = Once::new();
+
+    unsafe {
+      HANDLER.call_once(|| {
+        XSetErrorHandler(Some(on_error));
+      });
+
+      let display = XOpenDisplay(ptr::null());
+      if display.is_null() {
+        return Err(DisplayError::Connect);
+      }
+
+      Ok(Rc::new(Self {
+        display,
+        root: XDefaultRootWindow(display),
+        shm: XShmQueryExtension(display) != FALSE,
+      }))
+    }
+  }
+
+  /// Wait for every request to be processed, returning the code of an X error raised by
+  /// any of them
+  ///
+  /// # Safety
+  /// Calls to Xlib
+  unsafe fn sync(&self) -> Result<(), u8> {
+    XSync(self.display, FALSE);
+
+    match LAST_ERROR.swap(0, Ordering::SeqCst) {
+      0 => Ok(()),
+      code => Err(code),
+    }
+  }
+
+  /// Get the displays of the root window
+  ///
+  /// # Notes
+  /// Displays are the RandR 1.5 monitors of the screen, without RandR the whole screen
+  /// is a single primary display.
+  ///
+  /// # Safety
+  /// Calls to Xlib
+  pub unsafe fn displays(self: &Rc<Self>) -> Vec<X11Display> {
+    let (mut event_base, mut error_base, mut count) = (0, 0, 0);
+    let monitors = match XRRQueryExtension(self.display, &mut event_base, &mut error_base)
+    {
+      FALSE => ptr::null_mut(),
+      _ => XRRGetMonitors(self.display, self.root, TRUE, &mut count),
+    };
+
+    if monitors.is_null() || count <= 0 {
+      if !monitors.is_null() {
+        XRRFreeMonitors(monitors);
+      }
+
+      let screen = XDefaultScreen(self.display);
+
+      return vec![X11Display::new(
+        self.clone(),
+        format!("screen{}", screen),
+        (0, 0),
+        XDisplayWidth(self.display, screen) as usize,
+        XDisplayHeight(self.display, screen) as usize,
+        true,
+      )];
+    }
+
+    let displays = slice::from_raw_parts(monitors, count as usize)
+      .iter()
+      .map(|monitor| {
+        X11Display::new(
+          self.clone(),
+          self.atom_name(monitor.name),
+          (monitor.x, monitor.y),
+          monitor.width as usize,
+          monitor.height as usize,
+          monitor.primary != FALSE,
+        )
+      })
+      .collect();
+
+    XRRFreeMonitors(monitors);
+    displays
+  }
+
+  /// Get the name of `atom`, empty when it has none
+  ///
+  /// # Safety
+  /// Calls to Xlib
+  unsafe fn atom_name(&self, atom: c_ulong) -> String {
+    let name = XGetAtomName(self.display, atom);
+    if name.is_null() {
+      return String::new();
+    }
+
+    let value = CStr::from_ptr(name).to_string_lossy().into_owned();
+    XFree(name as *mut c_void);
+    value
+  }
+}
+
+impl Drop for Connection {
+  fn drop(&mut self) {
+    unsafe {
+      XCloseDisplay(self.display);
+    }
+  }
+}
+
+/// An X11 display, the area of the root window covered by a monitor
+#[derive(Debug)]
+pub struct X11Display {
+  conn: Rc<Connection>,
+  name: String,
+  position: (i32, i32),
+  width: usize,
+  height: usize,
+  primary: bool,
+  shm: bool,
+  image: Option<Image>,
+  sequence: u64,
+}
+
+impl X11Display {
+  fn new(
+    conn: Rc<Connection>,
+    name: String,
+    position: (i32, i32),
+    width: usize,
+    height: usize,
+    primary: bool,
+  ) -> Self {
+    Self {
+      shm: conn.shm,
+      conn,
+      name,
+      position,
+      width,
+      height,
+      primary,
+      image: None,
+      sequence: 0,
+    }
+  }
+
+  /// The RandR name of the display's monitor, e.g. `DP-1`
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// The position of the display on the root window
+  pub const fn position(&self) -> (i32, i32) {
+    self.position
+  }
+
+  /// The width of the display
+  pub const fn width(&self) -> usize {
+    self.width
+  }
+
+  /// The height of the display
+  pub const fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Whether the display is the primary display
+  pub const fn is_primary(&self) -> bool {
+    self.primary
+  }
+
+  /// Read the display's area of the root window into `image`
+  ///
+  /// # Notes
+  /// Reads go through a shared memory image when the server supports XShm, falling back
+  /// on `XGetImage` when the shared memory image can't be created (e.g. for clients
+  /// forwarded over SSH).
+  ///
+  /// # Safety
+  /// Calls to Xlib
+  unsafe fn capture(&mut self) -> Result<(), X11FrameError> {
+    if self.shm && self.image.is_none() {
+      match Image::shm(&self.conn, self.width, self.height) {
+        Ok(image) => self.image = Some(image),
+        Err(_) => self.shm = false,
+      }
+    }
+
+    let conn = &self.conn;
+    let (x, y) = self.position;
+
+    match &self.image {
+      Some(image) if self.shm => {
+        XShmGetImage(conn.display, conn.root, image.ximage, x, y, ALL_PLANES);
+        conn.sync().map_err(X11FrameError::GetImage)?;
+      }
+      _ => {
+        let ximage = XGetImage(
+          conn.display,
+          conn.root,
+          x,
+          y,
+          self.width as _,
+          self.height as _,
+          ALL_PLANES,
+          Z_PIXMAP,
+        );
+
+        let sync = conn.sync();
+        if ximage.is_null() {
+          return Err(X11FrameError::GetImage(sync.err().unwrap_or_default()));
+        }
+
+        self.image = Some(Image::new(conn.clone(), ximage));
+      }
+    }
+
+    if let Some(image) = &mut self.image {
+      image.fill_alpha();
+    }
+
+    Ok(())
+  }
+}
+
+impl<'frame> Display<'frame> for X11Display {
+  type Frame = X11Frame<'frame>;
+
+  fn width(&self) -> Result<usize, DisplayError> {
+    Ok(self.width())
+  }
+
+  fn height(&self) -> Result<usize, DisplayError> {
+    Ok(self.height())
+  }
+
+  fn frame(&'frame mut self) -> Result<Self::Frame, FrameError> {
+    unsafe { self.capture()? };
+
+    let image = match &self.image {
+      Some(image) => image,
+      None => return Err(X11FrameError::GetImage(0).into()),
+    };
+
+    let sequence = self.sequence;
+    self.sequence += 1;
+
+    Ok(X11Frame::new(
+      image.bytes(),
+      self.width,
+      self.height,
+      image.format()?,
+      sequence,
+    ))
+  }
+}
+
+/// An `XImage` owned by a display
+#[derive(Debug)]
+struct Image {
+  conn: Rc<Connection>,
+  ximage: *mut XImage,
+  shm: Option<Box<XShmSegmentInfo>>,
+  attached: bool,
+}
+
+impl Image {
+  fn new(conn: Rc<Connection>, ximage: *mut XImage) -> Self {
+    Self {
+      conn,
+      ximage,
+      shm: None,
+      attached: false,
+    }
+  }
+
+  /// Create an image backed by a shared memory segment the X server writes into
+  ///
+  /// # Safety
+  /// Calls to Xlib and System V shared memory
+  unsafe fn shm(
+    conn: &Rc<Connection>,
+    width: usize,
+    height: usize,
+  ) -> Result<Self, X11FrameError> {
+    let screen = XDefaultScreen(conn.display);
+
+    // Xlib keeps a pointer to the segment info in the image so, its address must not move
+    let mut info = Box::new(XShmSegmentInfo {
+      shmseg: 0,
+      shmid: -1,
+      shmaddr: ptr::null_mut(),
+      read_only: FALSE,
+    });
+
+    let ximage = XShmCreateImage(
+      conn.display,
+      XDefaultVisual(conn.display, screen),
+      XDefaultDepth(conn.display, screen) as _,
+      Z_PIXMAP,
+      ptr::null_mut(),
+      &mut *info,
+      width as _,
+      height as _,
+    );
+
+    if ximage.is_null() {
+      return Err(X11FrameError::Shm(0));
+    }
+
+    let len = (*ximage).bytes_per_line as usize * (*ximage).height as usize;
+    let mut image = Self {
+      conn: conn.clone(),
+      ximage,
+      shm: Some(info),
+      attached: false,
+    };
+
+    let info = match &mut image.shm {
+      Some(info) => info,
+      None => unreachable!(),
+    };
+
+    info.shmid = libc::shmget(libc::IPC_PRIVATE, len, libc::IPC_CREAT | 0o600);
+    if info.shmid < 0 {
+      return Err(last_errno());
+    }
+
+    let addr = libc::shmat(info.shmid, ptr::null(), 0);
+    let result = match addr as isize {
+      -1 => Err(last_errno()),
+      _ => {
+        info.shmaddr = addr as *mut c_char;
+        (*ximage).data = info.shmaddr;
+
+        XShmAttach(conn.display, &mut **info);
+        conn.sync().map_err(X11FrameError::GetImage)
+      }
+    };
+
+    // The segment is freed once both this process and the X server detach from it, even
+    // when either exits without detaching
+    libc::shmctl(info.shmid, libc::IPC_RMID, ptr::null_mut());
+
+    result?;
+    image.attached = true;
+
+    Ok(image)
+  }
+
+  /// The pixel data of the image
+  fn bytes(&self) -> &[u8] {
+    unsafe {
+      let image = &*self.ximage;
+      let len = image.bytes_per_line as usize * image.height as usize;
+
+      slice::from_raw_parts(image.data as *const u8, len)
+    }
+  }
+
+  /// Make the padding byte of a 24 bit depth visual stored in 32 bits opaque
+  ///
+  /// # Notes
+  /// The X server leaves the fourth byte undefined, usually zero so, reading it as alpha
+  /// would make the frame transparent.
+  fn fill_alpha(&mut self) {
+    unsafe {
+      let image = &*self.ximage;
+      if image.depth >= 32 || image.bits_per_pixel != 32 {
+        return;
+      }
+
+      let len = image.bytes_per_line as usize * image.height as usize;
+      fill_alpha(slice::from_raw_parts_mut(image.data as *mut u8, len));
+    }
+  }
+
+  /// The pixel format of the image
+  fn format(&self) -> Result<FrameFormat, X11FrameError> {
+    format(unsafe { &*self.ximage })
+  }
+}
+
+impl Drop for Image {
+  fn drop(&mut self) {
+    unsafe {
+      if let Some(info) = &mut self.shm {
+        if self.attached {
+          XShmDetach(self.conn.display, &mut **info);
+          XSync(self.conn.display, FALSE);
+        }
+
+        if !info.shmaddr.is_null() {
+          libc::shmdt(info.shmaddr as *const c_void);
+        }
+
+        // Keep Xlib from freeing the shared memory
+        (*self.ximage).data = ptr::null_mut();
+      }
+
+      if let Some(destroy) = (*self.ximage).f.destroy_image {
+        destroy(self.ximage);
+      }
+    }
+  }
+}
+
+/// Get the errno of the last failed call
+fn last_errno() -> X11FrameError {
+  X11FrameError::Shm(
+    io::Error::last_os_error()
+      .raw_os_error()
+      .unwrap_or_default(),
+  )
+}
+
+/// Set the fourth byte of every 32 bit pixel in `bytes` to `0xff`
+///
+/// # Notes
+/// Both 32 bit [`FrameFormat`]s store alpha in the last byte, whatever the byte order of
+/// the visual.
+fn fill_alpha(bytes: &mut [u8]) {
+  bytes.chunks_exact_mut(4).for_each(|px| px[3] = 0xff);
+}
+
+/// Get the [`FrameFormat`] matching the pixel layout of `image`
+///
+/// # Notes
+/// Only 24 and 32 bit visuals with 8 bit channels are supported.
+fn format(image: &XImage) -> Result<FrameFormat, X11FrameError> {
+  let bytes = image.bits_per_pixel / 8;
+
+  // Get the index of the byte a channel is stored in
+  let index = |mask: c_ulong| {
+    let byte = mask.trailing_zeros() as i32 / 8;
+
+    match image.byte_order {
+      LSB_FIRST => byte,
+      _ => bytes - 1 - byte,
+    }
+  };
+
+  match (bytes, index(image.red_mask), index(image.blue_mask)) {
+    (4, 2, 0) => Ok(FrameFormat::B8G8R8A8),
+    (4, 0, 2) => Ok(FrameFormat::R8G8B8A8),
+    (3, 2, 0) => Ok(FrameFormat::B8G8R8),
+    (3, 0, 2) => Ok(FrameFormat::R8G8B8),
+    _ => Err(X11FrameError::UnsupportedVisual {
+      bits_per_pixel: image.bits_per_pixel,
+      byte_order: image.byte_order,
+    }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{fill_alpha, format};
+  use crate::{
+    driver::x11::{errors::FrameError, ffi::XImage},
+    FrameFormat,
+  };
+  use std::mem;
+
+  fn image(bits_per_pixel: i32, byte_order: i32, red: u64, blue: u64) -> XImage {
+    XImage {
+      bits_per_pixel,
+      byte_order,
+      red_mask: red as _,
+      green_mask: 0xff00,
+      blue_mask: blue as _,
+      ..unsafe { mem::zeroed() }
+    }
+  }
+
+  #[test]
+  fn test_format_from_visual() {
+    let bgra = image(32, 0, 0xff_0000, 0xff);
+    let rgba = image(32, 0, 0xff, 0xff_0000);
+    let msb_bgra = image(32, 1, 0xff00, 0xff00_0000);
+    let rgb = image(24, 0, 0xff, 0xff_0000);
+
+    assert_eq!(format(&bgra), Ok(FrameFormat::B8G8R8A8));
+    assert_eq!(format(&rgba), Ok(FrameFormat::R8G8B8A8));
+    assert_eq!(format(&msb_bgra), Ok(FrameFormat::B8G8R8A8));
+    assert_eq!(format(&rgb), Ok(FrameFormat::R8G8B8));
+    assert_eq!(
+      format(&image(16, 0, 0xf800, 0x1f)),
+      Err(FrameError::UnsupportedVisual {
+        bits_per_pixel: 16,
+        byte_order: 0
+      })
+    );
+  }
+
+  #[test]
+  fn test_fill_alpha() {
+    let mut buf = [1, 2, 3, 0, 4, 5, 6, 0];
+    fill_alpha(&mut buf);
+
+    assert_eq!(buf, [1, 2, 3, 255, 4, 5, 6, 255]);
+  }
+}
@@ -0,0 +1,15 @@
+/// An error that occurs when capturing the root window
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+  #[error("Failed to read the root window, X error `{0}`")]
+  GetImage(u8),
+  #[error("Failed to create the shared memory image, errno `{0}`")]
+  Shm(i32),
+  #[error(
+    "Unsupported visual with {bits_per_pixel} bits per pixel and byte order {byte_order}"
+  )]
+  UnsupportedVisual {
+    bits_per_pixel: i32,
+    byte_order: i32,
+  },
+}
@@ -0,0 +1,160 @@
+//! Provides the subset of Xlib, XShm and RandR used for capture
+
+#![allow(non_snake_case)]
+
+use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
+
+pub type Atom = c_ulong;
+pub type Bool = c_int;
+pub type Drawable = c_ulong;
+pub type Status = c_int;
+pub type Window = c_ulong;
+
+/// An Xlib connection
+pub enum XDisplay {}
+
+/// An X visual
+pub enum Visual {}
+
+pub const FALSE: Bool = 0;
+pub const TRUE: Bool = 1;
+pub const Z_PIXMAP: c_int = 2;
+pub const LSB_FIRST: c_int = 0;
+pub const ALL_PLANES: c_ulong = !0;
+
+#[repr(C)]
+pub struct XImage {
+  pub width: c_int,
+  pub height: c_int,
+  pub xoffset: c_int,
+  pub format: c_int,
+  pub data: *mut c_char,
+  pub byte_order: c_int,
+  pub bitmap_unit: c_int,
+  pub bitmap_bit_order: c_int,
+  pub bitmap_pad: c_int,
+  pub depth: c_int,
+  pub bytes_per_line: c_int,
+  pub bits_per_pixel: c_int,
+  pub red_mask: c_ulong,
+  pub green_mask: c_ulong,
+  pub blue_mask: c_ulong,
+  pub obdata: *mut c_char,
+  pub f: XImageFns,
+}
+
+/// The function table of [`XImage`], `XDestroyImage` is a macro calling `destroy_image`
+#[repr(C)]
+pub struct XImageFns {
+  pub create_image: *mut c_void,
+  pub destroy_image: Option<unsafe extern "C" fn(*mut XImage) -> c_int>,
+  pub get_pixel: *mut c_void,
+  pub put_pixel: *mut c_void,
+  pub sub_image: *mut c_void,
+  pub add_pixel: *mut c_void,
+}
+
+#[repr(C)]
+pub struct XErrorEvent {
+  pub kind: c_int,
+  pub display: *mut XDisplay,
+  pub resourceid: c_ulong,
+  pub serial: c_ulong,
+  pub error_code: u8,
+  pub request_code: u8,
+  pub minor_code: u8,
+}
+
+pub type XErrorHandler =
+  Option<unsafe extern "C" fn(*mut XDisplay, *mut XErrorEvent) -> c_int>;
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct XShmSegmentInfo {
+  pub shmseg: c_ulong,
+  pub shmid: c_int,
+  pub shmaddr: *mut c_char,
+  pub read_only: Bool,
+}
+
+#[repr(C)]
+pub struct XRRMonitorInfo {
+  pub name: Atom,
+  pub primary: Bool,
+  pub automatic: Bool,
+  pub noutput: c_int,
+  pub x: c_int,
+  pub y: c_int,
+  pub width: c_int,
+  pub height: c_int,
+  pub mwidth: c_int,
+  pub mheight: c_int,
+  pub outputs: *mut c_ulong,
+}
+
+#[link(name = "X11")]
+extern "C" {
+  pub fn XOpenDisplay(name: *const c_char) -> *mut XDisplay;
+  pub fn XCloseDisplay(display: *mut XDisplay) -> c_int;
+  pub fn XDefaultRootWindow(display: *mut XDisplay) -> Window;
+  pub fn XDefaultScreen(display: *mut XDisplay) -> c_int;
+  pub fn XDefaultVisual(display: *mut XDisplay, screen: c_int) -> *mut Visual;
+  pub fn XDefaultDepth(display: *mut XDisplay, screen: c_int) -> c_int;
+  pub fn XDisplayWidth(display: *mut XDisplay, screen: c_int) -> c_int;
+  pub fn XDisplayHeight(display: *mut XDisplay, screen: c_int) -> c_int;
+  pub fn XGetImage(
+    display: *mut XDisplay,
+    drawable: Drawable,
+    x: c_int,
+    y: c_int,
+    width: c_uint,
+    height: c_uint,
+    plane_mask: c_ulong,
+    format: c_int,
+  ) -> *mut XImage;
+  pub fn XSync(display: *mut XDisplay, discard: Bool) -> c_int;
+  pub fn XSetErrorHandler(handler: XErrorHandler) -> XErrorHandler;
+  pub fn XGetAtomName(display: *mut XDisplay, atom: Atom) -> *mut c_char;
+  pub fn XFree(data: *mut c_void) -> c_int;
+}
+
+#[link(name = "Xext")]
+extern "C" {
+  pub fn XShmQueryExtension(display: *mut XDisplay) -> Bool;
+  pub fn XShmCreateImage(
+    display: *mut XDisplay,
+    visual: *mut Visual,
+    depth: c_uint,
+    format: c_int,
+    data: *mut c_char,
+    shminfo: *mut XShmSegmentInfo,
+    width: c_uint,
+    height: c_uint,
+  ) -> *mut XImage;
+  pub fn XShmAttach(display: *mut XDisplay, shminfo: *mut XShmSegmentInfo) -> Bool;
+  pub fn XShmDetach(display: *mut XDisplay, shminfo: *mut XShmSegmentInfo) -> Bool;
+  pub fn XShmGetImage(
+    display: *mut XDisplay,
+    drawable: Drawable,
+    image: *mut XImage,
+    x: c_int,
+    y: c_int,
+    plane_mask: c_ulong,
+  ) -> Bool;
+}
+
+#[link(name = "Xrandr")]
+extern "C" {
+  pub fn XRRQueryExtension(
+    display: *mut XDisplay,
+    event_base: *mut c_int,
+    error_base: *mut c_int,
+  ) -> Bool;
+  pub fn XRRGetMonitors(
+    display: *mut XDisplay,
+    window: Window,
+    get_active: Bool,
+    monitors: *mut c_int,
+  ) -> *mut XRRMonitorInfo;
+  pub fn XRRFreeMonitors(monitors: *mut XRRMonitorInfo);
+}
@@ -0,0 +1,113 @@
+use crate::{
+  convert::Layout, errors::FrameError, DirtyRect, Frame, FrameFormat, MovedRect,
+};
+use std::borrow::Cow;
+
+/// A capture of the root window area covered by an X11 display
+#[derive(Debug, Clone)]
+pub struct X11Frame<'a> {
+  data: &'a [u8],
+  width: usize,
+  height: usize,
+  format: FrameFormat,
+  sequence: u64,
+}
+
+impl<'a> X11Frame<'a> {
+  pub fn new(
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    format: FrameFormat,
+    sequence: u64,
+  ) -> Self {
+    Self {
+      data,
+      width,
+      height,
+      format,
+      sequence,
+    }
+  }
+}
+
+impl<'frame> Frame<'frame> for X11Frame<'frame> {
+  fn width(&self) -> usize {
+    self.width
+  }
+
+  fn height(&self) -> usize {
+    self.height
+  }
+
+  /// X11 doesn't report damage to image reads so, the whole frame is always dirty
+  fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    Ok(vec![DirtyRect::new(
+      0,
+      self.width as i32,
+      self.height as i32,
+      0,
+    )])
+  }
+
+  fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+    Ok(Vec::new())
+  }
+
+  /// The byte order of the X visual
+  ///
+  /// # Notes
+  /// The alpha byte of a 24 bit depth visual is padding so, it's always opaque.
+  fn format(&self) -> FrameFormat {
+    self.format
+  }
+
+  fn as_bytes(&self) -> anyhow::Result<Cow<'frame, [u8]>> {
+    Ok(Cow::Borrowed(self.data))
+  }
+
+  fn stride(&self) -> usize {
+    Layout::infer(self.width, self.height, self.format, self.data.len()).stride
+  }
+
+  fn sequence(&self) -> u64 {
+    self.sequence
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::X11Frame;
+  use crate::{DirtyRect, Frame, FrameFormat};
+
+  #[test]
+  fn test_stride_includes_padding() {
+    // 2x2 `B8G8R8A8` pixels with 8 bytes of row padding
+    let buf = [0; 32];
+    let frame = X11Frame::new(&buf, 2, 2, FrameFormat::B8G8R8A8, 0);
+
+    assert_eq!(frame.stride(), 16);
+  }
+
+  #[test]
+  fn test_dirty_subframes_honor_stride() {
+    let buf = [1, 2, 3, 9, 4, 5, 6, 9];
+    let frame = X11Frame::new(&buf, 1, 2, FrameFormat::B8G8R8, 0);
+
+    let subframes = frame.dirty_subframes().unwrap();
+    assert_eq!(subframes.len(), 1);
+    assert_eq!(subframes[0].0, DirtyRect::new(0, 1, 2, 0));
+    assert_eq!(subframes[0].1, vec![1, 2, 3, 4, 5, 6]);
+  }
+
+  #[test]
+  fn test_as_packed_bytes_strips_padding() {
+    let buf = [1, 2, 3, 9, 4, 5, 6, 9];
+    let frame = X11Frame::new(&buf, 1, 2, FrameFormat::B8G8R8, 0);
+
+    assert_eq!(frame.as_packed_bytes().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+
+    let frame = X11Frame::new(&buf, 1, 2, FrameFormat::R8G8B8A8, 0);
+    assert_eq!(frame.as_packed_bytes().unwrap(), buf.to_vec());
+  }
+}
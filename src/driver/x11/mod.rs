@@ -0,0 +1,56 @@
+pub mod display;
+pub mod errors;
+pub mod ffi;
+pub mod frame;
+
+use crate::{errors::DisplayError, DisplayDriver};
+use display::{Connection, X11Display};
+
+/// The X11 root window display driver
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct X11;
+
+impl<'buf> DisplayDriver<'buf> for X11 {
+  type Display = X11Display;
+
+  fn name(&self) -> &'static str {
+    "x11"
+  }
+
+  fn all(&self) -> Result<Vec<Self::Display>, DisplayError> {
+    Ok(unsafe { Connection::open()?.displays() })
+  }
+
+  /// Gets the primary display, the first display when RandR marks none as primary
+  fn primary(&self) -> Result<Option<Self::Display>, DisplayError> {
+    let mut displays = self.all()?;
+
+    match displays.iter().position(X11Display::is_primary) {
+      Some(idx) => Ok(Some(displays.swap_remove(idx))),
+      None => Ok(displays.into_iter().next()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::X11;
+  use crate::{Display, DisplayDriver, Frame};
+
+  #[test]
+  fn test_x11_frame() {
+    // Skip when no X server is reachable, e.g. on headless CI
+    let mut display = match X11.primary() {
+      Ok(display) => display.unwrap(),
+      Err(_) => return,
+    };
+
+    let (width, height) = (display.width(), display.height());
+    let frame = Display::frame(&mut display).unwrap();
+    let bytes = frame.as_bytes().unwrap();
+
+    assert_eq!(frame.width(), width);
+    assert_eq!(frame.height(), height);
+    assert!(bytes.len() >= width * height * frame.format().bytes_per_pixel());
+  }
+}
@@ -0,0 +1,161 @@
+//! Provides parsing of the Extended Display Identification Data reported by monitors
+
+/// The length of an EDID base block
+pub const BLOCK_LEN: usize = 128;
+
+/// The fixed header every EDID base block starts with
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+/// The offsets of the four 18 byte descriptors of a base block
+const DESCRIPTORS: [usize; 4] = [54, 72, 90, 108];
+
+/// Identifies the physical monitor behind a display
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edid {
+  /// The three letter PNP id of the manufacturer, e.g. `DEL`
+  pub manufacturer: String,
+  /// The product code assigned by the manufacturer
+  pub product_code: u16,
+  /// The model name from the display name descriptor, if present
+  pub model: Option<String>,
+  /// The serial number from the serial descriptor, falling back to the numeric serial
+  /// of the header when the descriptor is absent and the number is set
+  pub serial: Option<String>,
+  /// The width of the visible area in millimeters, zero when undefined (e.g. projectors)
+  pub width_mm: u32,
+  /// The height of the visible area in millimeters, zero when undefined
+  pub height_mm: u32,
+  /// The EDID version and revision, e.g. `(1, 4)`
+  pub version: (u8, u8),
+}
+
+impl Edid {
+  /// Parse an EDID 1.x base block
+  ///
+  /// # Notes
+  /// Extension blocks following the base block are ignored.  Returns `None` when the
+  /// header, checksum, or version is invalid.
+  pub fn parse(buf: &[u8]) -> Option<Self> {
+    let block = buf.get(..BLOCK_LEN)?;
+    let checksum = block.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+
+    if block[..8] != HEADER || checksum != 0 || block[18] != 1 {
+      return None;
+    }
+
+    // Three letters packed into five bits each, `1` being `A`
+    let id = u16::from_be_bytes([block[8], block[9]]);
+    let manufacturer = [10, 5, 0]
+      .iter()
+      .map(|shift| (b'@' + ((id >> shift) & 0x1f) as u8) as char)
+      .collect();
+
+    let mut model = None;
+    let mut serial = None;
+    let mut size = None;
+
+    for &offset in &DESCRIPTORS {
+      let descriptor = &block[offset..offset + 18];
+
+      match descriptor[..3] {
+        [0, 0, 0] => match descriptor[3] {
+          0xff => serial = Some(descriptor_text(descriptor)),
+          0xfc => model = Some(descriptor_text(descriptor)),
+          _ => {}
+        },
+        // A detailed timing descriptor, the first one holds the preferred mode
+        _ if size.is_none() => {
+          let width = descriptor[12] as u32 | (descriptor[14] as u32 & 0xf0) << 4;
+          let height = descriptor[13] as u32 | (descriptor[14] as u32 & 0x0f) << 8;
+
+          size = Some((width, height));
+        }
+        _ => {}
+      }
+    }
+
+    // The size of the detailed timing is in millimeters, fall back on the basic size in
+    // centimeters
+    let (width_mm, height_mm) = match size {
+      Some((width, height)) if width > 0 && height > 0 => (width, height),
+      _ => (block[21] as u32 * 10, block[22] as u32 * 10),
+    };
+
+    let serial_number = u32::from_le_bytes([block[12], block[13], block[14], block[15]]);
+
+    Some(Self {
+      manufacturer,
+      product_code: u16::from_le_bytes([block[10], block[11]]),
+      model: model.filter(|model| !model.is_empty()),
+      serial: serial
+        .filter(|serial| !serial.is_empty())
+        .or_else(|| (serial_number != 0).then(|| serial_number.to_string())),
+      width_mm,
+      height_mm,
+      version: (block[18], block[19]),
+    })
+  }
+}
+
+/// Get the text of a display descriptor, which ends at a line feed and is space padded
+fn descriptor_text(descriptor: &[u8]) -> String {
+  let text = &descriptor[5..];
+  let len = text.iter().position(|&c| c == b'\n').unwrap_or(text.len());
+
+  String::from_utf8_lossy(&text[..len]).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Edid, BLOCK_LEN};
+
+  /// An EDID 1.4 block of a 24" Dell U2415
+  const U2415: [u8; BLOCK_LEN] = [
+    0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x10, 0xac, 0x6e, 0xa0, 0x4c, 0x4b,
+    0x4d, 0x30, 0x0a, 0x1a, 0x01, 0x04, 0xa5, 0x34, 0x20, 0x78, 0x3a, 0xee, 0x95, 0xa3,
+    0x54, 0x4c, 0x99, 0x26, 0x0f, 0x50, 0x54, 0xa5, 0x4b, 0x00, 0x71, 0x4f, 0x81, 0x80,
+    0xa9, 0x40, 0xd1, 0xc0, 0xd1, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x28, 0x3c,
+    0x80, 0xa0, 0x70, 0xb0, 0x23, 0x40, 0x30, 0x20, 0x36, 0x00, 0x06, 0x44, 0x21, 0x00,
+    0x00, 0x1a, 0x00, 0x00, 0x00, 0xff, 0x00, 0x43, 0x46, 0x56, 0x39, 0x4e, 0x35, 0x38,
+    0x45, 0x30, 0x4d, 0x4b, 0x4c, 0x0a, 0x00, 0x00, 0x00, 0xfc, 0x00, 0x44, 0x45, 0x4c,
+    0x4c, 0x20, 0x55, 0x32, 0x34, 0x31, 0x35, 0x0a, 0x20, 0x20, 0x00, 0x00, 0x00, 0xfd,
+    0x00, 0x31, 0x3d, 0x1e, 0x53, 0x11, 0x00, 0x0a, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20,
+    0x00, 0x00,
+  ];
+
+  /// Fix up the checksum of a block after editing it
+  fn with_checksum(mut block: [u8; BLOCK_LEN]) -> [u8; BLOCK_LEN] {
+    let sum = block[..BLOCK_LEN - 1]
+      .iter()
+      .fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    block[BLOCK_LEN - 1] = 0u8.wrapping_sub(sum);
+    block
+  }
+
+  #[test]
+  fn test_parse_known_edid() {
+    let edid = Edid::parse(&with_checksum(U2415)).unwrap();
+
+    assert_eq!(
+      edid,
+      Edid {
+        manufacturer: "DEL".to_string(),
+        product_code: 0xa06e,
+        model: Some("DELL U2415".to_string()),
+        serial: Some("CFV9N58E0MKL".to_string()),
+        width_mm: 518,
+        height_mm: 324,
+        version: (1, 4),
+      }
+    );
+  }
+
+  #[test]
+  fn test_parse_rejects_invalid() {
+    let mut block = with_checksum(U2415);
+    block[100] ^= 0xff;
+
+    assert_eq!(Edid::parse(&block), None);
+    assert_eq!(Edid::parse(&U2415[..64]), None);
+  }
+}
@@ -0,0 +1,102 @@
+//! Provides encoding frames into image files
+
+use crate::convert::{self, Layout};
+
+/// Encode pixels as an 8 bit `R8G8B8A8` PNG
+///
+/// # Arguments
+/// * `buf` - The pixels to encode
+/// * `layout` - The layout of `buf`
+///
+/// # Notes
+/// Row padding is dropped and, like [`convert::to_rgba`], formats without alpha are made
+/// opaque.
+#[cfg(feature = "png")]
+pub fn png(buf: &[u8], layout: Layout) -> anyhow::Result<Vec<u8>> {
+  let rgba = convert::to_rgba(buf, layout)?;
+  let mut out = Vec::new();
+
+  let mut encoder =
+    png::Encoder::new(&mut out, layout.width as u32, layout.height as u32);
+  encoder.set_color(png::ColorType::RGBA);
+  encoder.set_depth(png::BitDepth::Eight);
+
+  let mut writer = encoder.write_header()?;
+  writer.write_image_data(&rgba)?;
+  drop(writer);
+
+  Ok(out)
+}
+
+/// Encode pixels as a baseline 8 bit RGB JPEG
+///
+/// # Arguments
+/// * `buf` - The pixels to encode
+/// * `layout` - The layout of `buf`
+/// * `quality` - The JPEG quality, clamped to `1..=100`
+///
+/// # Notes
+/// Alpha is dropped.  Like every JFIF file the pixels are stored as full range BT.601
+/// YCbCr.  Below quality 90 chroma is subsampled 4:2:0 so, colors of thin text and lines
+/// bleed slightly.  Frames larger than 65535 pixels in either dimension can't be encoded.
+#[cfg(feature = "jpeg")]
+pub fn jpeg(buf: &[u8], layout: Layout, quality: u8) -> anyhow::Result<Vec<u8>> {
+  let max = u16::MAX as usize;
+  if layout.width > max || layout.height > max {
+    anyhow::bail!("{}x{} is too large for JPEG", layout.width, layout.height);
+  }
+
+  let rgb = convert::to_rgba(buf, layout)?
+    .chunks_exact(4)
+    .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+    .collect::<Vec<_>>();
+
+  let mut out = Vec::new();
+  jpeg_encoder::Encoder::new(&mut out, quality.clamp(1, 100)).encode(
+    &rgb,
+    layout.width as u16,
+    layout.height as u16,
+    jpeg_encoder::ColorType::Rgb,
+  )?;
+
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{convert::Layout, FrameFormat};
+
+  #[cfg(feature = "png")]
+  #[test]
+  fn test_png_round_trip() {
+    // 2x1 `B8G8R8A8` pixels with 4 bytes of row padding
+    let buf = [1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0];
+    let layout = Layout::new(2, 1, 12, FrameFormat::B8G8R8A8);
+
+    let encoded = super::png(&buf, layout).unwrap();
+    let (info, mut reader) = ::png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+    let mut decoded = vec![0; info.buffer_size()];
+    reader.next_frame(&mut decoded).unwrap();
+
+    assert_eq!((info.width, info.height), (2, 1));
+    assert_eq!(decoded, vec![3, 2, 1, 4, 7, 6, 5, 8]);
+  }
+
+  #[cfg(feature = "jpeg")]
+  #[test]
+  fn test_jpeg_quality() {
+    // 16x16 `B8G8R8A8` noise so, quality visibly changes the size
+    let buf = (0..16 * 16 * 4)
+      .map(|i| (i * 37 % 251) as u8)
+      .collect::<Vec<_>>();
+    let layout = Layout::packed(16, 16, FrameFormat::B8G8R8A8);
+
+    let low = super::jpeg(&buf, layout, 0).unwrap();
+    let high = super::jpeg(&buf, layout, 255).unwrap();
+
+    assert_eq!(&low[..2], &[0xff, 0xd8]);
+    assert_eq!(&high[high.len() - 2..], &[0xff, 0xd9]);
+    assert!(low.len() < high.len());
+    assert_eq!(high, super::jpeg(&buf, layout, 100).unwrap());
+  }
+}
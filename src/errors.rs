@@ -5,6 +5,18 @@ pub enum FrameError {
   #[cfg(target_os = "windows")]
   #[error(transparent)]
   Dxgi(crate::driver::dxgi::errors::FrameError),
+  #[cfg(target_os = "windows")]
+  #[error(transparent)]
+  Gdi(crate::driver::gdi::errors::FrameError),
+  #[cfg(all(target_os = "linux", feature = "x11"))]
+  #[error(transparent)]
+  X11(crate::driver::x11::errors::FrameError),
+  #[cfg(target_os = "linux")]
+  #[error(transparent)]
+  Wayland(crate::driver::wayland::errors::FrameError),
+  #[cfg(target_os = "macos")]
+  #[error(transparent)]
+  Quartz(crate::driver::quartz::errors::FrameError),
 }
 
 #[cfg(target_os = "windows")]
@@ -17,8 +29,77 @@ impl From<crate::driver::dxgi::errors::FrameError> for FrameError {
   }
 }
 
-#[derive(thiserror::Error, Debug, Clone, PartialEq, PartialOrd)]
-pub enum DisplayError {}
+#[cfg(target_os = "windows")]
+impl From<crate::driver::gdi::errors::FrameError> for FrameError {
+  fn from(inner: crate::driver::gdi::errors::FrameError) -> Self {
+    Self::Gdi(inner)
+  }
+}
+
+#[cfg(all(target_os = "linux", feature = "x11"))]
+impl From<crate::driver::x11::errors::FrameError> for FrameError {
+  fn from(inner: crate::driver::x11::errors::FrameError) -> Self {
+    Self::X11(inner)
+  }
+}
 
-#[derive(thiserror::Error, Debug, Clone, PartialEq, PartialOrd)]
-pub enum DriverError {}
+#[cfg(target_os = "linux")]
+impl From<crate::driver::wayland::errors::FrameError> for FrameError {
+  fn from(inner: crate::driver::wayland::errors::FrameError) -> Self {
+    Self::Wayland(inner)
+  }
+}
+
+#[cfg(target_os = "macos")]
+impl From<crate::driver::quartz::errors::FrameError> for FrameError {
+  fn from(inner: crate::driver::quartz::errors::FrameError) -> Self {
+    Self::Quartz(inner)
+  }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum DisplayError {
+  #[cfg(target_os = "windows")]
+  #[error("Failed to enumerate displays `{0}`")]
+  Enumerate(#[from] windows::Error),
+  #[cfg(target_os = "windows")]
+  #[error("No display adapters were found")]
+  NoAdapters,
+  #[cfg(target_os = "linux")]
+  #[error("Failed to connect to the X server")]
+  Connect,
+  #[cfg(target_os = "linux")]
+  #[error("Failed to enumerate Wayland outputs `{0}`")]
+  Wayland(String),
+  #[cfg(target_os = "macos")]
+  #[error("Failed to list displays, CGError `{0}`")]
+  DisplayList(i32),
+  #[error("Display `{0}` was not found")]
+  NotFound(String),
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum DriverError {
+  #[error("No display drivers are available")]
+  NoDriverAvailable,
+  #[error("Failed to initialize {name}")]
+  InitFailed {
+    name: &'static str,
+    #[source]
+    source: std::sync::Arc<dyn std::error::Error + Send + Sync>,
+  },
+  #[error("Every display driver failed ({})", join(.0))]
+  AllFailed(Vec<DriverError>),
+}
+
+/// Join the errors of several drivers, each followed by its source, into a single line
+fn join(errors: &[DriverError]) -> String {
+  errors
+    .iter()
+    .map(|err| match std::error::Error::source(err) {
+      Some(source) => format!("{}: {}", err, source),
+      None => err.to_string(),
+    })
+    .collect::<Vec<_>>()
+    .join(", ")
+}
@@ -0,0 +1,107 @@
+//! Provides uploading frames into wgpu textures
+
+use crate::{
+  convert::{self, Layout},
+  FrameFormat,
+};
+use std::borrow::Cow;
+
+/// Get the wgpu format holding pixels of `format` as is
+///
+/// # Notes
+/// `None` for formats without a matching texture format, those are uploaded as
+/// `Rgba8Unorm` after [`convert::to_rgba`].
+pub const fn texture_format(format: FrameFormat) -> Option<wgpu::TextureFormat> {
+  match format {
+    FrameFormat::B8G8R8A8 => Some(wgpu::TextureFormat::Bgra8Unorm),
+    FrameFormat::R8G8B8A8 => Some(wgpu::TextureFormat::Rgba8Unorm),
+    FrameFormat::R10G10B10A2 => Some(wgpu::TextureFormat::Rgb10a2Unorm),
+    FrameFormat::R16G16B16A16Float => Some(wgpu::TextureFormat::Rgba16Float),
+    FrameFormat::Luma8 => Some(wgpu::TextureFormat::R8Unorm),
+    _ => None,
+  }
+}
+
+/// Upload pixels into a new texture that can be sampled and copied into
+///
+/// # Arguments
+/// * `device` - The device creating the texture
+/// * `queue` - The queue of `device` the pixels are written with
+/// * `buf` - The pixels to upload
+/// * `layout` - The layout of `buf`
+///
+/// # Notes
+/// Row padding is passed along as the stride of the upload, so pixels are only copied
+/// once into the staging memory of wgpu.  The upload is queued and lands before any
+/// commands submitted to `queue` afterwards.
+pub fn upload(
+  device: &wgpu::Device,
+  queue: &wgpu::Queue,
+  buf: &[u8],
+  layout: Layout,
+) -> anyhow::Result<wgpu::Texture> {
+  layout.check(buf)?;
+
+  let (format, buf, layout) = match texture_format(layout.format) {
+    Some(format) => (format, Cow::from(buf), layout),
+    None => (
+      wgpu::TextureFormat::Rgba8Unorm,
+      Cow::from(convert::to_rgba(buf, layout)?),
+      Layout::packed(layout.width, layout.height, FrameFormat::R8G8B8A8),
+    ),
+  };
+
+  let size = wgpu::Extent3d {
+    width: layout.width as u32,
+    height: layout.height as u32,
+    depth_or_array_layers: 1,
+  };
+
+  let texture = device.create_texture(&wgpu::TextureDescriptor {
+    label: Some("scraptor frame"),
+    size,
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension: wgpu::TextureDimension::D2,
+    format,
+    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    view_formats: &[],
+  });
+
+  queue.write_texture(
+    wgpu::TexelCopyTextureInfo {
+      texture: &texture,
+      mip_level: 0,
+      origin: wgpu::Origin3d::ZERO,
+      aspect: wgpu::TextureAspect::All,
+    },
+    &buf,
+    wgpu::TexelCopyBufferLayout {
+      offset: 0,
+      bytes_per_row: Some(layout.stride as u32),
+      rows_per_image: Some(layout.height as u32),
+    },
+    size,
+  );
+
+  Ok(texture)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::texture_format;
+  use crate::FrameFormat;
+
+  #[test]
+  fn test_texture_format() {
+    assert_eq!(
+      texture_format(FrameFormat::B8G8R8A8),
+      Some(wgpu::TextureFormat::Bgra8Unorm)
+    );
+    assert_eq!(
+      texture_format(FrameFormat::R16G16B16A16Float),
+      Some(wgpu::TextureFormat::Rgba16Float)
+    );
+    assert_eq!(texture_format(FrameFormat::B8G8R8), None);
+  }
+}
@@ -1,11 +1,45 @@
+pub mod alloc;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod compare;
+pub mod config;
+pub mod convert;
+pub mod cursor;
+pub mod diagnostics;
 pub mod driver;
+pub mod edid;
+#[cfg(any(feature = "png", feature = "jpeg"))]
+pub mod encode;
 pub mod errors;
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+pub mod overlay;
+pub mod owned;
+pub mod parallel;
+pub mod power;
+pub mod queue;
+pub mod record;
+pub mod ring;
+#[cfg(feature = "tokio")]
+pub mod stream;
+pub mod transform;
+#[cfg(feature = "ws")]
+pub mod ws;
 
 #[cfg(target_os = "windows")]
 pub mod bindings;
 
+pub use config::{CaptureConfig, DriverType, DropPolicy};
+pub use diagnostics::{diagnostics, Diagnostics};
+pub use driver::{capture, capture_primary, drivers, Capture};
+pub use owned::{MetaValue, OwnedFrame};
+pub use power::PowerState;
+pub use ring::FrameRing;
+
+use convert::Layout;
+use cursor::CursorInfo;
 use errors::{DisplayError, FrameError};
-use std::borrow::Cow;
+use std::{borrow::Cow, time::Duration};
 
 /// Provides access to displays
 pub trait DisplayDriver<'buf> {
@@ -32,23 +66,382 @@ pub trait Display<'buf> {
 
 /// A screen capture frame.
 pub trait Frame<'buf> {
+  /// The width of the frame in pixels
+  fn width(&self) -> usize;
+
+  /// The height of the frame in pixels
+  fn height(&self) -> usize;
+
   /// Gets rectangles that changed since last frame
-  fn dirty(&self) -> Vec<DirtyRect>;
+  ///
+  /// # Notes
+  /// An empty list means nothing changed, failing to read the rectangles is an error.
+  fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError>;
+
+  /// Gets rectangles that changed since last frame with overlapping and touching ones
+  /// merged
+  ///
+  /// # Notes
+  /// Fewer, larger rectangles than [`Frame::dirty`], which is left as reported by the
+  /// driver.  See [`compare::merge_rects`].
+  fn dirty_merged(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    Ok(compare::merge_rects(
+      self.dirty()?,
+      self.width(),
+      self.height(),
+    ))
+  }
 
   /// Gets rectangles that moved since last frame
-  fn moved(&self) -> Vec<MovedRect>;
+  ///
+  /// # Notes
+  /// An empty list means nothing moved, failing to read the rectangles is an error.
+  fn moved(&self) -> Result<Vec<MovedRect>, FrameError>;
 
   /// The pixel format of the frame
   fn format(&self) -> FrameFormat;
 
   /// The pixel data of the frame
   fn as_bytes(&self) -> anyhow::Result<Cow<'buf, [u8]>>;
+
+  /// The number of bytes between the start of two rows in [`Frame::as_bytes`]
+  ///
+  /// # Notes
+  /// Rows may be padded for alignment so, this can be larger than `width` times
+  /// [`FrameFormat::bytes_per_pixel`].  Defaults to tightly packed rows.
+  fn stride(&self) -> usize {
+    self.width() * self.format().bytes_per_pixel()
+  }
+
+  /// Copy the pixel data of the frame without row padding
+  ///
+  /// # Notes
+  /// Returns exactly `width * height * bytes_per_pixel` bytes.  Rows are only copied one
+  /// at a time when [`Frame::stride`] includes padding.
+  fn as_packed_bytes(&self) -> anyhow::Result<Vec<u8>> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+    layout.check(&bytes)?;
+
+    let len = layout.row_len() * layout.height;
+    if layout.stride == layout.row_len() {
+      let mut bytes = bytes.into_owned();
+      bytes.truncate(len);
+
+      return Ok(bytes);
+    }
+
+    let mut data = alloc::allocate(len);
+    for row in layout.rows(&bytes) {
+      data.extend_from_slice(row);
+    }
+
+    Ok(data)
+  }
+
+  /// Copy the pixel data of the frame into `buf` without row padding
+  ///
+  /// # Notes
+  /// `buf` is cleared first and only grows when the frame doesn't fit so, reusing it
+  /// across frames avoids allocating once capture settles.
+  fn copy_into(&self, buf: &mut Vec<u8>) -> anyhow::Result<()> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+    layout.check(&bytes)?;
+
+    buf.clear();
+    buf.reserve(layout.row_len() * layout.height);
+    for row in layout.rows(&bytes) {
+      buf.extend_from_slice(row);
+    }
+
+    Ok(())
+  }
+
+  /// Copy the pixels inside each changed rectangle
+  ///
+  /// # Notes
+  /// Covers both [`Frame::dirty`] and the destinations of [`Frame::moved`], clipped to
+  /// the frame.  Rectangles repeated or covered by another are only copied once, see
+  /// [`compare::distinct_rects`].  Each copy is tightly packed.
+  fn dirty_subframes(&self) -> anyhow::Result<Vec<(DirtyRect, Vec<u8>)>> {
+    let rects = self
+      .dirty()?
+      .into_iter()
+      .chain(self.moved()?.into_iter().map(|moved| moved.to));
+    let rects = compare::distinct_rects(rects, self.width(), self.height());
+
+    if rects.is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+    layout.check(&bytes)?;
+
+    let bpp = layout.format.bytes_per_pixel();
+
+    Ok(
+      rects
+        .into_iter()
+        .map(|rect| {
+          let columns = rect.left as usize * bpp..rect.right as usize * bpp;
+          let rows = rect.top as usize..rect.bottom as usize;
+          let mut data = alloc::allocate(columns.len() * rows.len());
+
+          for row in layout.rows(&bytes).skip(rows.start).take(rows.len()) {
+            data.extend_from_slice(&row[columns.clone()]);
+          }
+
+          (rect, data)
+        })
+        .collect(),
+    )
+  }
+
+  /// The position of the frame in capture order, restarting at zero every generation
+  fn sequence(&self) -> u64 {
+    0
+  }
+
+  /// The capturer instance the frame came from, bumped whenever the capturer is recreated
+  fn generation(&self) -> u32 {
+    0
+  }
+
+  /// Whether the pixels are unchanged since the previous frame
+  fn is_repeat(&self) -> bool {
+    false
+  }
+
+  /// Where the cursor was when the frame was captured, `None` when the driver doesn't
+  /// report it or the cursor hasn't been seen yet
+  fn cursor(&self) -> Option<CursorInfo> {
+    None
+  }
+
+  /// Hash the pixel data of the frame so, unchanged frames can be skipped cheaply
+  ///
+  /// # Notes
+  /// Compare the hashes of consecutive frames when [`Frame::dirty`] can't be trusted.
+  /// Row padding is ignored, see [`compare::hash`].
+  fn content_hash(&self) -> anyhow::Result<u64> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+
+    compare::hash(&bytes, layout)
+  }
+
+  /// Convert the pixel data of the frame into tightly packed `R8G8B8A8`
+  ///
+  /// # Notes
+  /// Row padding is dropped and `R8G8B8A8` frames are copied as is, see
+  /// [`convert::to_rgba`] for how other formats are converted.
+  fn to_rgba(&self) -> anyhow::Result<Vec<u8>> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+
+    convert::to_rgba(&bytes, layout)
+  }
+
+  /// Convert the pixel data of the frame into NV12, the input most video encoders want
+  ///
+  /// # Notes
+  /// See [`convert::to_nv12`] for the layout of the planes and supported formats.
+  fn to_nv12(&self) -> anyhow::Result<Vec<u8>> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+
+    convert::to_nv12(&bytes, layout)
+  }
+
+  /// Replace the contents of the clipboard with the frame as a `CF_DIB` bitmap
+  #[cfg(all(target_os = "windows", feature = "clipboard"))]
+  fn copy_to_clipboard(&self) -> anyhow::Result<()> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+
+    clipboard::copy(&bytes, layout)
+  }
+
+  /// Encode the frame as an `R8G8B8A8` PNG
+  ///
+  /// # Notes
+  /// See [`convert::to_rgba`] for how pixel formats other than `R8G8B8A8` are converted.
+  #[cfg(feature = "png")]
+  fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+
+    encode::png(&bytes, layout)
+  }
+
+  /// Encode the frame as an `R8G8B8A8` PNG and write it to `path`
+  #[cfg(feature = "png")]
+  fn save_png<P>(&self, path: P) -> anyhow::Result<()>
+  where
+    P: AsRef<std::path::Path>,
+    Self: Sized,
+  {
+    std::fs::write(path, self.to_png()?)?;
+    Ok(())
+  }
+
+  /// Encode the frame as a baseline RGB JPEG
+  ///
+  /// # Arguments
+  /// * `quality` - The JPEG quality, clamped to `1..=100`
+  ///
+  /// # Notes
+  /// See [`encode::jpeg`], alpha is dropped.
+  #[cfg(feature = "jpeg")]
+  fn to_jpeg(&self, quality: u8) -> anyhow::Result<Vec<u8>> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+
+    encode::jpeg(&bytes, layout, quality)
+  }
+
+  /// Convert the frame into an [`image::RgbaImage`]
+  ///
+  /// # Notes
+  /// See [`convert::to_rgba`] for how pixel formats other than `R8G8B8A8` are converted.
+  #[cfg(feature = "image")]
+  fn to_image(&self) -> anyhow::Result<image::RgbaImage> {
+    let (width, height) = (self.width() as u32, self.height() as u32);
+
+    image::RgbaImage::from_raw(width, height, self.to_rgba()?)
+      .ok_or_else(|| anyhow::anyhow!("Pixels don't fit a {}x{} image", width, height))
+  }
+
+  /// Upload the frame into a new [`wgpu::Texture`] that can be sampled
+  ///
+  /// # Arguments
+  /// * `device` - The device creating the texture
+  /// * `queue` - The queue of `device` the pixels are written with
+  ///
+  /// # Notes
+  /// Works with every wgpu backend.  The pixels go through the CPU, see
+  /// [`gpu::texture_format`] for the texture format of each [`FrameFormat`].
+  #[cfg(feature = "wgpu")]
+  fn to_wgpu_texture(
+    &self,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+  ) -> anyhow::Result<wgpu::Texture> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+
+    gpu::upload(device, queue, &bytes, layout)
+  }
+
+  /// Whether every pixel in `rect` matches `color` within `tolerance`
+  ///
+  /// # Arguments
+  /// * `rect` - The region to check, must lie within the frame
+  /// * `color` - The bytes of a pixel in the native [`FrameFormat`] of the frame, bytes
+  ///   beyond [`FrameFormat::bytes_per_pixel`] are ignored
+  /// * `tolerance` - The largest difference allowed per byte
+  ///
+  /// # Notes
+  /// Returns `false` when `rect` is empty, out of bounds or the pixel data is unavailable.
+  fn region_is_color(&self, rect: DirtyRect, color: [u8; 4], tolerance: u8) -> bool {
+    let in_bounds = rect.left >= 0
+      && rect.top >= 0
+      && rect.left < rect.right
+      && rect.top < rect.bottom
+      && rect.right as usize <= self.width()
+      && rect.bottom as usize <= self.height();
+
+    let bytes = match self.as_bytes() {
+      Ok(bytes) if in_bounds => bytes,
+      _ => return false,
+    };
+
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+    if layout.check(&bytes).is_err() {
+      return false;
+    }
+
+    let bpp = layout.format.bytes_per_pixel();
+    let color = &color[..bpp.min(color.len())];
+
+    let mut rows = layout
+      .rows(&bytes)
+      .skip(rect.top as usize)
+      .take((rect.bottom - rect.top) as usize);
+
+    rows.all(|row| {
+      row[rect.left as usize * bpp..rect.right as usize * bpp]
+        .chunks_exact(bpp)
+        .all(|px| {
+          px.iter()
+            .zip(color)
+            .all(|(a, b)| a.abs_diff(*b) <= tolerance)
+        })
+    })
+  }
+
+  /// Append the tightly packed pixel data of the frame to `dst`
+  ///
+  /// # Notes
+  /// Capacity for the whole frame is reserved up front so, `dst` grows at most once.
+  #[cfg(feature = "bytes")]
+  fn copy_to_bytes(&self, dst: &mut bytes::BytesMut) -> anyhow::Result<()> {
+    let bytes = self.as_bytes()?;
+    let layout = Layout::new(self.width(), self.height(), self.stride(), self.format());
+    layout.check(&bytes)?;
+
+    dst.reserve(layout.row_len() * layout.height);
+
+    for row in layout.rows(&bytes) {
+      dst.extend_from_slice(row);
+    }
+
+    Ok(())
+  }
 }
 
 /// Pixel data format
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum FrameFormat {
   B8G8R8A8,
+  R8G8B8A8,
+  B8G8R8,
+  R8G8B8,
+  Luma8,
+  Luma16,
+  /// 10 bit color and 2 bit alpha packed into a little endian `u32`, red in the lowest
+  /// bits
+  R10G10B10A2,
+  /// Linear scRGB stored as little endian half floats, values above `1.0` are brighter
+  /// than SDR white so, they need tonemapping before display on an SDR screen
+  R16G16B16A16Float,
+}
+
+impl FrameFormat {
+  /// The number of bytes used to store a single pixel
+  pub const fn bytes_per_pixel(&self) -> usize {
+    match self {
+      Self::R16G16B16A16Float => 8,
+      Self::B8G8R8A8 | Self::R8G8B8A8 | Self::R10G10B10A2 => 4,
+      Self::B8G8R8 | Self::R8G8B8 => 3,
+      Self::Luma16 => 2,
+      Self::Luma8 => 1,
+    }
+  }
+
+  /// Whether pixels carry an alpha channel
+  ///
+  /// # Notes
+  /// Desktop images are opaque so, captured alpha is usually `0xff` (or undefined) rather
+  /// than meaningful transparency.
+  pub const fn has_alpha(&self) -> bool {
+    matches!(
+      self,
+      Self::B8G8R8A8 | Self::R8G8B8A8 | Self::R10G10B10A2 | Self::R16G16B16A16Float
+    )
+  }
 }
 
 /// An area where pixels have changed since the last frame capture
@@ -69,6 +462,71 @@ impl DirtyRect {
       bottom,
     }
   }
+
+  /// Create [`DirtyRect`] from its left, top, right and bottom edges
+  ///
+  /// # Notes
+  /// Swapped edges are put back in order, e.g. a `left` past `right`, so the rectangle
+  /// always has `left <= right` and `top <= bottom`.
+  pub fn from_ltrb(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+    Self {
+      top: top.min(bottom),
+      left: left.min(right),
+      right: left.max(right),
+      bottom: top.max(bottom),
+    }
+  }
+
+  /// The width of the rectangle in pixels, zero when `right` isn't past `left`
+  pub const fn width(&self) -> i32 {
+    if self.right > self.left {
+      self.right - self.left
+    } else {
+      0
+    }
+  }
+
+  /// The height of the rectangle in pixels, zero when `bottom` isn't past `top`
+  pub const fn height(&self) -> i32 {
+    if self.bottom > self.top {
+      self.bottom - self.top
+    } else {
+      0
+    }
+  }
+
+  /// The number of pixels the rectangle covers
+  pub const fn area(&self) -> i64 {
+    self.width() as i64 * self.height() as i64
+  }
+
+  /// Whether the rectangles share at least one pixel
+  ///
+  /// # Notes
+  /// `right` and `bottom` are exclusive so, rectangles that only touch don't intersect.
+  pub const fn intersects(&self, other: &DirtyRect) -> bool {
+    self.left < other.right
+      && other.left < self.right
+      && self.top < other.bottom
+      && other.top < self.bottom
+  }
+
+  /// Get the pixels both rectangles cover, `None` when they don't intersect
+  pub fn intersection(&self, other: &DirtyRect) -> Option<DirtyRect> {
+    self.intersects(other).then(|| {
+      DirtyRect::new(
+        self.top.max(other.top),
+        self.right.min(other.right),
+        self.bottom.min(other.bottom),
+        self.left.max(other.left),
+      )
+    })
+  }
+
+  /// Whether the pixel at `x` and `y` is inside the rectangle
+  pub const fn contains_point(&self, x: i32, y: i32) -> bool {
+    self.left <= x && x < self.right && self.top <= y && y < self.bottom
+  }
 }
 
 /// A point where an area of pixel moved to since the last frame capture
@@ -96,3 +554,46 @@ impl MovedRect {
     Self { to, from }
   }
 }
+
+/// How long each stage of capturing a frame took
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FrameTiming {
+  /// Time spent releasing the previous frame
+  pub release: Duration,
+  /// Time spent waiting for and acquiring the frame
+  pub acquire: Duration,
+  /// Time spent mapping or copying the frame pixels
+  pub map: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::DirtyRect;
+
+  #[test]
+  fn test_dirty_rect_geometry() {
+    let a = DirtyRect::new(0, 4, 2, 0);
+    let b = DirtyRect::new(1, 6, 3, 2);
+    // Only shares an edge with `a`
+    let c = DirtyRect::new(0, 8, 2, 4);
+
+    assert_eq!((a.width(), a.height(), a.area()), (4, 2, 8));
+    assert_eq!(DirtyRect::new(5, 1, 2, 3).area(), 0);
+
+    assert!(a.intersects(&b) && b.intersects(&a));
+    assert_eq!(a.intersection(&b), Some(DirtyRect::new(1, 4, 2, 2)));
+    assert!(!a.intersects(&c));
+    assert_eq!(a.intersection(&c), None);
+
+    assert!(a.contains_point(0, 0));
+    assert!(a.contains_point(3, 1));
+    assert!(!a.contains_point(4, 1));
+    assert!(!a.contains_point(3, 2));
+  }
+
+  #[test]
+  fn test_dirty_rect_from_ltrb() {
+    assert_eq!(DirtyRect::from_ltrb(1, 2, 3, 4), DirtyRect::new(2, 3, 4, 1));
+    assert_eq!(DirtyRect::from_ltrb(3, 4, 1, 2), DirtyRect::new(2, 3, 4, 1));
+  }
+}
@@ -0,0 +1,148 @@
+//! Provides a tiny built-in bitmap font for burning text into captured frames
+
+use crate::{convert::Layout, FrameFormat};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The width of a glyph in font pixels
+const GLYPH_WIDTH: usize = 3;
+/// The height of a glyph in font pixels
+const GLYPH_HEIGHT: usize = 5;
+
+/// Get the rows of a glyph, each a 3 bit mask with the leftmost pixel in the highest bit
+///
+/// # Notes
+/// Only the characters produced by [`timestamp`] are included, anything else is blank.
+const fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+  match c {
+    '0' => [7, 5, 5, 5, 7],
+    '1' => [2, 6, 2, 2, 7],
+    '2' => [7, 1, 7, 4, 7],
+    '3' => [7, 1, 7, 1, 7],
+    '4' => [5, 5, 7, 1, 1],
+    '5' => [7, 4, 7, 1, 7],
+    '6' => [7, 4, 7, 5, 7],
+    '7' => [7, 1, 1, 1, 1],
+    '8' => [7, 5, 7, 5, 7],
+    '9' => [7, 5, 7, 1, 7],
+    ':' => [0, 2, 0, 2, 0],
+    '.' => [0, 0, 0, 0, 2],
+    '#' => [5, 7, 5, 7, 5],
+    '-' => [0, 0, 7, 0, 0],
+    _ => [0; GLYPH_HEIGHT],
+  }
+}
+
+/// Format a capture time as `HH:MM:SS.mmm` (UTC), followed by ` #sequence` when given
+pub fn timestamp(time: SystemTime, sequence: Option<u64>) -> String {
+  let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+  let secs = since_epoch.as_secs();
+  let text = format!(
+    "{:02}:{:02}:{:02}.{:03}",
+    secs / 3600 % 24,
+    secs / 60 % 60,
+    secs % 60,
+    since_epoch.subsec_millis()
+  );
+
+  match sequence {
+    Some(sequence) => format!("{} #{}", text, sequence),
+    None => text,
+  }
+}
+
+/// Draw white text on a black box in the top-left corner of a buffer
+///
+/// # Arguments
+/// * `buf` - The pixels to draw on
+/// * `layout` - The layout of `buf`
+/// * `text` - The text to draw
+/// * `scale` - The size of a font pixel in buffer pixels
+///
+/// # Notes
+/// The box has a one font pixel margin around the text and is clipped to the buffer.
+pub fn draw_text(
+  buf: &mut [u8],
+  layout: Layout,
+  text: &str,
+  scale: usize,
+) -> anyhow::Result<()> {
+  layout.check(buf)?;
+  anyhow::ensure!(
+    layout.format != FrameFormat::R16G16B16A16Float,
+    "Cannot draw text on {:?}",
+    layout.format
+  );
+
+  let bpp = layout.format.bytes_per_pixel();
+  let on = pixel(layout.format, 0xff);
+  let off = pixel(layout.format, 0x00);
+
+  // Glyphs are separated by a blank column, the margin surrounds everything
+  let glyphs: Vec<_> = text.chars().map(glyph).collect();
+  let cols = glyphs.len() * (GLYPH_WIDTH + 1) + 1;
+  let rows = GLYPH_HEIGHT + 2;
+
+  for y in 0..(rows * scale).min(layout.height) {
+    let row = y / scale;
+
+    for x in 0..(cols * scale).min(layout.width) {
+      let col = x / scale;
+      let (idx, bit) = (col / (GLYPH_WIDTH + 1), col % (GLYPH_WIDTH + 1));
+      let lit = match glyphs.get(idx) {
+        Some(glyph) if bit > 0 && (1..=GLYPH_HEIGHT).contains(&row) => {
+          (glyph[row - 1] >> (GLYPH_WIDTH - bit)) & 1 == 1
+        }
+        _ => false,
+      };
+
+      let start = y * layout.stride + x * bpp;
+      let color = if lit { &on } else { &off };
+      buf[start..start + bpp].copy_from_slice(&color[..bpp]);
+    }
+  }
+
+  Ok(())
+}
+
+/// Get the bytes of a gray pixel in `format`, opaque when the format has alpha
+const fn pixel(format: FrameFormat, value: u8) -> [u8; 4] {
+  match format {
+    FrameFormat::B8G8R8A8 | FrameFormat::R8G8B8A8 => [value, value, value, 0xff],
+    FrameFormat::R10G10B10A2 => {
+      // Repeat the top bits so, `0xff` becomes the 10 bit maximum
+      let value = (value as u32) << 2 | (value as u32) >> 6;
+      (value | value << 10 | value << 20 | 3 << 30).to_le_bytes()
+    }
+    _ => [value; 4],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{draw_text, timestamp};
+  use crate::{convert::Layout, FrameFormat};
+  use std::time::{Duration, UNIX_EPOCH};
+
+  #[test]
+  fn test_timestamp() {
+    let time = UNIX_EPOCH + Duration::from_millis(3_661_500);
+
+    assert_eq!(timestamp(time, None), "01:01:01.500");
+    assert_eq!(timestamp(time, Some(7)), "01:01:01.500 #7");
+  }
+
+  #[test]
+  fn test_draw_text_region() {
+    let layout = Layout::packed(20, 10, FrameFormat::Luma8);
+    let mut buf = vec![128; 20 * 10];
+
+    draw_text(&mut buf, layout, "1", 1).unwrap();
+
+    // The box covers 5x7 pixels, "1" starts with a single lit pixel in the middle column
+    assert_eq!(&buf[20..25], &[0, 0, 255, 0, 0]);
+    assert_eq!(&buf[5 * 20 + 1..5 * 20 + 4], &[255, 255, 255]);
+    assert_eq!(buf[6 * 20], 0);
+    assert!(buf[..7 * 20].chunks(20).all(|row| row[5..] == [128; 15]));
+    assert!(buf[7 * 20..].iter().all(|&px| px == 128));
+  }
+}
@@ -0,0 +1,390 @@
+//! Provides a frame that owns its pixel data
+
+use crate::{
+  alloc, convert::Layout, errors::FrameError, DirtyRect, Frame, FrameFormat, MovedRect,
+};
+use std::{borrow::Cow, fmt, mem};
+
+/// A frame whose pixels are copied out of the capturer
+///
+/// Rows are tightly packed so `stride` is always `width * bytes_per_pixel`.
+///
+/// # Notes
+/// Unlike the frames of drivers, which borrow thread bound COM or display server state,
+/// an [`OwnedFrame`] holds no ties to its capturer so, it's `Send` and `Sync` and can be
+/// handed to other threads, e.g. an encoder.
+#[derive(Clone, PartialEq, Eq)]
+pub struct OwnedFrame {
+  width: usize,
+  height: usize,
+  format: FrameFormat,
+  data: Vec<u8>,
+  dirty: Vec<DirtyRect>,
+  moved: Vec<MovedRect>,
+  sequence: u64,
+  generation: u32,
+  metadata: Vec<(Box<str>, MetaValue)>,
+}
+
+/// A value attached to an [`OwnedFrame`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MetaValue {
+  Bool(bool),
+  Int(i64),
+  UInt(u64),
+  Text(String),
+}
+
+impl OwnedFrame {
+  /// Create [`OwnedFrame`] from tightly packed pixel data
+  ///
+  /// # Arguments
+  /// * `width` - The width of the frame in pixels
+  /// * `height` - The height of the frame in pixels
+  /// * `format` - The pixel format of `data`
+  /// * `data` - The pixel data without any row padding
+  pub fn new(width: usize, height: usize, format: FrameFormat, data: Vec<u8>) -> Self {
+    debug_assert_eq!(data.len(), width * height * format.bytes_per_pixel());
+
+    Self {
+      width,
+      height,
+      format,
+      data,
+      dirty: Vec::new(),
+      moved: Vec::new(),
+      sequence: 0,
+      generation: 0,
+      metadata: Vec::new(),
+    }
+  }
+
+  /// Copy the pixels and metadata of a frame, removing any row padding
+  pub fn from_frame(frame: &dyn Frame<'_>) -> anyhow::Result<Self> {
+    let width = frame.width();
+    let height = frame.height();
+    let format = frame.format();
+    let bytes = frame.as_bytes()?;
+
+    let layout = Layout::new(width, height, frame.stride(), format);
+    layout.check(&bytes)?;
+
+    let mut data = alloc::allocate(layout.row_len() * height);
+    if layout.stride == layout.row_len() {
+      data.extend_from_slice(&bytes[..layout.row_len() * height]);
+    } else {
+      layout
+        .rows(&bytes)
+        .for_each(|row| data.extend_from_slice(row));
+    }
+
+    Ok(Self {
+      width,
+      height,
+      format,
+      data,
+      dirty: frame.dirty()?,
+      moved: frame.moved()?,
+      sequence: frame.sequence(),
+      generation: frame.generation(),
+      metadata: Vec::new(),
+    })
+  }
+
+  /// The width of the frame in pixels
+  pub const fn width(&self) -> usize {
+    self.width
+  }
+
+  /// The height of the frame in pixels
+  pub const fn height(&self) -> usize {
+    self.height
+  }
+
+  /// The pixel format of the frame
+  pub const fn format(&self) -> FrameFormat {
+    self.format
+  }
+
+  /// The position of the frame in capture order
+  pub const fn sequence(&self) -> u64 {
+    self.sequence
+  }
+
+  /// The capturer instance the frame came from
+  pub const fn generation(&self) -> u32 {
+    self.generation
+  }
+
+  /// The number of bytes in a single row of pixels
+  pub const fn stride(&self) -> usize {
+    self.width * self.format.bytes_per_pixel()
+  }
+
+  /// Get the tightly packed pixel data
+  pub fn data(&self) -> &[u8] {
+    &self.data
+  }
+
+  /// Convert into the tightly packed pixel data
+  pub fn into_data(mut self) -> Vec<u8> {
+    mem::take(&mut self.data)
+  }
+
+  /// Attach a metadata value to the frame, replacing any existing value for `key`
+  pub fn with_metadata(mut self, key: &str, value: MetaValue) -> Self {
+    self.set_metadata(key, value);
+    self
+  }
+
+  /// Attach a metadata value to the frame, replacing any existing value for `key`
+  pub fn set_metadata(&mut self, key: &str, value: MetaValue) {
+    // Frames only carry a handful of values so, a linear scan beats hashing here
+    match self.metadata.iter_mut().find(|(k, _)| &**k == key) {
+      Some((_, existing)) => *existing = value,
+      None => self.metadata.push((key.into(), value)),
+    }
+  }
+
+  /// Get a metadata value attached to the frame
+  pub fn metadata(&self, key: &str) -> Option<&MetaValue> {
+    self
+      .metadata
+      .iter()
+      .find(|(k, _)| &**k == key)
+      .map(|(_, value)| value)
+  }
+
+  /// Get the pixels of a single row
+  pub fn row(&self, y: usize) -> &[u8] {
+    let stride = self.stride();
+    &self.data[y * stride..(y + 1) * stride]
+  }
+
+  /// Place frames on a single frame at their positions
+  ///
+  /// # Arguments
+  /// * `frames` - Frames with the position of their top-left corner, which may be negative
+  ///
+  /// # Notes
+  /// The result covers the bounding box of every frame and its origin is the top-left
+  /// corner of that box.  Pixels no frame covers are black, opaque for formats with an 8
+  /// bit alpha channel.  Every frame must share the same format.
+  pub fn stitch(frames: &[((i32, i32), OwnedFrame)]) -> anyhow::Result<Self> {
+    let rects = frames.iter().map(|&((x, y), ref frame)| {
+      DirtyRect::from_ltrb(x, y, x + frame.width() as i32, y + frame.height() as i32)
+    });
+    let bounds = match rects.reduce(|a, b| {
+      DirtyRect::from_ltrb(
+        a.left.min(b.left),
+        a.top.min(b.top),
+        a.right.max(b.right),
+        a.bottom.max(b.bottom),
+      )
+    }) {
+      Some(bounds) => bounds,
+      None => anyhow::bail!("No frames to stitch"),
+    };
+
+    let format = frames[0].1.format();
+    anyhow::ensure!(
+      frames.iter().all(|(_, frame)| frame.format() == format),
+      "Frames to stitch must share the same format"
+    );
+
+    let (width, height) = (bounds.width() as usize, bounds.height() as usize);
+    let bpp = format.bytes_per_pixel();
+    let black = match format {
+      FrameFormat::B8G8R8A8 | FrameFormat::R8G8B8A8 => vec![0, 0, 0, 255],
+      _ => vec![0; bpp],
+    };
+
+    let mut data = alloc::allocate(width * height * bpp);
+    for _ in 0..width * height {
+      data.extend_from_slice(&black);
+    }
+
+    let stride = width * bpp;
+    for &((x, y), ref frame) in frames {
+      let left = (x - bounds.left) as usize * bpp;
+      let top = (y - bounds.top) as usize;
+
+      for row in 0..frame.height() {
+        let start = (top + row) * stride + left;
+        data[start..start + frame.stride()].copy_from_slice(frame.row(row));
+      }
+    }
+
+    Ok(Self::new(width, height, format, data))
+  }
+}
+
+/// Summarizes the frame rather than printing megabytes of pixels
+impl fmt::Debug for OwnedFrame {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("OwnedFrame")
+      .field("width", &self.width)
+      .field("height", &self.height)
+      .field("format", &self.format)
+      .field("stride", &self.stride())
+      .field("len", &self.data.len())
+      .field("dirty", &self.dirty.len())
+      .field("moved", &self.moved.len())
+      .field("sequence", &self.sequence)
+      .field("generation", &self.generation)
+      .field("metadata", &self.metadata)
+      .finish()
+  }
+}
+
+impl Drop for OwnedFrame {
+  fn drop(&mut self) {
+    // Hand the buffer back so pooling allocators can reuse it
+    if self.data.capacity() > 0 {
+      alloc::release(mem::take(&mut self.data));
+    }
+  }
+}
+
+impl<'a> Frame<'a> for &'a OwnedFrame {
+  fn width(&self) -> usize {
+    self.width
+  }
+
+  fn height(&self) -> usize {
+    self.height
+  }
+
+  fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+    Ok(self.dirty.clone())
+  }
+
+  fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+    Ok(self.moved.clone())
+  }
+
+  fn format(&self) -> FrameFormat {
+    self.format
+  }
+
+  fn as_bytes(&self) -> anyhow::Result<Cow<'a, [u8]>> {
+    let frame: &'a OwnedFrame = self;
+    Ok(Cow::from(frame.data.as_slice()))
+  }
+
+  fn stride(&self) -> usize {
+    OwnedFrame::stride(self)
+  }
+
+  fn sequence(&self) -> u64 {
+    self.sequence
+  }
+
+  fn generation(&self) -> u32 {
+    self.generation
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::OwnedFrame;
+  use crate::{DirtyRect, Frame, FrameFormat};
+
+  #[test]
+  fn test_debug_omits_pixels() {
+    let frame = OwnedFrame::new(1000, 1000, FrameFormat::Luma8, vec![7; 1000 * 1000]);
+    let debug = format!("{:?}", frame);
+
+    assert!(debug.contains("width: 1000"));
+    assert!(debug.contains("len: 1000000"));
+    assert!(debug.len() < 300);
+  }
+
+  #[test]
+  fn test_stitch() {
+    // Left of and below the origin with a gap above the second frame
+    let left = OwnedFrame::new(2, 1, FrameFormat::Luma8, vec![1, 2]);
+    let right = OwnedFrame::new(1, 2, FrameFormat::Luma8, vec![3, 4]);
+
+    let stitched = OwnedFrame::stitch(&[((-2, 0), left), ((0, 1), right)]).unwrap();
+    assert_eq!((stitched.width(), stitched.height()), (3, 3));
+    assert_eq!(stitched.data(), &[1, 2, 0, 0, 0, 3, 0, 0, 4]);
+
+    let bgra = OwnedFrame::new(1, 1, FrameFormat::B8G8R8A8, vec![9; 4]);
+    let stitched = OwnedFrame::stitch(&[((1, 0), bgra)]).unwrap();
+    assert_eq!(stitched.data(), &[9; 4]);
+
+    let luma = OwnedFrame::new(1, 1, FrameFormat::Luma8, vec![0]);
+    let bgra = OwnedFrame::new(1, 1, FrameFormat::B8G8R8A8, vec![0; 4]);
+    assert!(OwnedFrame::stitch(&[((0, 0), luma), ((1, 0), bgra)]).is_err());
+    assert!(OwnedFrame::stitch(&[]).is_err());
+  }
+
+  /// 3x2 `B8G8R8A8` gray frame
+  fn gray() -> OwnedFrame {
+    OwnedFrame::new(3, 2, FrameFormat::B8G8R8A8, [128, 128, 128, 255].repeat(6))
+  }
+
+  #[test]
+  fn test_region_is_color_uniform() {
+    let frame = gray();
+
+    assert!((&frame).region_is_color(
+      DirtyRect::new(0, 3, 2, 0),
+      [128, 128, 128, 255],
+      0
+    ));
+    assert!((&frame).region_is_color(
+      DirtyRect::new(0, 3, 2, 1),
+      [130, 126, 128, 255],
+      2
+    ));
+    assert!(!(&frame).region_is_color(
+      DirtyRect::new(0, 4, 2, 0),
+      [128, 128, 128, 255],
+      0
+    ));
+  }
+
+  #[test]
+  fn test_region_is_color_off_pixel() {
+    let mut frame = gray();
+    frame.data[12 + 8] = 200;
+
+    let gray = [128, 128, 128, 255];
+    assert!(!(&frame).region_is_color(DirtyRect::new(0, 3, 2, 0), gray, 8));
+    assert!((&frame).region_is_color(DirtyRect::new(0, 2, 2, 0), gray, 8));
+  }
+
+  #[cfg(feature = "bytes")]
+  #[test]
+  fn test_copy_to_bytes() {
+    use bytes::BytesMut;
+
+    let frame = OwnedFrame::new(2, 2, FrameFormat::Luma8, vec![1, 2, 3, 4]);
+    let mut dst = BytesMut::from(&b"header"[..]);
+
+    (&frame).copy_to_bytes(&mut dst).unwrap();
+
+    assert_eq!(dst.len(), 10);
+    assert_eq!(&dst[..], b"header\x01\x02\x03\x04");
+  }
+
+  #[cfg(feature = "image")]
+  #[test]
+  fn test_to_image() {
+    let frame =
+      OwnedFrame::new(2, 1, FrameFormat::B8G8R8A8, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    let image = (&frame).to_image().unwrap();
+
+    assert_eq!(image.dimensions(), (2, 1));
+    assert_eq!(image.into_raw(), vec![3, 2, 1, 4, 7, 6, 5, 8]);
+  }
+
+  #[test]
+  fn test_send_sync() {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    assert_send_sync::<OwnedFrame>();
+  }
+}
@@ -0,0 +1,345 @@
+//! Provides concurrent capture of multiple displays from separate threads
+
+use crate::{errors::FrameError, queue::FrameQueue, Display, DropPolicy, OwnedFrame};
+use std::{
+  sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+  },
+  thread::{self, JoinHandle},
+};
+
+/// A frame captured by one of the workers of a [`ParallelCapture`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedFrame {
+  /// The index of the display the frame was captured from
+  pub display: usize,
+  /// The captured frame
+  pub frame: OwnedFrame,
+}
+
+/// The error a worker of a [`ParallelCapture`] stopped on
+#[derive(Debug)]
+pub struct WorkerError {
+  /// The index of the display the worker captured
+  pub display: usize,
+  /// Why the display couldn't be opened or captured
+  pub error: anyhow::Error,
+}
+
+type Errors = Mutex<Vec<WorkerError>>;
+
+/// Captures multiple displays concurrently, one worker thread per display
+///
+/// # Notes
+/// Every worker opens its own display and therefore its own device, which lets displays
+/// capture truly in parallel at the cost of more VRAM than sharing a single device.
+/// Captured frames share a queue holding one frame per display, what happens when it's
+/// full is controlled by a [`DropPolicy`].  A worker that fails stops and reports its
+/// error outside of the queue so, errors are never dropped like frames, see
+/// [`ParallelCapture::errors`].  Workers are stopped and joined when the
+/// [`ParallelCapture`] is dropped.
+pub struct ParallelCapture {
+  stop: Arc<AtomicBool>,
+  frames: Arc<FrameQueue<TaggedFrame>>,
+  errors: Arc<Errors>,
+  workers: Vec<JoinHandle<()>>,
+}
+
+impl ParallelCapture {
+  /// Spawn a worker for every display opener
+  ///
+  /// # Arguments
+  /// * `openers` - Functions run on the worker thread to open a display, displays hold
+  ///   thread bound COM objects so they can't be opened up front and moved
+  /// * `policy` - What happens to frames when the consumer falls behind
+  pub fn spawn<F, D>(openers: Vec<F>, policy: DropPolicy) -> Self
+  where
+    F: FnOnce() -> anyhow::Result<D> + Send + 'static,
+    D: for<'a> Display<'a>,
+  {
+    let stop = Arc::new(AtomicBool::new(false));
+    let frames = Arc::new(FrameQueue::new(openers.len().max(1), policy));
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let running = Arc::new(AtomicUsize::new(openers.len()));
+
+    if openers.is_empty() {
+      frames.close();
+    }
+
+    let workers = openers
+      .into_iter()
+      .enumerate()
+      .map(|(idx, open)| {
+        let stop = stop.clone();
+        let frames = frames.clone();
+        let errors = errors.clone();
+        let running = running.clone();
+
+        thread::spawn(move || {
+          if let Err(error) = run_worker(idx, open, &stop, &frames) {
+            lock(&errors).push(WorkerError {
+              display: idx,
+              error,
+            });
+          }
+
+          // The last worker out lets the consumer know no more frames are coming
+          if running.fetch_sub(1, Ordering::SeqCst) == 1 {
+            frames.close();
+          }
+        })
+      })
+      .collect();
+
+    Self {
+      stop,
+      frames,
+      errors,
+      workers,
+    }
+  }
+
+  /// Spawn a worker for every DXGI display
+  ///
+  /// # Arguments
+  /// * `config` - The capture options of every display, including the drop policy
+  ///
+  /// # Notes
+  /// Each worker joins the multithreaded COM apartment for the lifetime of its thread.
+  #[cfg(target_os = "windows")]
+  pub fn dxgi(config: crate::CaptureConfig) -> anyhow::Result<Self> {
+    use crate::{
+      bindings::Windows::Win32::System::Com::{CoInitializeEx, COINIT_MULTITHREADED},
+      driver::dxgi::display::DxgiDisplays,
+    };
+
+    let policy = config.drop_policy();
+    let count = DxgiDisplays::new()?.count();
+    let openers = (0..count)
+      .map(|idx| {
+        let config = config.clone();
+
+        move || {
+          unsafe { CoInitializeEx(std::ptr::null_mut(), COINIT_MULTITHREADED).ok()? };
+
+          match DxgiDisplays::new()?.nth(idx) {
+            Some(display) => {
+              let mut display = display?;
+              display.set_config(config);
+
+              Ok(display)
+            }
+            None => anyhow::bail!("Display {} is no longer attached", idx),
+          }
+        }
+      })
+      .collect();
+
+    Ok(Self::spawn(openers, policy))
+  }
+
+  /// Pin every worker to a single logical processor
+  ///
+  /// # Arguments
+  /// * `core` - The index of the logical processor, starting at zero
+  ///
+  /// # Notes
+  /// Keeping capture on a dedicated core avoids jitter from threads migrating between
+  /// cores on busy systems.  This is only a hint, the scheduler may still run other
+  /// threads on the core.
+  #[cfg(target_os = "windows")]
+  pub fn set_affinity(&self, core: usize) -> anyhow::Result<()> {
+    use crate::bindings::Windows::Win32::{
+      Foundation::HANDLE, System::Threading::SetThreadAffinityMask,
+    };
+    use std::os::windows::io::AsRawHandle;
+
+    let mask = affinity_mask(core)?;
+
+    for worker in &self.workers {
+      let handle = HANDLE(worker.as_raw_handle() as isize);
+
+      if unsafe { SetThreadAffinityMask(handle, mask) } == 0 {
+        return Err(std::io::Error::last_os_error().into());
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Wait for the next frame from any display
+  ///
+  /// # Notes
+  /// Returns `None` once every worker has stopped, check [`ParallelCapture::errors`] for
+  /// why.
+  pub fn recv(&self) -> Option<TaggedFrame> {
+    self.frames.pop()
+  }
+
+  /// Get the next frame from any display without waiting
+  pub fn try_recv(&self) -> Option<TaggedFrame> {
+    self.frames.try_pop()
+  }
+
+  /// Take the errors workers stopped on since the last call
+  ///
+  /// # Notes
+  /// An error is recorded before its worker is counted as stopped so, once
+  /// [`ParallelCapture::recv`] returns `None` every error is available.
+  pub fn errors(&self) -> Vec<WorkerError> {
+    std::mem::take(&mut *lock(&self.errors))
+  }
+}
+
+impl Drop for ParallelCapture {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::SeqCst);
+
+    // Close the queue so no worker is left waiting for room
+    self.frames.close();
+
+    for worker in self.workers.drain(..) {
+      let _ = worker.join();
+    }
+  }
+}
+
+/// Get the affinity mask selecting only logical processor `core`
+#[cfg(target_os = "windows")]
+fn affinity_mask(core: usize) -> anyhow::Result<usize> {
+  let count = thread::available_parallelism().map_or(1, |count| count.get());
+
+  // A thread affinity mask can only address the processors of a single group
+  anyhow::ensure!(
+    core < count && core < usize::BITS as usize,
+    "Core {} is out of range, {} logical processors are available",
+    core,
+    count
+  );
+
+  Ok(1 << core)
+}
+
+/// Lock the errors, a poisoned lock only means a worker panicked while pushing
+fn lock(errors: &Errors) -> std::sync::MutexGuard<'_, Vec<WorkerError>> {
+  errors.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Capture frames from a single display until stopped, the queue is closed or capturing
+/// fails
+fn run_worker<F, D>(
+  idx: usize,
+  open: F,
+  stop: &AtomicBool,
+  frames: &FrameQueue<TaggedFrame>,
+) -> anyhow::Result<()>
+where
+  F: FnOnce() -> anyhow::Result<D>,
+  D: for<'a> Display<'a>,
+{
+  let mut display =
+    open().map_err(|err| err.context(format!("Failed to open display {}", idx)))?;
+
+  while !stop.load(Ordering::SeqCst) {
+    let frame = match display.frame() {
+      Ok(frame) => OwnedFrame::from_frame(&frame)?,
+      Err(FrameError::WouldBlock) => {
+        thread::yield_now();
+        continue;
+      }
+      Err(err) => return Err(err.into()),
+    };
+
+    if !frames.push(TaggedFrame {
+      display: idx,
+      frame,
+    }) {
+      break;
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ParallelCapture;
+  use crate::{
+    driver::mock::display::{MockDisplay, MockPattern},
+    DropPolicy,
+  };
+  use std::{collections::HashSet, time::Duration};
+
+  fn opener(value: u8) -> impl FnOnce() -> anyhow::Result<MockDisplay> + Send {
+    move || {
+      Ok(
+        MockDisplay::new(1, 1)
+          .with_pattern(MockPattern::Solid([value; 4]))
+          .with_latency(Duration::from_millis(1)),
+      )
+    }
+  }
+
+  #[test]
+  fn test_frames_from_every_display() {
+    let capture = ParallelCapture::spawn(vec![opener(0), opener(1)], DropPolicy::Block);
+    let mut seen = HashSet::new();
+
+    for _ in 0..100 {
+      let tagged = capture.recv().unwrap();
+      assert_eq!(tagged.frame.data(), &[tagged.display as u8; 4]);
+
+      seen.insert(tagged.display);
+      if seen.len() == 2 {
+        return;
+      }
+    }
+
+    panic!("Only received frames from {:?}", seen);
+  }
+
+  #[test]
+  fn test_open_failure_is_reported() {
+    let capture = ParallelCapture::spawn(
+      vec![|| -> anyhow::Result<MockDisplay> { anyhow::bail!("no display") }],
+      DropPolicy::default(),
+    );
+
+    assert!(capture.recv().is_none());
+
+    let errors = capture.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].display, 0);
+    assert!(capture.errors().is_empty());
+  }
+
+  #[test]
+  fn test_open_failure_is_not_dropped() {
+    let failing = || -> anyhow::Result<MockDisplay> { anyhow::bail!("no display") };
+    let openers: Vec<Box<dyn FnOnce() -> anyhow::Result<MockDisplay> + Send>> =
+      vec![Box::new(opener(0)), Box::new(failing)];
+    let capture = ParallelCapture::spawn(openers, DropPolicy::DropOldest);
+
+    // Frames of the working display keep evicting each other while the failure waits
+    for _ in 0..100 {
+      assert_eq!(capture.recv().unwrap().display, 0);
+
+      let errors = capture.errors();
+      if !errors.is_empty() {
+        return assert_eq!(errors[0].display, 1);
+      }
+    }
+
+    panic!("The open failure was never reported");
+  }
+
+  #[cfg(target_os = "windows")]
+  #[test]
+  fn test_set_affinity() {
+    let capture = ParallelCapture::spawn(vec![opener(0), opener(1)], DropPolicy::Block);
+
+    capture.set_affinity(0).unwrap();
+    assert!(capture.set_affinity(usize::MAX).is_err());
+  }
+}
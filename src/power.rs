@@ -0,0 +1,95 @@
+//! Provides a best effort guess at whether a captured display is powered
+
+use std::time::{Duration, Instant};
+
+/// Whether a display is powered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PowerState {
+  /// The display recently showed new content
+  On,
+  /// The display is no longer part of the desktop
+  Off,
+  /// Nothing was shown for a long time, the display is either asleep or idle
+  Unknown,
+}
+
+/// Guesses the [`PowerState`] of a display from how recently it showed new content
+///
+/// # Notes
+/// Duplication keeps timing out on a sleeping monitor exactly like it does on an idle
+/// one, so a long idle display is reported as [`PowerState::Unknown`] rather than
+/// [`PowerState::Off`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerMonitor {
+  idle_after: Duration,
+  last_active: Option<Instant>,
+}
+
+impl PowerMonitor {
+  /// Create [`PowerMonitor`] treating displays idle for longer than `idle_after` as
+  /// possibly asleep
+  pub fn new(idle_after: Duration) -> Self {
+    Self {
+      idle_after,
+      last_active: None,
+    }
+  }
+
+  /// Record that the display showed new content, either a desktop or a pointer update
+  pub fn active(&mut self, at: Instant) {
+    self.last_active = Some(at);
+  }
+
+  /// Get the power state of the display at `now`
+  ///
+  /// # Arguments
+  /// * `now` - The time to evaluate idleness at
+  /// * `attached` - Whether the display is still part of the desktop
+  pub fn state(&self, now: Instant, attached: bool) -> PowerState {
+    match self.last_active {
+      _ if !attached => PowerState::Off,
+      Some(at) if now.saturating_duration_since(at) <= self.idle_after => PowerState::On,
+      _ => PowerState::Unknown,
+    }
+  }
+}
+
+impl Default for PowerMonitor {
+  fn default() -> Self {
+    // Longer than any sensible screen saver delay before monitors are put to sleep
+    Self::new(Duration::from_secs(60))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{PowerMonitor, PowerState};
+  use std::time::{Duration, Instant};
+
+  #[test]
+  fn test_long_idle_is_unknown() {
+    let start = Instant::now();
+    let mut monitor = PowerMonitor::new(Duration::from_secs(10));
+
+    assert_eq!(monitor.state(start, true), PowerState::Unknown);
+
+    monitor.active(start);
+    assert_eq!(
+      monitor.state(start + Duration::from_secs(5), true),
+      PowerState::On
+    );
+    assert_eq!(
+      monitor.state(start + Duration::from_secs(11), true),
+      PowerState::Unknown
+    );
+  }
+
+  #[test]
+  fn test_detached_is_off() {
+    let start = Instant::now();
+    let mut monitor = PowerMonitor::default();
+    monitor.active(start);
+
+    assert_eq!(monitor.state(start, false), PowerState::Off);
+  }
+}
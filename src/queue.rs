@@ -0,0 +1,211 @@
+//! Provides a bounded queue handing frames from capture threads to consumers
+
+use crate::DropPolicy;
+use std::{
+  collections::VecDeque,
+  sync::{Condvar, Mutex, MutexGuard},
+};
+
+/// A bounded, thread safe queue applying a [`DropPolicy`] when full
+///
+/// # Notes
+/// Once closed, pushes are rejected and pops drain the remaining items before returning
+/// `None`.
+#[derive(Debug)]
+pub struct FrameQueue<T> {
+  state: Mutex<State<T>>,
+  ready: Condvar,
+  space: Condvar,
+  capacity: usize,
+  policy: DropPolicy,
+}
+
+#[derive(Debug)]
+struct State<T> {
+  items: VecDeque<T>,
+  closed: bool,
+}
+
+impl<T> FrameQueue<T> {
+  /// Create [`FrameQueue`] holding at most `capacity` items
+  pub fn new(capacity: usize, policy: DropPolicy) -> Self {
+    assert!(
+      capacity > 0,
+      "FrameQueue capacity must be greater than zero"
+    );
+
+    Self {
+      state: Mutex::new(State {
+        items: VecDeque::with_capacity(capacity),
+        closed: false,
+      }),
+      ready: Condvar::new(),
+      space: Condvar::new(),
+      capacity,
+      policy,
+    }
+  }
+
+  /// The maximum number of items held
+  pub const fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// What happens to new items when full
+  pub const fn policy(&self) -> DropPolicy {
+    self.policy
+  }
+
+  /// The number of items currently held
+  pub fn len(&self) -> usize {
+    self.lock().items.len()
+  }
+
+  /// Whether no items are held
+  pub fn is_empty(&self) -> bool {
+    self.lock().items.is_empty()
+  }
+
+  /// Add an item, applying the drop policy when full
+  ///
+  /// # Notes
+  /// Returns `false` when the queue is closed, with [`DropPolicy::Block`] this waits until
+  /// there is room or the queue is closed.
+  pub fn push(&self, item: T) -> bool {
+    let mut state = self.lock();
+
+    while !state.closed && state.items.len() >= self.capacity {
+      match self.policy {
+        DropPolicy::DropOldest => {
+          state.items.pop_front();
+        }
+        DropPolicy::DropNewest => return true,
+        DropPolicy::Block => {
+          state = self
+            .space
+            .wait(state)
+            .unwrap_or_else(|err| err.into_inner())
+        }
+      }
+    }
+
+    if state.closed {
+      return false;
+    }
+
+    state.items.push_back(item);
+    self.ready.notify_one();
+
+    true
+  }
+
+  /// Wait for the next item
+  ///
+  /// # Notes
+  /// Returns `None` once the queue is closed and drained.
+  pub fn pop(&self) -> Option<T> {
+    let mut state = self.lock();
+
+    loop {
+      if let Some(item) = state.items.pop_front() {
+        self.space.notify_one();
+        return Some(item);
+      }
+
+      if state.closed {
+        return None;
+      }
+
+      state = self
+        .ready
+        .wait(state)
+        .unwrap_or_else(|err| err.into_inner());
+    }
+  }
+
+  /// Get the next item without waiting
+  pub fn try_pop(&self) -> Option<T> {
+    let item = self.lock().items.pop_front();
+    if item.is_some() {
+      self.space.notify_one();
+    }
+
+    item
+  }
+
+  /// Reject further pushes and wake everyone waiting on the queue
+  pub fn close(&self) {
+    self.lock().closed = true;
+    self.ready.notify_all();
+    self.space.notify_all();
+  }
+
+  fn lock(&self) -> MutexGuard<'_, State<T>> {
+    // Items are only moved in and out under the lock so a poisoned queue is still
+    // consistent
+    self.state.lock().unwrap_or_else(|err| err.into_inner())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::FrameQueue;
+  use crate::DropPolicy;
+  use std::{sync::Arc, thread, time::Duration};
+
+  /// Push `0..10` as fast as possible while a consumer takes an item every millisecond
+  fn slow_consumer(policy: DropPolicy) -> Vec<u32> {
+    let queue = Arc::new(FrameQueue::new(2, policy));
+    let producer = {
+      let queue = queue.clone();
+      thread::spawn(move || {
+        for i in 0..10 {
+          queue.push(i);
+        }
+
+        queue.close();
+      })
+    };
+
+    // Give the producer a head start so the queue fills up
+    thread::sleep(Duration::from_millis(20));
+
+    let mut received = Vec::new();
+    while let Some(i) = queue.pop() {
+      received.push(i);
+      thread::sleep(Duration::from_millis(1));
+    }
+
+    producer.join().unwrap();
+    received
+  }
+
+  #[test]
+  fn test_drop_oldest_keeps_latest() {
+    assert_eq!(slow_consumer(DropPolicy::DropOldest), vec![8, 9]);
+  }
+
+  #[test]
+  fn test_drop_newest_keeps_earliest() {
+    assert_eq!(slow_consumer(DropPolicy::DropNewest), vec![0, 1]);
+  }
+
+  #[test]
+  fn test_block_loses_nothing() {
+    assert_eq!(
+      slow_consumer(DropPolicy::Block),
+      (0..10).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn test_closed_queue_rejects_push() {
+    let queue = FrameQueue::new(1, DropPolicy::Block);
+    assert!(queue.push(1));
+
+    queue.close();
+    assert!(!queue.push(2));
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), None);
+  }
+}
@@ -0,0 +1,264 @@
+//! Provides capturing a display for a fixed amount of time
+
+use crate::{errors::FrameError, Display, Frame};
+use std::{
+  ops::ControlFlow,
+  thread,
+  time::{Duration, Instant},
+};
+
+/// What happened during a [`RecordingSession`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CaptureStats {
+  /// Frames handed to the callback
+  pub frames: u64,
+  /// Frames skipped because the desktop image didn't change
+  pub repeats: u64,
+  /// Frames skipped because they arrived sooner than the minimum interval
+  pub skipped: u64,
+  /// Times no frame was ready before the display timed out
+  pub timeouts: u64,
+  /// How long the session ran for
+  pub elapsed: Duration,
+}
+
+impl CaptureStats {
+  /// The average number of frames handed to the callback per second
+  pub fn fps(&self) -> f64 {
+    match self.elapsed.as_secs_f64() {
+      secs if secs > 0.0 => self.frames as f64 / secs,
+      _ => 0.0,
+    }
+  }
+}
+
+/// Captures a display for a fixed amount of time, handing every new frame to a callback
+///
+/// # Notes
+/// Repeated frames are never handed to the callback.  How long a single frame request
+/// waits is up to the display, e.g. [`crate::CaptureConfig::with_timeout`] for DXGI
+/// displays, so a session may overrun its duration by up to one timeout.
+pub struct RecordingSession<D> {
+  display: D,
+  duration: Duration,
+  interval: Duration,
+}
+
+impl<D> RecordingSession<D>
+where
+  D: for<'a> Display<'a>,
+{
+  /// Create [`RecordingSession`] capturing `display` for ten seconds
+  pub fn new(display: D) -> Self {
+    Self {
+      display,
+      duration: Duration::from_secs(10),
+      interval: Duration::ZERO,
+    }
+  }
+
+  /// Capture for `duration`
+  pub fn with_duration(mut self, duration: Duration) -> Self {
+    self.duration = duration;
+    self
+  }
+
+  /// Skip frames arriving sooner than `interval` after the last delivered frame
+  ///
+  /// # Notes
+  /// Zero (the default) delivers every new frame, e.g. `1/30s` caps delivery at 30fps.
+  pub fn with_interval(mut self, interval: Duration) -> Self {
+    self.interval = interval;
+    self
+  }
+
+  /// The amount of time frames are captured for
+  pub const fn duration(&self) -> Duration {
+    self.duration
+  }
+
+  /// The minimum amount of time between delivered frames
+  pub const fn interval(&self) -> Duration {
+    self.interval
+  }
+
+  /// Capture until the duration elapses or `on_frame` returns [`ControlFlow::Break`]
+  ///
+  /// # Arguments
+  /// * `on_frame` - Called with every delivered frame
+  ///
+  /// # Notes
+  /// Stops at the first capture error other than a timeout.
+  pub fn run<F>(mut self, mut on_frame: F) -> anyhow::Result<CaptureStats>
+  where
+    F: FnMut(&dyn Frame<'_>) -> ControlFlow<()>,
+  {
+    let (duration, interval) = (self.duration, self.interval);
+    let start = Instant::now();
+    let mut last = None;
+    let mut stats = CaptureStats::default();
+
+    while start.elapsed() < duration {
+      let frame = match self.display.frame() {
+        Ok(frame) => frame,
+        Err(FrameError::WouldBlock) => {
+          stats.timeouts += 1;
+          continue;
+        }
+        Err(err) => return Err(err.into()),
+      };
+
+      if frame.is_repeat() {
+        stats.repeats += 1;
+        continue;
+      }
+
+      let now = Instant::now();
+      if last.is_some_and(|last| now - last < interval) {
+        stats.skipped += 1;
+        continue;
+      }
+
+      last = Some(now);
+      stats.frames += 1;
+
+      if on_frame(&frame).is_break() {
+        break;
+      }
+    }
+
+    stats.elapsed = start.elapsed();
+
+    Ok(stats)
+  }
+}
+
+/// Capture frames at up to `fps` frames per second until `on_frame` returns
+/// [`ControlFlow::Break`]
+///
+/// # Arguments
+/// * `display` - The display to capture
+/// * `fps` - The target frame rate, must be greater than zero
+/// * `on_frame` - Called with every captured frame, including repeats
+///
+/// # Notes
+/// After each frame the rest of its budget is slept away, as is the remaining budget when
+/// no frame was ready.  A slow callback delays the following frames rather than having
+/// them captured in a burst to catch up.  Stops at the first capture error other than a
+/// timeout.
+pub fn capture_loop<D, F>(
+  display: &mut D,
+  fps: f64,
+  mut on_frame: F,
+) -> anyhow::Result<CaptureStats>
+where
+  D: for<'a> Display<'a>,
+  F: FnMut(&dyn Frame<'_>) -> ControlFlow<()>,
+{
+  anyhow::ensure!(fps > 0.0, "Target frame rate {} must be positive", fps);
+
+  let budget = Duration::from_secs_f64(1.0 / fps);
+  let start = Instant::now();
+  let mut deadline = start;
+  let mut stats = CaptureStats::default();
+
+  loop {
+    match display.frame() {
+      Ok(frame) => {
+        stats.frames += 1;
+
+        if on_frame(&frame).is_break() {
+          break;
+        }
+
+        deadline = (deadline + budget).max(Instant::now());
+      }
+      Err(FrameError::WouldBlock) => stats.timeouts += 1,
+      Err(err) => return Err(err.into()),
+    }
+
+    thread::sleep(deadline.saturating_duration_since(Instant::now()));
+  }
+
+  stats.elapsed = start.elapsed();
+
+  Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{capture_loop, RecordingSession};
+  use crate::driver::mock::display::MockDisplay;
+  use std::{ops::ControlFlow, time::Duration};
+
+  /// Produces a frame every millisecond, timing out on every fourth request
+  fn display() -> MockDisplay {
+    MockDisplay::new(1, 1)
+      .with_latency(Duration::from_millis(1))
+      .with_timeout_every(4)
+  }
+
+  #[test]
+  fn test_session_runs_for_duration() {
+    let mut delivered = 0;
+    let stats = RecordingSession::new(display())
+      .with_duration(Duration::from_millis(200))
+      .run(|frame| {
+        assert_eq!(frame.width(), 1);
+        delivered += 1;
+        ControlFlow::Continue(())
+      })
+      .unwrap();
+
+    assert!(stats.frames > 0);
+    assert!(stats.timeouts > 0);
+    assert_eq!(stats.frames, delivered);
+    assert!(stats.elapsed >= Duration::from_millis(200));
+    assert!(stats.elapsed < Duration::from_secs(2));
+    assert!(stats.fps() > 0.0);
+  }
+
+  #[test]
+  fn test_session_stops_early() {
+    let stats = RecordingSession::new(display())
+      .with_duration(Duration::from_secs(10))
+      .run(|_| ControlFlow::Break(()))
+      .unwrap();
+
+    assert_eq!(stats.frames, 1);
+    assert!(stats.elapsed < Duration::from_secs(10));
+  }
+
+  #[test]
+  fn test_session_interval_skips_frames() {
+    let stats = RecordingSession::new(display())
+      .with_duration(Duration::from_millis(100))
+      .with_interval(Duration::from_millis(20))
+      .run(|_| ControlFlow::Continue(()))
+      .unwrap();
+
+    assert!(stats.skipped > 0);
+    assert!(stats.frames <= 6);
+  }
+
+  #[test]
+  fn test_capture_loop_paces_frames() {
+    let mut display = display();
+    let mut delivered = 0;
+
+    let stats = capture_loop(&mut display, 100.0, |_| {
+      delivered += 1;
+
+      match delivered {
+        5 => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+      }
+    })
+    .unwrap();
+
+    assert_eq!(stats.frames, 5);
+    // Four full budgets pass between the first and fifth frame
+    assert!(stats.elapsed >= Duration::from_millis(40));
+    assert!(capture_loop(&mut display, 0.0, |_| ControlFlow::Break(())).is_err());
+  }
+}
@@ -0,0 +1,126 @@
+//! Provides a fixed size buffer of recently captured frames
+
+use crate::OwnedFrame;
+use std::collections::{vec_deque, VecDeque};
+
+/// A fixed capacity buffer that evicts the oldest frame when full
+#[derive(Debug, Clone)]
+pub struct FrameRing {
+  frames: VecDeque<OwnedFrame>,
+  capacity: usize,
+}
+
+impl FrameRing {
+  /// Create [`FrameRing`] holding at most `capacity` frames
+  pub fn new(capacity: usize) -> Self {
+    assert!(capacity > 0, "FrameRing capacity must be greater than zero");
+
+    Self {
+      frames: VecDeque::with_capacity(capacity),
+      capacity,
+    }
+  }
+
+  /// The maximum number of frames held
+  pub const fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// The number of frames currently held
+  pub fn len(&self) -> usize {
+    self.frames.len()
+  }
+
+  /// Whether no frames are held
+  pub fn is_empty(&self) -> bool {
+    self.frames.is_empty()
+  }
+
+  /// Add a frame, returning the oldest frame if it had to be evicted
+  pub fn push(&mut self, frame: OwnedFrame) -> Option<OwnedFrame> {
+    let evicted = if self.frames.len() == self.capacity {
+      self.frames.pop_front()
+    } else {
+      None
+    };
+
+    self.frames.push_back(frame);
+    evicted
+  }
+
+  /// Remove and return the oldest frame
+  pub fn pop(&mut self) -> Option<OwnedFrame> {
+    self.frames.pop_front()
+  }
+
+  /// Get the most recently added frame
+  pub fn latest(&self) -> Option<&OwnedFrame> {
+    self.frames.back()
+  }
+
+  /// Iterate over frames from oldest to newest
+  pub fn iter(&self) -> vec_deque::Iter<'_, OwnedFrame> {
+    self.frames.iter()
+  }
+
+  /// Remove all frames
+  pub fn clear(&mut self) {
+    self.frames.clear();
+  }
+}
+
+impl<'a> IntoIterator for &'a FrameRing {
+  type Item = &'a OwnedFrame;
+  type IntoIter = vec_deque::Iter<'a, OwnedFrame>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::FrameRing;
+  use crate::{FrameFormat, MetaValue, OwnedFrame};
+
+  fn frame(sequence: u64) -> OwnedFrame {
+    OwnedFrame::new(1, 1, FrameFormat::Luma8, vec![0])
+      .with_metadata("sequence", MetaValue::UInt(sequence))
+      .with_metadata("monitor", MetaValue::Text("DISPLAY1".into()))
+  }
+
+  #[test]
+  fn test_metadata_through_ring() {
+    let mut ring = FrameRing::new(2);
+
+    assert!(ring.push(frame(0)).is_none());
+    assert!(ring.push(frame(1)).is_none());
+
+    let evicted = ring.push(frame(2)).unwrap();
+    assert_eq!(evicted.metadata("sequence"), Some(&MetaValue::UInt(0)));
+
+    let sequences = ring
+      .iter()
+      .map(|frame| frame.metadata("sequence").cloned())
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      sequences,
+      vec![Some(MetaValue::UInt(1)), Some(MetaValue::UInt(2))]
+    );
+
+    let latest = ring.latest().unwrap();
+    assert_eq!(
+      latest.metadata("monitor"),
+      Some(&MetaValue::Text("DISPLAY1".into()))
+    );
+    assert_eq!(latest.metadata("missing"), None);
+  }
+
+  #[test]
+  fn test_metadata_replaces_existing_key() {
+    let frame = frame(0).with_metadata("sequence", MetaValue::UInt(7));
+
+    assert_eq!(frame.metadata("sequence"), Some(&MetaValue::UInt(7)));
+  }
+}
@@ -0,0 +1,281 @@
+//! Provides an asynchronous stream of frames captured on a blocking thread
+
+use crate::{errors::FrameError, queue::FrameQueue, Display, DropPolicy, OwnedFrame};
+use futures_core::Stream;
+use std::{
+  pin::Pin,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  task::{Context, Poll, Waker},
+  thread,
+  time::Duration,
+};
+
+/// Frames captured by a display on a blocking thread of the Tokio runtime
+///
+/// # Notes
+/// A single frame is queued, the [`DropPolicy`] decides what happens to the frames
+/// captured while a slow consumer is busy.  The stream ends after the first error and the
+/// capture thread is stopped when the stream is dropped.
+pub struct FrameStream {
+  shared: Arc<Shared>,
+}
+
+/// State shared between the stream and its capture thread
+struct Shared {
+  frames: FrameQueue<anyhow::Result<OwnedFrame>>,
+  waker: Mutex<Option<Waker>>,
+  stop: AtomicBool,
+  done: AtomicBool,
+}
+
+impl Shared {
+  fn wake(&self) {
+    let waker = self
+      .waker
+      .lock()
+      .unwrap_or_else(|err| err.into_inner())
+      .take();
+
+    if let Some(waker) = waker {
+      waker.wake();
+    }
+  }
+}
+
+impl FrameStream {
+  /// Open a display on a blocking thread and stream its frames
+  ///
+  /// # Arguments
+  /// * `open` - Opens the display on the blocking thread, displays hold thread bound COM
+  ///   objects so they can't be opened up front and moved
+  /// * `policy` - What happens to frames when the consumer falls behind
+  ///
+  /// # Notes
+  /// Must be called from within a Tokio runtime.
+  pub fn spawn<F, D>(open: F, policy: DropPolicy) -> Self
+  where
+    F: FnOnce() -> anyhow::Result<D> + Send + 'static,
+    D: for<'a> Display<'a>,
+  {
+    let shared = Arc::new(Shared {
+      frames: FrameQueue::new(1, policy),
+      waker: Mutex::new(None),
+      stop: AtomicBool::new(false),
+      done: AtomicBool::new(false),
+    });
+
+    let worker = shared.clone();
+    tokio::task::spawn_blocking(move || {
+      run_worker(open, &worker);
+
+      worker.done.store(true, Ordering::SeqCst);
+      worker.wake();
+    });
+
+    Self { shared }
+  }
+
+  /// Stream the frames of the primary DXGI display
+  ///
+  /// # Arguments
+  /// * `config` - The capture options of the display
+  ///
+  /// # Notes
+  /// The blocking thread joins the multithreaded COM apartment.  Must be called from
+  /// within a Tokio runtime.
+  #[cfg(target_os = "windows")]
+  pub fn dxgi(config: crate::CaptureConfig) -> Self {
+    use crate::bindings::Windows::Win32::System::Com::{
+      CoInitializeEx, COINIT_MULTITHREADED,
+    };
+    use crate::driver::dxgi::display::DxgiDisplays;
+
+    let policy = config.drop_policy();
+
+    Self::spawn(
+      move || {
+        unsafe { CoInitializeEx(std::ptr::null_mut(), COINIT_MULTITHREADED).ok()? };
+
+        let mut display = DxgiDisplays::new()?
+          .primary()?
+          .ok_or_else(|| anyhow::anyhow!("No primary display attached"))?;
+        display.set_config(config);
+
+        Ok(display)
+      },
+      policy,
+    )
+  }
+}
+
+impl Stream for FrameStream {
+  type Item = anyhow::Result<OwnedFrame>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    if let Some(frame) = self.shared.frames.try_pop() {
+      return Poll::Ready(Some(frame));
+    }
+
+    *self
+      .shared
+      .waker
+      .lock()
+      .unwrap_or_else(|err| err.into_inner()) = Some(cx.waker().clone());
+
+    // Check again in case a frame arrived, or the worker stopped, before the waker was
+    // stored.  The worker pushes its last frame before it's done so, reading `done` first
+    // never misses that frame.
+    let done = self.shared.done.load(Ordering::SeqCst);
+
+    match self.shared.frames.try_pop() {
+      Some(frame) => Poll::Ready(Some(frame)),
+      None if done => Poll::Ready(None),
+      None => Poll::Pending,
+    }
+  }
+}
+
+impl Drop for FrameStream {
+  fn drop(&mut self) {
+    self.shared.stop.store(true, Ordering::SeqCst);
+    self.shared.frames.close();
+  }
+}
+
+/// Capture frames until stopped, an error occurs or the stream is dropped
+fn run_worker<F, D>(open: F, shared: &Shared)
+where
+  F: FnOnce() -> anyhow::Result<D>,
+  D: for<'a> Display<'a>,
+{
+  let mut display = match open() {
+    Ok(display) => display,
+    Err(err) => {
+      shared
+        .frames
+        .push(Err(err.context("Failed to open display")));
+      return;
+    }
+  };
+
+  // Displays may answer without waiting, e.g. with a zero timeout or skipped repeats so,
+  // the thread sleeps longer the longer no frame is ready, up to about a refresh interval
+  const MIN_BACKOFF: Duration = Duration::from_millis(1);
+  const MAX_BACKOFF: Duration = Duration::from_millis(16);
+
+  let mut backoff = MIN_BACKOFF;
+
+  while !shared.stop.load(Ordering::SeqCst) {
+    let frame = match display.frame() {
+      Ok(frame) => OwnedFrame::from_frame(&frame),
+      Err(FrameError::WouldBlock) => {
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        continue;
+      }
+      Err(err) => Err(err.into()),
+    };
+
+    backoff = MIN_BACKOFF;
+
+    let failed = frame.is_err();
+    let pushed = shared.frames.push(frame);
+    shared.wake();
+
+    if !pushed || failed {
+      return;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::FrameStream;
+  use crate::{
+    driver::mock::display::{MockDisplay, MockPattern},
+    DropPolicy, OwnedFrame,
+  };
+  use futures_core::Stream;
+  use std::{future, pin::Pin, thread, time::Duration};
+
+  async fn next(stream: &mut FrameStream) -> Option<anyhow::Result<OwnedFrame>> {
+    future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+  }
+
+  fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+      .build()
+      .unwrap()
+  }
+
+  #[test]
+  fn test_stream_frames() {
+    runtime().block_on(async {
+      let mut stream = FrameStream::spawn(
+        || {
+          Ok(
+            MockDisplay::new(1, 1)
+              .with_pattern(MockPattern::Solid([7; 4]))
+              .with_latency(Duration::from_millis(1)),
+          )
+        },
+        DropPolicy::DropOldest,
+      );
+
+      for _ in 0..3 {
+        assert_eq!(next(&mut stream).await.unwrap().unwrap().data(), &[7; 4]);
+      }
+    });
+  }
+
+  #[test]
+  fn test_stream_waits_for_frames() {
+    runtime().block_on(async {
+      // Every other request times out without waiting
+      let mut stream = FrameStream::spawn(
+        || Ok(MockDisplay::new(1, 1).with_timeout_every(2)),
+        DropPolicy::DropOldest,
+      );
+
+      for _ in 0..3 {
+        assert!(next(&mut stream).await.unwrap().is_ok());
+      }
+    });
+  }
+
+  #[test]
+  fn test_stream_ends_after_error() {
+    runtime().block_on(async {
+      let mut stream = FrameStream::spawn(
+        || -> anyhow::Result<MockDisplay> { anyhow::bail!("denied") },
+        DropPolicy::DropOldest,
+      );
+
+      assert!(next(&mut stream).await.unwrap().is_err());
+      assert!(next(&mut stream).await.is_none());
+    });
+  }
+
+  #[test]
+  fn test_stream_block_keeps_every_frame() {
+    runtime().block_on(async {
+      let mut stream = FrameStream::spawn(
+        || Ok(MockDisplay::new(1, 1).with_idle_after(4)),
+        DropPolicy::Block,
+      );
+
+      // The display captures faster than the frames are consumed so, any dropped frame
+      // would show up as a gap in the sequence
+      for sequence in 0..4 {
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(
+          next(&mut stream).await.unwrap().unwrap().sequence(),
+          sequence
+        );
+      }
+    });
+  }
+}
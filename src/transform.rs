@@ -0,0 +1,557 @@
+//! Provides composable processing steps that can be applied to captured frames
+
+use crate::{
+  alloc,
+  convert::{self, Luma},
+  DirtyRect, Frame, FrameFormat, OwnedFrame,
+};
+
+/// A processing step that produces a new frame from a captured one
+pub trait FrameTransform {
+  /// Apply the transform to `frame`
+  fn apply(&self, frame: &dyn Frame<'_>) -> anyhow::Result<OwnedFrame>;
+}
+
+/// How pixels are combined when a frame is resized
+///
+/// # Notes
+/// Ordered from fastest to best looking when downscaling, [`Filter::Area`] is the best
+/// choice for large reductions and [`Filter::Lanczos3`] for small ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Filter {
+  /// Copy the closest source pixel, by far the fastest but text and thin lines shimmer
+  /// or vanish when downscaling
+  #[default]
+  Nearest,
+  /// Blend neighbouring pixels with a tent widened to the scale factor, cheap and
+  /// smooth but slightly soft
+  Bilinear,
+  /// Windowed sinc over three lobes, the sharpest result and roughly three times the
+  /// cost of [`Filter::Bilinear`]
+  Lanczos3,
+  /// Average the source pixels covered by each output pixel, exact for downscaling
+  /// and no sharper than [`Filter::Nearest`] when upscaling
+  Area,
+}
+
+impl Filter {
+  /// Get the source pixels and their weights contributing to each output pixel along
+  /// one axis
+  fn weights(self, src: usize, dst: usize) -> Vec<(usize, Vec<f32>)> {
+    let scale = src as f32 / dst as f32;
+
+    (0..dst)
+      .map(|i| {
+        let (start, mut weights) = match self {
+          Self::Nearest => (i * src / dst, vec![1.0]),
+          Self::Area => {
+            let (left, right) = (i as f32 * scale, (i + 1) as f32 * scale);
+            let start = left.floor() as usize;
+            let end = (right.ceil() as usize).min(src);
+            let weights = (start..end)
+              .map(|j| right.min(j as f32 + 1.0) - left.max(j as f32))
+              .collect();
+
+            (start, weights)
+          }
+          Self::Bilinear | Self::Lanczos3 => {
+            // Widen the kernel when downscaling so every source pixel contributes
+            let stretch = scale.max(1.0);
+            let support = self.radius() * stretch;
+            let center = (i as f32 + 0.5) * scale;
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(src);
+            let weights = (start..end)
+              .map(|j| self.kernel((j as f32 + 0.5 - center) / stretch))
+              .collect();
+
+            (start, weights)
+          }
+        };
+
+        let sum: f32 = weights.iter().sum();
+        match sum.abs() > f32::EPSILON {
+          true => weights.iter_mut().for_each(|weight| *weight /= sum),
+          false => weights = vec![1.0],
+        }
+
+        (start.min(src - 1), weights)
+      })
+      .collect()
+  }
+
+  /// The distance from the center past which the kernel is zero
+  fn radius(self) -> f32 {
+    match self {
+      Self::Lanczos3 => 3.0,
+      _ => 1.0,
+    }
+  }
+
+  /// The weight of a source pixel `x` pixels from the center
+  fn kernel(self, x: f32) -> f32 {
+    let x = x.abs();
+
+    match self {
+      Self::Bilinear => (1.0 - x).max(0.0),
+      Self::Lanczos3 if x < f32::EPSILON => 1.0,
+      Self::Lanczos3 if x < 3.0 => {
+        let px = std::f32::consts::PI * x;
+        3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+      }
+      _ => 0.0,
+    }
+  }
+}
+
+/// Resizes a frame, using nearest neighbour sampling unless another [`Filter`] is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scale {
+  pub width: usize,
+  pub height: usize,
+  pub filter: Filter,
+}
+
+impl Scale {
+  pub fn new(width: usize, height: usize) -> Self {
+    Self {
+      width,
+      height,
+      filter: Filter::default(),
+    }
+  }
+
+  /// Resize with `filter`, see [`Filter`] for the speed and quality of each
+  pub fn with_filter(mut self, filter: Filter) -> Self {
+    self.filter = filter;
+    self
+  }
+}
+
+impl FrameTransform for Scale {
+  fn apply(&self, frame: &dyn Frame<'_>) -> anyhow::Result<OwnedFrame> {
+    let src = OwnedFrame::from_frame(frame)?;
+    let bpp = src.format().bytes_per_pixel();
+
+    anyhow::ensure!(
+      src.width() > 0 && src.height() > 0,
+      "Cannot scale an empty frame"
+    );
+
+    if self.filter == Filter::Nearest {
+      let mut data = alloc::allocate(self.width * self.height * bpp);
+
+      for y in 0..self.height {
+        let row = src.row(y * src.height() / self.height);
+
+        for x in 0..self.width {
+          let x = x * src.width() / self.width * bpp;
+          data.extend_from_slice(&row[x..x + bpp]);
+        }
+      }
+
+      return Ok(OwnedFrame::new(self.width, self.height, src.format(), data));
+    }
+
+    // `Luma16` is the only format with samples wider than a byte that can be filtered
+    let (samples, max) = match src.format() {
+      format @ (FrameFormat::R10G10B10A2 | FrameFormat::R16G16B16A16Float) => {
+        anyhow::bail!(
+          "Cannot filter {:?}, only nearest scaling is supported",
+          format
+        )
+      }
+      FrameFormat::Luma16 => (
+        src
+          .data()
+          .chunks_exact(2)
+          .map(|px| u16::from_le_bytes([px[0], px[1]]) as f32)
+          .collect::<Vec<_>>(),
+        u16::MAX as f32,
+      ),
+      _ => (
+        src.data().iter().map(|&px| px as f32).collect(),
+        u8::MAX as f32,
+      ),
+    };
+
+    // Resample rows first and then columns, each pass only blends along one axis
+    let channels = samples.len() / (src.width() * src.height());
+    let horizontal = self.filter.weights(src.width(), self.width);
+    let vertical = self.filter.weights(src.height(), self.height);
+
+    let mut rows = Vec::with_capacity(self.width * src.height() * channels);
+    for row in samples.chunks_exact(src.width() * channels) {
+      for (start, weights) in &horizontal {
+        for c in 0..channels {
+          rows.push(blend(weights, |j| row[(start + j) * channels + c]));
+        }
+      }
+    }
+
+    let stride = self.width * channels;
+    let mut data = alloc::allocate(self.width * self.height * bpp);
+    for (start, weights) in &vertical {
+      for x in 0..stride {
+        let sample = blend(weights, |j| rows[(start + j) * stride + x])
+          .round()
+          .clamp(0.0, max);
+
+        match src.format() {
+          FrameFormat::Luma16 => data.extend_from_slice(&(sample as u16).to_le_bytes()),
+          _ => data.push(sample as u8),
+        }
+      }
+    }
+
+    Ok(OwnedFrame::new(self.width, self.height, src.format(), data))
+  }
+}
+
+/// Sum the samples returned by `sample` for each weight
+fn blend<F>(weights: &[f32], sample: F) -> f32
+where
+  F: Fn(usize) -> f32,
+{
+  weights
+    .iter()
+    .enumerate()
+    .map(|(j, weight)| weight * sample(j))
+    .sum()
+}
+
+/// Cuts a rectangle out of a frame, clamped to the frame bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+  pub x: usize,
+  pub y: usize,
+  pub width: usize,
+  pub height: usize,
+}
+
+impl Crop {
+  pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+    Self {
+      x,
+      y,
+      width,
+      height,
+    }
+  }
+
+  /// Clamp to the bounds of a `width` by `height` frame, `None` when nothing is left
+  pub fn clamp(&self, width: usize, height: usize) -> Option<Self> {
+    let clamped = Self::new(
+      self.x,
+      self.y,
+      self.width.min(width.saturating_sub(self.x)),
+      self.height.min(height.saturating_sub(self.y)),
+    );
+
+    (clamped.width > 0 && clamped.height > 0).then_some(clamped)
+  }
+
+  /// The cropped area as a rectangle of the source frame
+  pub fn rect(&self) -> DirtyRect {
+    DirtyRect::new(
+      self.y as i32,
+      (self.x + self.width) as i32,
+      (self.y + self.height) as i32,
+      self.x as i32,
+    )
+  }
+}
+
+impl FrameTransform for Crop {
+  fn apply(&self, frame: &dyn Frame<'_>) -> anyhow::Result<OwnedFrame> {
+    let src = OwnedFrame::from_frame(frame)?;
+    let bpp = src.format().bytes_per_pixel();
+
+    let crop = self.clamp(src.width(), src.height()).ok_or_else(|| {
+      anyhow::anyhow!(
+        "Crop {:?} is outside of {}x{} frame",
+        self,
+        src.width(),
+        src.height()
+      )
+    })?;
+
+    let mut data = alloc::allocate(crop.width * crop.height * bpp);
+
+    for y in crop.y..crop.y + crop.height {
+      data.extend_from_slice(&src.row(y)[crop.x * bpp..(crop.x + crop.width) * bpp]);
+    }
+
+    Ok(OwnedFrame::new(crop.width, crop.height, src.format(), data))
+  }
+}
+
+/// Reorders color channels into `R8G8B8A8`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SwizzleToRgba;
+
+impl FrameTransform for SwizzleToRgba {
+  fn apply(&self, frame: &dyn Frame<'_>) -> anyhow::Result<OwnedFrame> {
+    let src = OwnedFrame::from_frame(frame)?;
+
+    match src.format() {
+      FrameFormat::R8G8B8A8 => Ok(src),
+      FrameFormat::B8G8R8A8 => Ok(map_pixels(&src, FrameFormat::R8G8B8A8, |px, out| {
+        out.extend_from_slice(&[px[2], px[1], px[0], px[3]])
+      })),
+      format => anyhow::bail!("Cannot swizzle {:?} to RGBA", format),
+    }
+  }
+}
+
+/// Removes the alpha channel from a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DropAlpha;
+
+impl FrameTransform for DropAlpha {
+  fn apply(&self, frame: &dyn Frame<'_>) -> anyhow::Result<OwnedFrame> {
+    let src = OwnedFrame::from_frame(frame)?;
+    let format = match src.format() {
+      FrameFormat::B8G8R8A8 => FrameFormat::B8G8R8,
+      FrameFormat::R8G8B8A8 => FrameFormat::R8G8B8,
+      _ => return Ok(src),
+    };
+
+    Ok(map_pixels(&src, format, |px, out| {
+      out.extend_from_slice(&px[..3])
+    }))
+  }
+}
+
+/// Converts a frame into `Luma8` using Rec. 601 coefficients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Grayscale;
+
+impl FrameTransform for Grayscale {
+  fn apply(&self, frame: &dyn Frame<'_>) -> anyhow::Result<OwnedFrame> {
+    let src = OwnedFrame::from_frame(frame)?;
+    let (r, g, b) = match src.format() {
+      FrameFormat::Luma8 => return Ok(src),
+      // Keep the most significant byte of little endian samples
+      FrameFormat::Luma16 => (1, 1, 1),
+      FrameFormat::B8G8R8A8 | FrameFormat::B8G8R8 => (2, 1, 0),
+      FrameFormat::R8G8B8A8 | FrameFormat::R8G8B8 => (0, 1, 2),
+      FrameFormat::R10G10B10A2 => {
+        return Ok(map_pixels(&src, FrameFormat::Luma8, |px, out| {
+          let [r, g, b, _] = convert::unpack_r10g10b10a2(px);
+          out.push(Luma::Rec601.luma8(r, g, b))
+        }))
+      }
+      format @ FrameFormat::R16G16B16A16Float => {
+        anyhow::bail!(
+          "Cannot convert {:?} to grayscale without tonemapping",
+          format
+        )
+      }
+    };
+
+    Ok(map_pixels(&src, FrameFormat::Luma8, |px, out| {
+      out.push(Luma::Rec601.luma8(px[r], px[g], px[b]))
+    }))
+  }
+}
+
+/// A sequence of transforms applied one after another
+#[derive(Default)]
+pub struct Pipeline {
+  transforms: Vec<Box<dyn FrameTransform>>,
+}
+
+impl Pipeline {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Append a transform to the end of the pipeline
+  pub fn then<T>(mut self, transform: T) -> Self
+  where
+    T: FrameTransform + 'static,
+  {
+    self.transforms.push(Box::new(transform));
+    self
+  }
+}
+
+impl FrameTransform for Pipeline {
+  fn apply(&self, frame: &dyn Frame<'_>) -> anyhow::Result<OwnedFrame> {
+    let mut transforms = self.transforms.iter();
+    let mut owned = match transforms.next() {
+      Some(transform) => transform.apply(frame)?,
+      None => OwnedFrame::from_frame(frame)?,
+    };
+
+    for transform in transforms {
+      owned = transform.apply(&&owned)?;
+    }
+
+    Ok(owned)
+  }
+}
+
+/// Build a frame of `format` by mapping each pixel of `src`
+fn map_pixels<F>(src: &OwnedFrame, format: FrameFormat, mut map: F) -> OwnedFrame
+where
+  F: FnMut(&[u8], &mut Vec<u8>),
+{
+  let bpp = src.format().bytes_per_pixel();
+  let mut data = alloc::allocate(src.width() * src.height() * format.bytes_per_pixel());
+
+  for px in src.data().chunks_exact(bpp) {
+    map(px, &mut data);
+  }
+
+  OwnedFrame::new(src.width(), src.height(), format, data)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Crop, Filter, FrameTransform, Grayscale, Pipeline, Scale, SwizzleToRgba};
+  use crate::{errors::FrameError, DirtyRect, Frame, FrameFormat, MovedRect, OwnedFrame};
+  use std::borrow::Cow;
+
+  /// A 4x4 `B8G8R8A8` frame where each pixel is `[x, y, 0, 255]` with 8 bytes of row
+  /// padding
+  struct MockFrame(Vec<u8>);
+
+  impl MockFrame {
+    fn new() -> Self {
+      let mut buf = Vec::new();
+      for y in 0..4 {
+        for x in 0..4 {
+          buf.extend_from_slice(&[x, y, 0, 255]);
+        }
+        buf.extend_from_slice(&[0xAA; 8]);
+      }
+
+      Self(buf)
+    }
+  }
+
+  impl<'a> Frame<'a> for &'a MockFrame {
+    fn width(&self) -> usize {
+      4
+    }
+
+    fn height(&self) -> usize {
+      4
+    }
+
+    fn dirty(&self) -> Result<Vec<DirtyRect>, FrameError> {
+      Ok(Vec::new())
+    }
+
+    fn moved(&self) -> Result<Vec<MovedRect>, FrameError> {
+      Ok(Vec::new())
+    }
+
+    fn format(&self) -> FrameFormat {
+      FrameFormat::B8G8R8A8
+    }
+
+    fn stride(&self) -> usize {
+      24
+    }
+
+    fn as_bytes(&self) -> anyhow::Result<Cow<'a, [u8]>> {
+      let frame: &'a MockFrame = self;
+      Ok(Cow::from(frame.0.as_slice()))
+    }
+  }
+
+  #[test]
+  fn test_scale_crop_swizzle() {
+    let frame = MockFrame::new();
+    let pipeline = Pipeline::new()
+      .then(Scale::new(2, 2))
+      .then(Crop::new(1, 0, 1, 2))
+      .then(SwizzleToRgba);
+
+    let out = pipeline.apply(&&frame).unwrap();
+
+    assert_eq!(out.width(), 1);
+    assert_eq!(out.height(), 2);
+    assert_eq!(out.format(), FrameFormat::R8G8B8A8);
+    assert_eq!(out.data(), &[0, 0, 2, 255, 0, 2, 2, 255]);
+  }
+
+  #[test]
+  fn test_crop_clamps_to_bounds() {
+    let frame = MockFrame::new();
+    let out = Crop::new(3, 3, 10, 10).apply(&&frame).unwrap();
+
+    assert_eq!((out.width(), out.height()), (1, 1));
+    assert!(Crop::new(4, 0, 1, 1).apply(&&frame).is_err());
+  }
+
+  #[test]
+  fn test_crop_clamp_rect() {
+    let crop = Crop::new(2, 1, 10, 10).clamp(4, 3).unwrap();
+
+    assert_eq!(crop, Crop::new(2, 1, 2, 2));
+    assert_eq!(crop.rect(), DirtyRect::new(1, 4, 3, 2));
+    assert_eq!(Crop::new(4, 0, 1, 1).clamp(4, 3), None);
+  }
+
+  #[test]
+  fn test_grayscale() {
+    let frame = MockFrame::new();
+    let out = Grayscale.apply(&&frame).unwrap();
+
+    assert_eq!(out.format(), FrameFormat::Luma8);
+    assert_eq!(out.data().len(), 16);
+    assert_eq!(out.data()[0], 0);
+  }
+
+  #[test]
+  fn test_scale_filters_checkerboard() {
+    // A one pixel checkerboard averages to mid gray, which nearest sampling misses
+    let data = (0..8 * 8).map(|i| ((i + i / 8) % 2 * 255) as u8).collect();
+    let frame = OwnedFrame::new(8, 8, FrameFormat::Luma8, data);
+    let error = |filter| {
+      let out = Scale::new(4, 4).with_filter(filter).apply(&&frame).unwrap();
+      assert_eq!((out.width(), out.height()), (4, 4));
+
+      out
+        .data()
+        .iter()
+        .map(|&px| (px as i32 - 128).abs())
+        .max()
+        .unwrap()
+    };
+
+    assert_eq!(error(Filter::Nearest), 128);
+    assert_eq!(error(Filter::Area), 0);
+    assert!(error(Filter::Bilinear) < 32);
+    assert!(error(Filter::Lanczos3) < 32);
+  }
+
+  #[test]
+  fn test_scale_filters_keep_flat_color() {
+    let data = [10, 20, 30, 255].repeat(6 * 6);
+    let frame = OwnedFrame::new(6, 6, FrameFormat::B8G8R8A8, data);
+
+    for &filter in &[
+      Filter::Nearest,
+      Filter::Bilinear,
+      Filter::Lanczos3,
+      Filter::Area,
+    ] {
+      for &(width, height) in &[(2, 2), (4, 3), (9, 9)] {
+        let out = Scale::new(width, height)
+          .with_filter(filter)
+          .apply(&&frame)
+          .unwrap();
+
+        assert_eq!(
+          out.data(),
+          [10, 20, 30, 255].repeat(width * height).as_slice()
+        );
+      }
+    }
+  }
+}
@@ -0,0 +1,295 @@
+//! Provides a minimal WebSocket server streaming frames to browsers for live preview
+
+use crate::{errors::FrameError, Display, FrameFormat, OwnedFrame};
+use std::{
+  io::ErrorKind,
+  net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver, SyncSender, TrySendError},
+    Arc, Mutex,
+  },
+  thread::{self, JoinHandle},
+  time::Duration,
+};
+use tungstenite::{Bytes, Message};
+
+/// The size of the header preceding the pixels of every frame message
+pub const HEADER_LEN: usize = 9;
+
+/// How long a client may take to complete the handshake or accept a frame before it's
+/// disconnected
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Clients = Arc<Mutex<Vec<SyncSender<Bytes>>>>;
+
+/// Streams frames from a display to every connected WebSocket client
+///
+/// # Notes
+/// Every frame is sent as a single binary message made of a [`HEADER_LEN`] byte header
+/// (little endian `u32` width, little endian `u32` height and the format code, see
+/// [`format_code`]) followed by tightly packed pixels, ready to be drawn on a browser
+/// canvas.
+///
+/// A single capturer is shared by all clients and each message is shared between them
+/// rather than copied.  Each client has room for one pending frame, frames captured while
+/// a client is still busy sending are dropped for that client so a slow connection never
+/// stalls capture or the other clients.  Clients that take longer than five seconds to
+/// handshake or accept a frame are disconnected.
+pub struct WsPreviewServer {
+  addr: SocketAddr,
+  stop: Arc<AtomicBool>,
+  workers: Vec<JoinHandle<()>>,
+}
+
+impl WsPreviewServer {
+  /// Start serving frames of a display
+  ///
+  /// # Arguments
+  /// * `open` - Function run on the capture thread to open the display, displays hold
+  ///   thread bound COM objects so they can't be opened up front and moved
+  /// * `addr` - The address to listen on
+  pub fn serve<F, D, A>(open: F, addr: A) -> anyhow::Result<Self>
+  where
+    F: FnOnce() -> anyhow::Result<D> + Send + 'static,
+    D: for<'a> Display<'a>,
+    A: ToSocketAddrs,
+  {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+
+    // Poll for connections so the listener notices when it's asked to stop
+    listener.set_nonblocking(true)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let clients = Clients::default();
+    let workers = vec![
+      {
+        let stop = stop.clone();
+        let clients = clients.clone();
+        thread::spawn(move || run_listener(listener, &stop, &clients))
+      },
+      {
+        let stop = stop.clone();
+        thread::spawn(move || run_capture(open, &stop, &clients))
+      },
+    ];
+
+    Ok(Self {
+      addr,
+      stop,
+      workers,
+    })
+  }
+
+  /// The address the server is listening on
+  pub fn local_addr(&self) -> SocketAddr {
+    self.addr
+  }
+}
+
+impl Drop for WsPreviewServer {
+  /// Stop capturing and accepting clients, connected clients finish on their own threads
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::SeqCst);
+
+    for worker in self.workers.drain(..) {
+      let _ = worker.join();
+    }
+  }
+}
+
+/// Encode a frame into a preview message
+pub fn encode(frame: &OwnedFrame) -> Vec<u8> {
+  let mut message = Vec::with_capacity(HEADER_LEN + frame.data().len());
+
+  message.extend_from_slice(&(frame.width() as u32).to_le_bytes());
+  message.extend_from_slice(&(frame.height() as u32).to_le_bytes());
+  message.push(format_code(frame.format()));
+  message.extend_from_slice(frame.data());
+  message
+}
+
+/// Get the code identifying a pixel format in preview messages
+///
+/// # Notes
+/// Codes are part of the wire format so, they never change once assigned.
+pub const fn format_code(format: FrameFormat) -> u8 {
+  match format {
+    FrameFormat::B8G8R8A8 => 0,
+    FrameFormat::R8G8B8A8 => 1,
+    FrameFormat::B8G8R8 => 2,
+    FrameFormat::R8G8B8 => 3,
+    FrameFormat::Luma8 => 4,
+    FrameFormat::Luma16 => 5,
+    FrameFormat::R10G10B10A2 => 6,
+    FrameFormat::R16G16B16A16Float => 7,
+  }
+}
+
+/// Accept WebSocket clients until stopped
+///
+/// # Notes
+/// Client threads are detached, they end once capture stops or their timeouts expire so,
+/// a stuck client never holds up stopping the server.
+fn run_listener(listener: TcpListener, stop: &AtomicBool, clients: &Clients) {
+  while !stop.load(Ordering::SeqCst) {
+    match listener.accept() {
+      Ok((stream, _)) => {
+        // Only a single pending frame per client, anything more is dropped
+        let (tx, rx) = mpsc::sync_channel(1);
+        if let Ok(mut clients) = clients.lock() {
+          clients.push(tx);
+        }
+
+        thread::spawn(move || run_client(stream, rx));
+      }
+      Err(err) if err.kind() == ErrorKind::WouldBlock => {
+        thread::sleep(Duration::from_millis(10))
+      }
+      Err(_) => break,
+    }
+  }
+}
+
+/// Send frames to a single client until either side goes away
+fn run_client(stream: TcpStream, frames: Receiver<Bytes>) {
+  let configured = stream.set_nonblocking(false).is_ok()
+    && stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_ok()
+    && stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_ok();
+
+  if !configured {
+    return;
+  }
+
+  let mut socket = match tungstenite::accept(stream) {
+    Ok(socket) => socket,
+    Err(_) => return,
+  };
+
+  for frame in frames {
+    if socket.send(Message::Binary(frame)).is_err() {
+      return;
+    }
+  }
+
+  let _ = socket.close(None);
+}
+
+/// Capture frames and fan them out to every client until stopped
+fn run_capture<F, D>(open: F, stop: &AtomicBool, clients: &Clients)
+where
+  F: FnOnce() -> anyhow::Result<D>,
+  D: for<'a> Display<'a>,
+{
+  if let Ok(mut display) = open() {
+    while !stop.load(Ordering::SeqCst) {
+      let frame = match display.frame() {
+        Ok(frame) => OwnedFrame::from_frame(&frame),
+        Err(FrameError::WouldBlock) => {
+          thread::yield_now();
+          continue;
+        }
+        Err(_) => break,
+      };
+
+      let message = match frame {
+        Ok(frame) => Bytes::from(encode(&frame)),
+        Err(_) => break,
+      };
+
+      if let Ok(mut clients) = clients.lock() {
+        clients.retain(|client| match client.try_send(message.clone()) {
+          Ok(_) | Err(TrySendError::Full(_)) => true,
+          Err(TrySendError::Disconnected(_)) => false,
+        });
+      }
+    }
+  }
+
+  // Without frames there is nothing left to serve, stop accepting clients and drop the
+  // senders so every connected client finishes
+  stop.store(true, Ordering::SeqCst);
+
+  if let Ok(mut clients) = clients.lock() {
+    clients.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{format_code, WsPreviewServer, HEADER_LEN};
+  use crate::{
+    driver::mock::display::{MockDisplay, MockPattern},
+    FrameFormat,
+  };
+  use std::{
+    collections::HashSet,
+    net::TcpStream,
+    time::{Duration, Instant},
+  };
+  use tungstenite::Message;
+
+  fn server() -> WsPreviewServer {
+    WsPreviewServer::serve(
+      || {
+        Ok(
+          MockDisplay::new(2, 1)
+            .with_pattern(MockPattern::Solid([1, 2, 3, 4]))
+            .with_latency(Duration::from_millis(1)),
+        )
+      },
+      "127.0.0.1:0",
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn test_client_receives_frame() {
+    let server = server();
+    let addr = server.local_addr();
+    let stream = TcpStream::connect(addr).unwrap();
+    let (mut socket, _) =
+      tungstenite::client(format!("ws://{}/", addr).as_str(), stream).unwrap();
+
+    match socket.read().unwrap() {
+      Message::Binary(message) => {
+        assert_eq!(&message[..HEADER_LEN], &[2, 0, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(&message[HEADER_LEN..], &[1, 2, 3, 4, 1, 2, 3, 4]);
+      }
+      message => panic!("Expected a binary message, got {:?}", message),
+    }
+  }
+
+  #[test]
+  fn test_stalled_client_does_not_block_drop() {
+    let server = server();
+
+    // Connect without ever completing the handshake
+    let _stream = TcpStream::connect(server.local_addr()).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+
+    let start = Instant::now();
+    drop(server);
+
+    assert!(start.elapsed() < Duration::from_secs(1));
+  }
+
+  #[test]
+  fn test_format_codes_are_distinct() {
+    let formats = [
+      FrameFormat::B8G8R8A8,
+      FrameFormat::R8G8B8A8,
+      FrameFormat::B8G8R8,
+      FrameFormat::R8G8B8,
+      FrameFormat::Luma8,
+      FrameFormat::Luma16,
+      FrameFormat::R10G10B10A2,
+      FrameFormat::R16G16B16A16Float,
+    ];
+    let codes: HashSet<_> = formats.iter().map(|&format| format_code(format)).collect();
+
+    assert_eq!(codes.len(), formats.len());
+    assert_eq!(format_code(FrameFormat::Luma8), 4);
+  }
+}